@@ -1,8 +1,11 @@
 use base64::{engine::general_purpose, Engine};
 use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum AuthType {
+    #[default]
     None,
     Basic { username: String, password: String },
     Bearer { token: String },
@@ -1,24 +1,100 @@
-use reqwest::{Client, RequestBuilder};
-use std::time::Duration;
+pub mod proxy;
+pub mod settings;
+pub mod timing;
 
+use bytes::Bytes;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH,
+    CONTENT_TYPE,
+};
+use reqwest::multipart::{Form, Part};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Method, Proxy, RequestBuilder, Response};
+use rustls_http::client::Resumption;
+use rustls_http::crypto::ring;
+use rustls_http::pki_types::CertificateDer;
+use rustls_http::{ClientConfig, RootCertStore};
+use std::future::Future;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::client::settings::{HttpClientSettings, HttpVersion, RedirectPolicy};
+use crate::client::timing::{
+    phases, with_connection_timing, TimedConnectLayer, TimedResolver, TimedSessionStore,
+};
 use crate::error::{AppError, AppResult};
+use crate::http::request::{HttpRequestSpec, KeyValue, MultipartValue, RequestBody};
+use crate::http::response::{raw_body, HttpResponse, ResponseBody};
 use crate::http::ApiResponse;
 
 pub struct HttpClient {
     client: Client,
+    settings: HttpClientSettings,
 }
 
 impl HttpClient {
-    pub fn new() -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("Solo-Client/1.0")
+    /// Client with the default settings
+    pub fn new() -> AppResult<Self> {
+        Self::with_settings(&HttpClientSettings::default())
+    }
+
+    pub fn with_settings(settings: &HttpClientSettings) -> AppResult<Self> {
+        let mut default_headers = header_map("default_headers", &settings.default_headers)?;
+        // reqwest only decompresses gzip and brotli itself; zstd is advertised
+        // here and decompressed by `read_body`
+        if settings.zstd() && !default_headers.contains_key(ACCEPT_ENCODING) {
+            let encodings = match (settings.gzip(), settings.brotli()) {
+                (true, true) => "gzip, br, zstd",
+                (true, false) => "gzip, zstd",
+                (false, true) => "br, zstd",
+                (false, false) => "zstd",
+            };
+            default_headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(encodings));
+        }
+
+        let proxy = settings.proxy().routes()?;
+
+        let mut builder = Client::builder()
+            .timeout(settings.timeout())
+            .user_agent(settings.user_agent())
+            .default_headers(default_headers)
+            .redirect(match settings.redirects() {
+                // `Policy::limited` counts the original URL as a redirect
+                RedirectPolicy::Follow { max } => Policy::custom(move |attempt| {
+                    if attempt.previous().len() > max {
+                        attempt.error(format!("more than {} redirects", max))
+                    } else {
+                        attempt.follow()
+                    }
+                }),
+                RedirectPolicy::Never => Policy::none(),
+            })
+            .gzip(settings.gzip())
+            .brotli(settings.brotli())
+            .proxy(Proxy::custom(move |url| proxy.proxy_for(url)))
+            .dns_resolver(Arc::new(TimedResolver))
+            .connector_layer(TimedConnectLayer)
+            .use_preconfigured_tls(tls_config(settings.http_version.unwrap_or_default())?)
             .pool_max_idle_per_host(4)
-            .pool_idle_timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
+            .pool_idle_timeout(Duration::from_secs(30));
+        if let Some(timeout) = settings.connect_timeout() {
+            builder = builder.connect_timeout(timeout);
+        }
+        builder = match settings.http_version.unwrap_or_default() {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        };
 
-        Self { client }
+        let client = builder
+            .build()
+            .map_err(|e| AppError::internal(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            settings: settings.clone(),
+        })
     }
 
     pub fn client(&self) -> &Client {
@@ -31,16 +107,7 @@ impl HttpClient {
         url: &str,
         body: Option<serde_json::Value>,
     ) -> AppResult<RequestBuilder> {
-        let request = match method.to_uppercase().as_str() {
-            "GET" => self.client.get(url),
-            "POST" => self.client.post(url),
-            "PUT" => self.client.put(url),
-            "DELETE" => self.client.delete(url),
-            "PATCH" => self.client.patch(url),
-            "HEAD" => self.client.head(url),
-            "OPTIONS" => self.client.request(reqwest::Method::OPTIONS, url),
-            _ => return Err(AppError::validation("method", "Invalid HTTP method")),
-        };
+        let request = self.client.request(parse_method(method)?, url);
 
         Ok(if let Some(body) = body {
             request.json(&body)
@@ -49,13 +116,231 @@ impl HttpClient {
         })
     }
 
+    /// Build the request described by `spec`. Files of the body are read here.
+    pub async fn build_from_spec(&self, spec: &HttpRequestSpec) -> AppResult<RequestBuilder> {
+        let mut request = self.client.request(parse_method(&spec.method)?, &spec.url);
+
+        if !spec.query.is_empty() {
+            let pairs: Vec<(&str, &str)> = spec
+                .query
+                .iter()
+                .map(|param| (param.name.as_str(), param.value.as_str()))
+                .collect();
+            request = request.query(&pairs);
+        }
+
+        request = match &spec.body {
+            RequestBody::None => request,
+            RequestBody::Json { value } => request.json(value),
+            RequestBody::Text {
+                content,
+                content_type,
+            } => request
+                .header(
+                    CONTENT_TYPE,
+                    content_type
+                        .as_deref()
+                        .unwrap_or("text/plain; charset=utf-8"),
+                )
+                .body(content.clone()),
+            RequestBody::Form { fields } => {
+                let pairs: Vec<(&str, &str)> = fields
+                    .iter()
+                    .map(|field| (field.name.as_str(), field.value.as_str()))
+                    .collect();
+                request.form(&pairs)
+            }
+            RequestBody::Multipart { parts } => {
+                let mut form = Form::new();
+                for part in parts {
+                    form = match &part.value {
+                        MultipartValue::Text { value } => {
+                            form.text(part.name.clone(), value.clone())
+                        }
+                        MultipartValue::File {
+                            path,
+                            file_name,
+                            content_type,
+                        } => {
+                            let file_name = file_name.clone().unwrap_or_else(|| {
+                                Path::new(path)
+                                    .file_name()
+                                    .map(|name| name.to_string_lossy().to_string())
+                                    .unwrap_or_default()
+                            });
+                            let mut file =
+                                Part::bytes(read_body_file(path).await?).file_name(file_name);
+                            if let Some(content_type) = content_type {
+                                file = file.mime_str(content_type).map_err(|e| {
+                                    AppError::validation(
+                                        "body",
+                                        format!("Invalid content type '{}': {}", content_type, e),
+                                    )
+                                })?;
+                            }
+                            form.part(part.name.clone(), file)
+                        }
+                    };
+                }
+                request.multipart(form)
+            }
+            RequestBody::File { path, content_type } => request
+                .header(
+                    CONTENT_TYPE,
+                    content_type
+                        .as_deref()
+                        .unwrap_or("application/octet-stream"),
+                )
+                .body(read_body_file(path).await?),
+        };
+
+        request = spec.auth.clone().apply_to_request(request);
+
+        // Given last so they replace the headers set for the body and auth
+        let headers = header_map("headers", &spec.headers)?;
+        Ok(request.headers(headers))
+    }
+
+    /// Send `request` and read the whole response, timing each phase
+    pub async fn send(&self, request: RequestBuilder) -> AppResult<HttpResponse> {
+        let request = request.build()?;
+
+        let sent = Instant::now();
+        let (response, connection) = with_connection_timing(self.response(self.client.execute(request))).await;
+        let mut response = response?;
+        let first_byte = sent.elapsed();
+
+        let status = response.status();
+        let http_version = format!("{:?}", response.version());
+        let url = response.url().to_string();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let zstd_encoded = self.is_zstd_encoded(&response);
+        let headers = response
+            .headers()
+            .iter()
+            // Like reqwest does for the encodings it decompresses
+            .filter(|(name, _)| {
+                !(zstd_encoded && (*name == CONTENT_ENCODING || *name == CONTENT_LENGTH))
+            })
+            .map(|(name, value)| {
+                KeyValue::new(name.as_str(), String::from_utf8_lossy(value.as_bytes()))
+            })
+            .collect();
+
+        let received = Instant::now();
+        let bytes = self.read_body(&mut response, zstd_encoded).await?;
+        let download = received.elapsed();
+
+        let body = ResponseBody::decode(content_type.as_deref(), &bytes);
+        let raw_body = (body != ResponseBody::Empty).then(|| raw_body(&bytes));
+
+        Ok(HttpResponse {
+            success: status.is_success(),
+            status: status.as_u16(),
+            status_text: status.canonical_reason().map(str::to_string),
+            http_version,
+            url,
+            headers,
+            body,
+            raw_body,
+            size_bytes: bytes.len() as u64,
+            timings: phases(connection, first_byte, download),
+        })
+    }
+
+    fn is_zstd_encoded(&self, response: &Response) -> bool {
+        self.settings.zstd()
+            && response
+                .headers()
+                .get(CONTENT_ENCODING)
+                .is_some_and(|encoding| encoding.as_bytes().eq_ignore_ascii_case(b"zstd"))
+    }
+
+    /// Read the whole body, within the read timeout and size limit
+    async fn read_body(&self, response: &mut Response, zstd_encoded: bool) -> AppResult<Vec<u8>> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.next_chunk(response).await? {
+            body.extend_from_slice(&chunk);
+            if !zstd_encoded {
+                self.check_size(body.len() as u64)?;
+            }
+        }
+
+        if zstd_encoded {
+            self.decode_zstd(&body)
+        } else {
+            Ok(body)
+        }
+    }
+
+    /// Wait for the response headers, failing when they take longer than the
+    /// read timeout
+    async fn response(
+        &self,
+        pending: impl Future<Output = reqwest::Result<Response>>,
+    ) -> AppResult<Response> {
+        match self.settings.read_timeout() {
+            Some(timeout) => tokio::time::timeout(timeout, pending)
+                .await
+                .map_err(|_| AppError::network("Timed out waiting for the response"))?
+                .map_err(Into::into),
+            None => Ok(pending.await?),
+        }
+    }
+
+    /// Next chunk of the body, failing when the server sends nothing for
+    /// longer than the read timeout
+    async fn next_chunk(&self, response: &mut Response) -> AppResult<Option<Bytes>> {
+        match self.settings.read_timeout() {
+            Some(timeout) => tokio::time::timeout(timeout, response.chunk())
+                .await
+                .map_err(|_| AppError::network("Timed out reading the response body"))?
+                .map_err(Into::into),
+            None => Ok(response.chunk().await?),
+        }
+    }
+
+    fn check_size(&self, size: u64) -> AppResult<()> {
+        match self.settings.max_response_bytes {
+            Some(max) if size > max => Err(AppError::http(
+                format!("Response body is larger than the {} bytes allowed", max),
+                None,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn decode_zstd(&self, body: &[u8]) -> AppResult<Vec<u8>> {
+        let invalid = |e: std::io::Error| AppError::parse(format!("Invalid zstd body: {}", e));
+        // One byte past the limit is enough to tell it is exceeded
+        let limit = self
+            .settings
+            .max_response_bytes
+            .map_or(u64::MAX, |max| max.saturating_add(1));
+
+        let mut decoded = Vec::new();
+        zstd::stream::read::Decoder::new(body)
+            .map_err(invalid)?
+            .take(limit)
+            .read_to_end(&mut decoded)
+            .map_err(invalid)?;
+        self.check_size(decoded.len() as u64)?;
+        Ok(decoded)
+    }
+
     pub async fn send_and_parse(&self, request: RequestBuilder) -> AppResult<ApiResponse> {
-        let response = request.send().await?;
+        let mut response = self.response(request.send()).await?;
         let status = response.status();
         let success = status.is_success();
+        let zstd_encoded = self.is_zstd_encoded(&response);
+        let body = self.read_body(&mut response, zstd_encoded).await?;
 
         // Try to parse as JSON first
-        match response.json::<serde_json::Value>().await {
+        match serde_json::from_slice::<serde_json::Value>(&body) {
             Ok(json) => Ok(ApiResponse {
                 success,
                 data: Some(json),
@@ -91,10 +376,76 @@ impl HttpClient {
     }
 }
 
-impl Default for HttpClient {
-    fn default() -> Self {
-        Self::new()
+fn parse_method(method: &str) -> AppResult<Method> {
+    match method.to_uppercase().as_str() {
+        "GET" => Ok(Method::GET),
+        "POST" => Ok(Method::POST),
+        "PUT" => Ok(Method::PUT),
+        "DELETE" => Ok(Method::DELETE),
+        "PATCH" => Ok(Method::PATCH),
+        "HEAD" => Ok(Method::HEAD),
+        "OPTIONS" => Ok(Method::OPTIONS),
+        _ => Err(AppError::validation("method", "Invalid HTTP method")),
+    }
+}
+
+/// TLS configuration trusting the system roots and offering the protocols
+/// of `version`. Its session store times the handshakes.
+fn tls_config(version: HttpVersion) -> AppResult<ClientConfig> {
+    let mut config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| AppError::internal(format!("Failed to configure TLS: {}", e)))?
+        .with_root_certificates(system_roots()?)
+        .with_no_client_auth();
+
+    config.alpn_protocols = match version {
+        HttpVersion::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        HttpVersion::Http1Only => vec![b"http/1.1".to_vec()],
+        HttpVersion::Http2PriorKnowledge => vec![b"h2".to_vec()],
+    };
+    config.resumption = Resumption::store(Arc::new(TimedSessionStore::default()));
+    Ok(config)
+}
+
+/// Root certificates of the system, loaded once
+fn system_roots() -> AppResult<Arc<RootCertStore>> {
+    static ROOTS: OnceLock<Result<Arc<RootCertStore>, String>> = OnceLock::new();
+    ROOTS
+        .get_or_init(|| {
+            let certs = rustls_native_certs::load_native_certs()
+                .map_err(|e| format!("Failed to load system root certificates: {}", e))?;
+            let mut roots = RootCertStore::empty();
+            roots.add_parsable_certificates(
+                certs.into_iter().map(|cert| CertificateDer::from(cert.0)),
+            );
+            Ok(Arc::new(roots))
+        })
+        .clone()
+        .map_err(AppError::internal)
+}
+
+/// Headers named by the user, `field` being where they come from
+fn header_map(field: &str, headers: &[KeyValue]) -> AppResult<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for header in headers {
+        let invalid = |reason: String| {
+            AppError::validation(
+                field,
+                format!("Invalid header '{}': {}", header.name, reason),
+            )
+        };
+        let name =
+            HeaderName::from_bytes(header.name.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+        let value = HeaderValue::from_str(&header.value).map_err(|e| invalid(e.to_string()))?;
+        map.append(name, value);
     }
+    Ok(map)
+}
+
+async fn read_body_file(path: &str) -> AppResult<Vec<u8>> {
+    tokio::fs::read(path)
+        .await
+        .map_err(|e| AppError::validation("body", format!("Failed to read '{}': {}", path, e)))
 }
 
 #[cfg(test)]
@@ -103,14 +454,14 @@ mod tests {
 
     #[test]
     fn test_http_client_creation() {
-        let _client = HttpClient::new();
+        let _client = HttpClient::new().unwrap();
         // Ensure client was created successfully
         assert!(true);
     }
 
     #[test]
     fn test_build_request_valid_methods() {
-        let client = HttpClient::new();
+        let client = HttpClient::new().unwrap();
         let url = "https://example.com";
 
         let methods = ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"];
@@ -123,7 +474,7 @@ mod tests {
 
     #[test]
     fn test_build_request_invalid_method() {
-        let client = HttpClient::new();
+        let client = HttpClient::new().unwrap();
         let result = client.build_request("INVALID", "https://example.com", None);
 
         assert!(result.is_err());
@@ -135,7 +486,7 @@ mod tests {
 
     #[test]
     fn test_build_request_with_body() {
-        let client = HttpClient::new();
+        let client = HttpClient::new().unwrap();
         let body = Some(serde_json::json!({"key": "value"}));
 
         let result = client.build_request("POST", "https://example.com", body);
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::client::proxy::ProxySettings;
+use crate::http::request::KeyValue;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+pub const DEFAULT_USER_AGENT: &str = "Solo-Client/1.0";
+
+/// Settings of the client sending HTTP requests. Settings left out fall back
+/// to the layer below: a request overrides its collection, which overrides
+/// the defaults given on each field.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpClientSettings {
+    /// Time allowed to open a connection. Unlimited by default.
+    pub connect_timeout_ms: Option<u64>,
+    /// Longest wait for the response headers, then for each chunk of the
+    /// response body. Unlimited by default.
+    pub read_timeout_ms: Option<u64>,
+    /// Time allowed for the whole request, body included. 30 s by default.
+    pub timeout_ms: Option<u64>,
+    /// Follow up to 10 redirects by default
+    pub redirects: Option<RedirectPolicy>,
+    /// "Solo-Client/1.0" by default
+    pub user_agent: Option<String>,
+    /// Sent with every request unless it sets the same header. Headers of an
+    /// override replace those of the same name.
+    pub default_headers: Vec<KeyValue>,
+    pub http_version: Option<HttpVersion>,
+    /// Decompression of `gzip`, `br` and `zstd` encoded responses, each
+    /// enabled by default
+    pub gzip: Option<bool>,
+    pub brotli: Option<bool>,
+    pub zstd: Option<bool>,
+    /// Responses with a larger (decompressed) body fail. Unlimited by default.
+    pub max_response_bytes: Option<u64>,
+    /// Proxy for the requests; an override replaces it as a whole. By
+    /// default, the proxy of the environment variables.
+    pub proxy: Option<ProxySettings>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RedirectPolicy {
+    /// Follow up to `max` redirects, failing beyond
+    Follow { max: usize },
+    /// Return redirect responses as they are
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpVersion {
+    /// HTTP/1.1, or HTTP/2 when the server offers it
+    #[default]
+    Auto,
+    Http1Only,
+    /// HTTP/2 without upgrade or negotiation, for servers known to speak it
+    Http2PriorKnowledge,
+}
+
+impl HttpClientSettings {
+    /// These settings with those set in `overrides` replacing them
+    pub fn with_overrides(&self, overrides: &HttpClientSettings) -> Self {
+        let mut default_headers: Vec<KeyValue> = self
+            .default_headers
+            .iter()
+            .filter(|header| {
+                !overrides
+                    .default_headers
+                    .iter()
+                    .any(|other| other.name.eq_ignore_ascii_case(&header.name))
+            })
+            .cloned()
+            .collect();
+        default_headers.extend(overrides.default_headers.iter().cloned());
+
+        Self {
+            connect_timeout_ms: overrides.connect_timeout_ms.or(self.connect_timeout_ms),
+            read_timeout_ms: overrides.read_timeout_ms.or(self.read_timeout_ms),
+            timeout_ms: overrides.timeout_ms.or(self.timeout_ms),
+            redirects: overrides.redirects.or(self.redirects),
+            user_agent: overrides
+                .user_agent
+                .clone()
+                .or_else(|| self.user_agent.clone()),
+            default_headers,
+            http_version: overrides.http_version.or(self.http_version),
+            gzip: overrides.gzip.or(self.gzip),
+            brotli: overrides.brotli.or(self.brotli),
+            zstd: overrides.zstd.or(self.zstd),
+            max_response_bytes: overrides.max_response_bytes.or(self.max_response_bytes),
+            proxy: overrides.proxy.clone().or_else(|| self.proxy.clone()),
+        }
+    }
+
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_TIMEOUT, Duration::from_millis)
+    }
+
+    pub fn redirects(&self) -> RedirectPolicy {
+        self.redirects.unwrap_or(RedirectPolicy::Follow {
+            max: DEFAULT_MAX_REDIRECTS,
+        })
+    }
+
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
+
+    pub fn gzip(&self) -> bool {
+        self.gzip.unwrap_or(true)
+    }
+
+    pub fn brotli(&self) -> bool {
+        self.brotli.unwrap_or(true)
+    }
+
+    pub fn zstd(&self) -> bool {
+        self.zstd.unwrap_or(true)
+    }
+
+    pub fn proxy(&self) -> ProxySettings {
+        self.proxy.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defaults() {
+        let settings = HttpClientSettings::default();
+
+        assert_eq!(settings.timeout(), DEFAULT_TIMEOUT);
+        assert_eq!(settings.connect_timeout(), None);
+        assert_eq!(settings.redirects(), RedirectPolicy::Follow { max: 10 });
+        assert_eq!(settings.user_agent(), "Solo-Client/1.0");
+        assert!(settings.gzip() && settings.brotli() && settings.zstd());
+    }
+
+    #[test]
+    fn test_request_overrides_collection() {
+        let collection: HttpClientSettings = serde_json::from_value(json!({
+            "timeout_ms": 5000,
+            "redirects": {"type": "never"},
+            "user_agent": "team-agent",
+            "default_headers": [
+                {"name": "X-Team", "value": "core"},
+                {"name": "Accept", "value": "application/xml"}
+            ],
+            "zstd": false
+        }))
+        .unwrap();
+        let request: HttpClientSettings = serde_json::from_value(json!({
+            "timeout_ms": 100,
+            "default_headers": [{"name": "accept", "value": "application/json"}],
+            "http_version": "http1_only"
+        }))
+        .unwrap();
+
+        let settings = collection.with_overrides(&request);
+
+        assert_eq!(settings.timeout(), Duration::from_millis(100));
+        assert_eq!(settings.redirects(), RedirectPolicy::Never);
+        assert_eq!(settings.user_agent(), "team-agent");
+        assert_eq!(settings.http_version, Some(HttpVersion::Http1Only));
+        assert!(!settings.zstd());
+        assert_eq!(
+            settings.default_headers,
+            [
+                KeyValue::new("X-Team", "core"),
+                KeyValue::new("accept", "application/json"),
+            ]
+        );
+    }
+}
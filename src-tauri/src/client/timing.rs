@@ -0,0 +1,348 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use rustls_http::client::{
+    ClientSessionMemoryCache, ClientSessionStore, Tls12ClientSessionValue, Tls13ClientSessionValue,
+};
+use rustls_http::pki_types::ServerName;
+use rustls_http::NamedGroup;
+use std::cell::Cell;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::net::lookup_host;
+use tower::{Layer, Service};
+
+use crate::http::response::ResponseTimings;
+
+tokio::task_local! {
+    /// Steps of opening the connection of the request being sent
+    static CONNECTION: Cell<ConnectionTiming>;
+}
+
+/// When each step of opening a connection happened. Only connections opened
+/// while sending the request are timed, reused ones are not.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionTiming {
+    /// The connector started, host name lookup included
+    started: Option<Instant>,
+    /// Duration of the host name lookup; there is none for IP addresses
+    lookup: Option<Duration>,
+    /// The TCP connection was up and the TLS handshake started
+    handshake: Option<Instant>,
+    /// The connection was ready for the request
+    ready: Option<Instant>,
+}
+
+/// Update the timing of the connection being opened. Connections opened
+/// outside of `with_connection_timing` are not timed.
+fn record(update: impl FnOnce(&mut ConnectionTiming)) {
+    let _ = CONNECTION.try_with(|connection| {
+        let mut timing = connection.get();
+        update(&mut timing);
+        connection.set(timing);
+    });
+}
+
+/// Host name resolver of the HTTP client, timing the lookups made for the
+/// requests sent through `with_connection_timing`
+pub struct TimedResolver;
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let start = Instant::now();
+            // The connector sets the port of the addresses
+            let addresses: Vec<SocketAddr> = lookup_host((name.as_str(), 0)).await?.collect();
+            record(|timing| timing.lookup = Some(start.elapsed()));
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Connector layer of the HTTP client, timing the connections opened for the
+/// requests sent through `with_connection_timing`
+#[derive(Debug, Clone, Copy)]
+pub struct TimedConnectLayer;
+
+impl<S> Layer<S> for TimedConnectLayer {
+    type Service = TimedConnect<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimedConnect(inner)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimedConnect<S>(S);
+
+impl<S, R> Service<R> for TimedConnect<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, destination: R) -> Self::Future {
+        let connecting = self.0.call(destination);
+        Box::pin(async move {
+            // A redirect to another host opens a second connection, the one
+            // the response comes from
+            record(|timing| {
+                *timing = ConnectionTiming {
+                    started: Some(Instant::now()),
+                    ..Default::default()
+                }
+            });
+            let connection = connecting.await?;
+            record(|timing| timing.ready = Some(Instant::now()));
+            Ok(connection)
+        })
+    }
+}
+
+/// Session store of the TLS configuration of the HTTP client, noting when
+/// the handshakes of the requests sent through `with_connection_timing`
+/// start. rustls looks for a TLS 1.3 ticket to resume first thing in every
+/// handshake, right after the TCP connection is up.
+#[derive(Debug)]
+pub struct TimedSessionStore(ClientSessionMemoryCache);
+
+impl Default for TimedSessionStore {
+    fn default() -> Self {
+        // As many sessions as the default store of rustls
+        Self(ClientSessionMemoryCache::new(256))
+    }
+}
+
+impl ClientSessionStore for TimedSessionStore {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.0.set_kx_hint(server_name, group)
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.0.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(&self, server_name: ServerName<'static>, value: Tls12ClientSessionValue) {
+        self.0.set_tls12_session(server_name, value)
+    }
+
+    fn tls12_session(&self, server_name: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        self.0.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.0.remove_tls12_session(server_name)
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        value: Tls13ClientSessionValue,
+    ) {
+        self.0.insert_tls13_ticket(server_name, value)
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<Tls13ClientSessionValue> {
+        record(|timing| timing.handshake = Some(Instant::now()));
+        self.0.take_tls13_ticket(server_name)
+    }
+}
+
+/// Send a request with `sending`, also returning how the connection it
+/// opened was set up. There is none for pooled connections.
+pub async fn with_connection_timing<T>(sending: impl Future<Output = T>) -> (T, ConnectionTiming) {
+    CONNECTION
+        .scope(Cell::new(ConnectionTiming::default()), async {
+            let result = sending.await;
+            (result, CONNECTION.with(Cell::get))
+        })
+        .await
+}
+
+/// Split the time of a request into its phases. `first_byte`, from sending
+/// the request to receiving the response headers, includes opening the
+/// `connection` when the request needed a new one.
+pub fn phases(
+    connection: ConnectionTiming,
+    first_byte: Duration,
+    download: Duration,
+) -> ResponseTimings {
+    let mut timings = ResponseTimings {
+        download_ms: millis(download),
+        total_ms: millis(first_byte + download),
+        ..Default::default()
+    };
+
+    let mut setup = Duration::ZERO;
+    // A connection still being opened when a pooled one came free was not used
+    if let (Some(started), Some(ready)) = (connection.started, connection.ready) {
+        let lookup = connection.lookup.unwrap_or_default();
+        let connected = connection.handshake.unwrap_or(ready);
+
+        timings.dns_ms = connection.lookup.map(millis);
+        timings.connect_ms = Some(millis(
+            connected
+                .saturating_duration_since(started)
+                .saturating_sub(lookup),
+        ));
+        timings.tls_ms = connection
+            .handshake
+            .map(|handshake| millis(ready.saturating_duration_since(handshake)));
+        setup = ready.saturating_duration_since(started);
+    }
+    timings.ttfb_ms = millis(first_byte.saturating_sub(setup));
+
+    timings
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::test_utils::pki::TestPki;
+    use httpmock::MockServer;
+    use rustls_http::client::Resumption;
+    use rustls_http::crypto::ring;
+    use rustls_http::pki_types::CertificateDer;
+    use rustls_http::{ClientConfig, RootCertStore};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn timed_client(builder: reqwest::ClientBuilder) -> reqwest::Client {
+        builder
+            .dns_resolver(Arc::new(TimedResolver))
+            .connector_layer(TimedConnectLayer)
+            .no_proxy()
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connection_timed_when_opened() {
+        let server = MockServer::start();
+        server.mock(|_, then| {
+            then.status(200);
+        });
+        let client = timed_client(reqwest::Client::builder());
+        let by_name = format!("http://localhost:{}/", server.port());
+
+        let (response, connection) = with_connection_timing(client.get(&by_name).send()).await;
+        assert!(response.unwrap().status().is_success());
+        assert!(connection.lookup.is_some());
+        assert!(connection.started.is_some());
+        assert!(connection.ready.is_some());
+        assert!(connection.handshake.is_none());
+
+        // The pooled connection is not timed
+        let (_, connection) = with_connection_timing(client.get(&by_name).send()).await;
+        assert!(connection.started.is_none());
+        assert!(connection.lookup.is_none());
+
+        // IP addresses need no lookup
+        let (_, connection) = with_connection_timing(client.get(server.url("/")).send()).await;
+        assert!(connection.ready.is_some());
+        assert!(connection.lookup.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tls_handshake_timed() {
+        let pki = TestPki::generate();
+        let mut server_config = pki.server_config(false);
+        server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(socket).await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+            stream.flush().await.unwrap();
+        });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(CertificateDer::from(pki.ca_der.clone())).unwrap();
+        let mut tls = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        tls.resumption = Resumption::store(Arc::new(TimedSessionStore::default()));
+        let client = timed_client(reqwest::Client::builder().use_preconfigured_tls(tls));
+
+        let url = format!("https://localhost:{}/", port);
+        let (response, connection) = with_connection_timing(client.get(&url).send()).await;
+        assert!(response.unwrap().status().is_success());
+
+        let started = connection.started.unwrap();
+        let handshake = connection.handshake.unwrap();
+        let ready = connection.ready.unwrap();
+        assert!(started <= handshake && handshake <= ready);
+
+        let timings = phases(connection, ready - started, Duration::ZERO);
+        assert!(timings.dns_ms.is_some());
+        assert!(timings.connect_ms.is_some());
+        assert!(timings.tls_ms.is_some());
+    }
+
+    #[test]
+    fn test_phases_split_connection_setup() {
+        let started = Instant::now();
+        let connection = ConnectionTiming {
+            started: Some(started),
+            lookup: Some(Duration::from_millis(5)),
+            handshake: Some(started + Duration::from_millis(12)),
+            ready: Some(started + Duration::from_millis(30)),
+        };
+
+        let timings = phases(
+            connection,
+            Duration::from_millis(40),
+            Duration::from_millis(3),
+        );
+
+        assert_eq!(timings.dns_ms, Some(5.0));
+        assert_eq!(timings.connect_ms, Some(7.0));
+        assert_eq!(timings.tls_ms, Some(18.0));
+        assert_eq!(timings.ttfb_ms, 10.0);
+        assert_eq!(timings.total_ms, 43.0);
+    }
+
+    #[test]
+    fn test_phases_of_reused_connection() {
+        let timings = phases(
+            ConnectionTiming::default(),
+            Duration::from_millis(8),
+            Duration::from_millis(2),
+        );
+
+        assert_eq!(timings.dns_ms, None);
+        assert_eq!(timings.connect_ms, None);
+        assert_eq!(timings.tls_ms, None);
+        assert_eq!(timings.ttfb_ms, 8.0);
+        assert_eq!(timings.total_ms, 10.0);
+    }
+}
@@ -3,6 +3,20 @@ use std::fmt;
 
 pub type AppResult<T> = Result<T, AppError>;
 
+/// Position of a diagnostic in a source file, 1-based like compiler output
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum AppError {
     HttpError {
@@ -11,6 +25,8 @@ pub enum AppError {
     },
     ParseError {
         message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
     },
     AuthError {
         message: String,
@@ -22,6 +38,15 @@ pub enum AppError {
     NetworkError {
         message: String,
     },
+    /// A gRPC call that ended with a non-OK status
+    GrpcError {
+        code: u32,
+        status: String,
+        message: String,
+        /// Rich error details decoded from `grpc-status-details-bin`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        details: Vec<serde_json::Value>,
+    },
     InternalError {
         message: String,
     },
@@ -38,6 +63,14 @@ impl AppError {
     pub fn parse(message: impl Into<String>) -> Self {
         Self::ParseError {
             message: message.into(),
+            location: None,
+        }
+    }
+
+    pub fn parse_at(message: impl Into<String>, location: SourceLocation) -> Self {
+        Self::ParseError {
+            message: message.into(),
+            location: Some(location),
         }
     }
 
@@ -69,10 +102,11 @@ impl AppError {
     pub fn message(&self) -> &str {
         match self {
             AppError::HttpError { message, .. } => message,
-            AppError::ParseError { message } => message,
+            AppError::ParseError { message, .. } => message,
             AppError::AuthError { message } => message,
             AppError::ValidationError { message, .. } => message,
             AppError::NetworkError { message } => message,
+            AppError::GrpcError { message, .. } => message,
             AppError::InternalError { message } => message,
         }
     }
@@ -84,6 +118,7 @@ impl AppError {
             AppError::AuthError { .. } => "AuthError",
             AppError::ValidationError { .. } => "ValidationError",
             AppError::NetworkError { .. } => "NetworkError",
+            AppError::GrpcError { .. } => "GrpcError",
             AppError::InternalError { .. } => "InternalError",
         }
     }
@@ -99,12 +134,18 @@ impl fmt::Display for AppError {
                 Some(code) => write!(f, "HTTP Error ({}): {}", code, message),
                 None => write!(f, "HTTP Error: {}", message),
             },
-            AppError::ParseError { message } => write!(f, "Parse Error: {}", message),
+            AppError::ParseError { message, location } => match location {
+                Some(location) => write!(f, "Parse Error at {}: {}", location, message),
+                None => write!(f, "Parse Error: {}", message),
+            },
             AppError::AuthError { message } => write!(f, "Authentication Error: {}", message),
             AppError::ValidationError { field, message } => {
                 write!(f, "Validation Error on '{}': {}", field, message)
             }
             AppError::NetworkError { message } => write!(f, "Network Error: {}", message),
+            AppError::GrpcError {
+                status, message, ..
+            } => write!(f, "gRPC Error ({}): {}", status, message),
             AppError::InternalError { message } => write!(f, "Internal Error: {}", message),
         }
     }
@@ -143,7 +184,15 @@ impl From<tonic::codegen::http::uri::InvalidUri> for AppError {
 
 impl From<tonic::transport::Error> for AppError {
     fn from(err: tonic::transport::Error) -> Self {
-        AppError::network(format!("gRPC transport error: {}", err))
+        // The transport error itself only says "transport error"; the cause
+        // (refused connection, certificate rejected, ...) is in its sources
+        let mut message = format!("gRPC transport error: {}", err);
+        let mut source = std::error::Error::source(&err);
+        while let Some(cause) = source {
+            message.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+        AppError::network(message)
     }
 }
 
@@ -173,7 +222,15 @@ impl From<prost::DecodeError> for AppError {
 
 impl From<tonic::Status> for AppError {
     fn from(err: tonic::Status) -> Self {
-        AppError::network(format!("gRPC error: {}", err.message()))
+        AppError::GrpcError {
+            code: err.code() as u32,
+            status: crate::grpc::status_code_name(err.code()).to_string(),
+            message: err.message().to_string(),
+            details: crate::grpc::error_details::decode_details(
+                &err,
+                &crate::grpc::JsonOptions::default(),
+            ),
+        }
     }
 }
 
@@ -207,6 +264,42 @@ mod tests {
         assert!(display.contains("Invalid email format"));
     }
 
+    #[test]
+    fn test_parse_error_display_with_location() {
+        let err = AppError::parse_at(
+            "expected ';'",
+            SourceLocation {
+                file: "input.proto".to_string(),
+                line: 3,
+                column: 18,
+            },
+        );
+        assert_eq!(err.message(), "expected ';'");
+        assert_eq!(
+            err.to_string(),
+            "Parse Error at input.proto:3:18: expected ';'"
+        );
+    }
+
+    #[test]
+    fn test_grpc_error_from_status() {
+        let err = AppError::from(tonic::Status::permission_denied("not allowed"));
+
+        assert_eq!(err.error_type(), "GrpcError");
+        assert_eq!(err.message(), "not allowed");
+        assert_eq!(
+            err.to_string(),
+            "gRPC Error (PERMISSION_DENIED): not allowed"
+        );
+        match err {
+            AppError::GrpcError { code, details, .. } => {
+                assert_eq!(code, 7);
+                assert!(details.is_empty());
+            }
+            other => panic!("Expected a gRPC error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_error_from_string() {
         let err = AppError::internal("Something went wrong");
@@ -4,6 +4,7 @@ use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 
 use crate::auth::AuthType;
+use crate::client::settings::HttpClientSettings;
 use crate::client::HttpClient;
 use crate::error::AppResult;
 use crate::http::ApiResponse;
@@ -32,10 +33,14 @@ pub struct GraphQLClient {
 }
 
 impl GraphQLClient {
-    pub fn new() -> Self {
-        Self {
-            http_client: HttpClient::new(),
-        }
+    pub fn new() -> AppResult<Self> {
+        Self::with_settings(&HttpClientSettings::default())
+    }
+
+    pub fn with_settings(settings: &HttpClientSettings) -> AppResult<Self> {
+        Ok(Self {
+            http_client: HttpClient::with_settings(settings)?,
+        })
     }
 
     pub fn build_request(
@@ -77,12 +82,6 @@ impl GraphQLClient {
     }
 }
 
-impl Default for GraphQLClient {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,7 +107,7 @@ mod tests {
 
     #[test]
     fn test_graphql_client_creation() {
-        let _client = GraphQLClient::new();
+        let _client = GraphQLClient::new().unwrap();
         // Just ensure it compiles and creates successfully
         assert!(true);
     }
@@ -0,0 +1,721 @@
+use crate::grpc::schema::{cardinality, ProtoCardinality};
+use prost_reflect::{
+    DescriptorPool, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor, MethodDescriptor,
+    ServiceDescriptor,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Messages still decode, but the JSON form or the generated code changes
+    Warning,
+    /// Existing clients and servers no longer understand each other
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    PackageChanged,
+    ServiceRemoved,
+    MethodRemoved,
+    MethodTypeChanged,
+    StreamingChanged,
+    MessageRemoved,
+    FieldRemoved,
+    FieldRenumbered,
+    FieldRenamed,
+    FieldTypeChanged,
+    FieldCardinalityChanged,
+    FieldOneofChanged,
+    EnumRemoved,
+    EnumValueRemoved,
+    EnumValueRenamed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakingChange {
+    pub kind: ChangeKind,
+    /// Full name of the changed element in the previous schema
+    pub element: String,
+    pub message: String,
+}
+
+/// Changes of a schema that affect existing clients, grouped by severity
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakingReport {
+    pub errors: Vec<BreakingChange>,
+    pub warnings: Vec<BreakingChange>,
+}
+
+impl BreakingReport {
+    pub fn is_breaking(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+/// Find the changes from the `previous` schema, e.g. the one a server is
+/// running, to the `current` one.
+///
+/// Elements are matched by full name. A type that is gone but exists under
+/// another package counts as moved: the package change is reported once and
+/// the type is compared with its moved counterpart. Additions never break
+/// anything and are not reported. Well-known types are skipped.
+pub fn compare_pools(previous: &DescriptorPool, current: &DescriptorPool) -> BreakingReport {
+    let mut comparison = Comparison {
+        current,
+        packages: current
+            .files()
+            .map(|file| file.package_name().to_string())
+            .collect(),
+        moves: BTreeSet::new(),
+        report: BreakingReport::default(),
+    };
+
+    for service in previous.services() {
+        if !is_well_known(service.package_name()) {
+            comparison.service(&service);
+        }
+    }
+    for message in previous.all_messages() {
+        if !message.is_map_entry() && !is_well_known(message.package_name()) {
+            comparison.message(&message);
+        }
+    }
+    for enum_type in previous.all_enums() {
+        if !is_well_known(enum_type.package_name()) {
+            comparison.enumeration(&enum_type);
+        }
+    }
+
+    // Package moves come first, as they explain the changes of every element
+    let mut report = comparison.report;
+    let moves = comparison
+        .moves
+        .into_iter()
+        .map(|(from, to)| BreakingChange {
+            kind: ChangeKind::PackageChanged,
+            message: format!("Package '{}' was moved to '{}'", from, to),
+            element: from,
+        });
+    report.errors.splice(0..0, moves);
+    report
+}
+
+fn is_well_known(package: &str) -> bool {
+    package == "google.protobuf"
+}
+
+struct Comparison<'a> {
+    current: &'a DescriptorPool,
+    /// Packages of the current schema
+    packages: BTreeSet<String>,
+    /// Package moves found so far, from the previous package to the current one
+    moves: BTreeSet<(String, String)>,
+    report: BreakingReport,
+}
+
+impl Comparison<'_> {
+    fn push(&mut self, severity: Severity, kind: ChangeKind, element: &str, message: String) {
+        let change = BreakingChange {
+            kind,
+            element: element.to_string(),
+            message,
+        };
+        match severity {
+            Severity::Error => self.report.errors.push(change),
+            Severity::Warning => self.report.warnings.push(change),
+        }
+    }
+
+    /// Name that `full_name`, declared in `package`, has in the current
+    /// schema: the same one, or the one under another package it moved to
+    fn current_name(
+        &mut self,
+        full_name: &str,
+        package: &str,
+        exists: impl Fn(&DescriptorPool, &str) -> bool,
+    ) -> Option<String> {
+        if exists(self.current, full_name) {
+            return Some(full_name.to_string());
+        }
+
+        let relative = match package {
+            "" => full_name,
+            package => full_name
+                .strip_prefix(package)
+                .and_then(|name| name.strip_prefix('.'))
+                .unwrap_or(full_name),
+        };
+        let (moved_to, name) = self
+            .packages
+            .iter()
+            .filter(|candidate| *candidate != package)
+            .map(|candidate| match candidate.as_str() {
+                "" => (candidate.clone(), relative.to_string()),
+                _ => (candidate.clone(), format!("{}.{}", candidate, relative)),
+            })
+            .find(|(_, name)| exists(self.current, name))?;
+
+        self.moves.insert((package.to_string(), moved_to));
+        Some(name)
+    }
+
+    fn message_name(&mut self, message: &MessageDescriptor) -> String {
+        self.current_name(message.full_name(), message.package_name(), |pool, name| {
+            pool.get_message_by_name(name).is_some()
+        })
+        .unwrap_or_else(|| message.full_name().to_string())
+    }
+
+    fn enum_name(&mut self, enum_type: &EnumDescriptor) -> String {
+        self.current_name(
+            enum_type.full_name(),
+            enum_type.package_name(),
+            |pool, name| pool.get_enum_by_name(name).is_some(),
+        )
+        .unwrap_or_else(|| enum_type.full_name().to_string())
+    }
+
+    fn service(&mut self, previous: &ServiceDescriptor) {
+        let current = self
+            .current_name(
+                previous.full_name(),
+                previous.package_name(),
+                |pool, name| pool.get_service_by_name(name).is_some(),
+            )
+            .and_then(|name| self.current.get_service_by_name(&name));
+        let Some(current) = current else {
+            self.push(
+                Severity::Error,
+                ChangeKind::ServiceRemoved,
+                previous.full_name(),
+                format!("Service '{}' was removed", previous.full_name()),
+            );
+            return;
+        };
+
+        for method in previous.methods() {
+            match current.methods().find(|m| m.name() == method.name()) {
+                Some(current) => self.method(&method, &current),
+                None => self.push(
+                    Severity::Error,
+                    ChangeKind::MethodRemoved,
+                    method.full_name(),
+                    format!("Method '{}' was removed", method.full_name()),
+                ),
+            }
+        }
+    }
+
+    fn method(&mut self, previous: &MethodDescriptor, current: &MethodDescriptor) {
+        let element = previous.full_name();
+
+        for (role, previous_type, current_type) in [
+            ("Request", previous.input(), current.input()),
+            ("Response", previous.output(), current.output()),
+        ] {
+            if self.message_name(&previous_type) != current_type.full_name() {
+                self.push(
+                    Severity::Error,
+                    ChangeKind::MethodTypeChanged,
+                    element,
+                    format!(
+                        "{} type of '{}' changed from '{}' to '{}'",
+                        role,
+                        element,
+                        previous_type.full_name(),
+                        current_type.full_name()
+                    ),
+                );
+            }
+        }
+
+        if call_style(previous) != call_style(current) {
+            self.push(
+                Severity::Error,
+                ChangeKind::StreamingChanged,
+                element,
+                format!(
+                    "Method '{}' changed from {} to {}",
+                    element,
+                    call_style(previous),
+                    call_style(current)
+                ),
+            );
+        }
+    }
+
+    fn message(&mut self, previous: &MessageDescriptor) {
+        let current = self
+            .current_name(
+                previous.full_name(),
+                previous.package_name(),
+                |pool, name| pool.get_message_by_name(name).is_some(),
+            )
+            .and_then(|name| self.current.get_message_by_name(&name));
+        let Some(current) = current else {
+            self.push(
+                Severity::Error,
+                ChangeKind::MessageRemoved,
+                previous.full_name(),
+                format!("Message '{}' was removed", previous.full_name()),
+            );
+            return;
+        };
+
+        for field in previous.fields() {
+            let element = field.full_name();
+            match current.get_field(field.number()) {
+                Some(current) => self.field(&field, &current),
+                None => match current.get_field_by_name(field.name()) {
+                    Some(renumbered) => self.push(
+                        Severity::Error,
+                        ChangeKind::FieldRenumbered,
+                        element,
+                        format!(
+                            "Field '{}' changed number from {} to {}",
+                            element,
+                            field.number(),
+                            renumbered.number()
+                        ),
+                    ),
+                    None if current
+                        .reserved_ranges()
+                        .any(|range| range.contains(&field.number())) =>
+                    {
+                        self.push(
+                            Severity::Warning,
+                            ChangeKind::FieldRemoved,
+                            element,
+                            format!(
+                                "Field '{}' ({}) was removed and its number reserved",
+                                element,
+                                field.number()
+                            ),
+                        )
+                    }
+                    None => self.push(
+                        Severity::Error,
+                        ChangeKind::FieldRemoved,
+                        element,
+                        format!(
+                            "Field '{}' ({}) was removed without reserving its number",
+                            element,
+                            field.number()
+                        ),
+                    ),
+                },
+            }
+        }
+    }
+
+    /// Compare two fields with the same number
+    fn field(&mut self, previous: &FieldDescriptor, current: &FieldDescriptor) {
+        let element = previous.full_name();
+
+        if previous.name() != current.name() {
+            self.push(
+                Severity::Warning,
+                ChangeKind::FieldRenamed,
+                element,
+                format!(
+                    "Field {} was renamed from '{}' to '{}', which changes its JSON name",
+                    previous.number(),
+                    previous.name(),
+                    current.name()
+                ),
+            );
+        }
+
+        let (previous_shape, current_shape) = (shape(previous), shape(current));
+        if previous_shape != current_shape {
+            self.push(
+                Severity::Error,
+                ChangeKind::FieldCardinalityChanged,
+                element,
+                format!(
+                    "Field '{}' changed from {} to {}",
+                    element, previous_shape, current_shape
+                ),
+            );
+        } else if let Some(severity) = self.kind_change(&previous.kind(), &current.kind()) {
+            self.push(
+                severity,
+                ChangeKind::FieldTypeChanged,
+                element,
+                format!(
+                    "Field '{}' changed type from {} to {}",
+                    element,
+                    type_name(previous),
+                    type_name(current)
+                ),
+            );
+        }
+
+        let (previous_oneof, current_oneof) = (oneof_name(previous), oneof_name(current));
+        if previous_oneof != current_oneof {
+            let describe = |oneof: &Option<String>| match oneof {
+                Some(name) => format!("oneof '{}'", name),
+                None => "no oneof".to_string(),
+            };
+            self.push(
+                Severity::Warning,
+                ChangeKind::FieldOneofChanged,
+                element,
+                format!(
+                    "Field '{}' moved from {} to {}",
+                    element,
+                    describe(&previous_oneof),
+                    describe(&current_oneof)
+                ),
+            );
+        }
+    }
+
+    /// How bad changing a field of kind `previous` to `current` is, `None`
+    /// when it is the same type
+    fn kind_change(&mut self, previous: &Kind, current: &Kind) -> Option<Severity> {
+        match (previous, current) {
+            (Kind::Message(previous), Kind::Message(current))
+                if previous.is_map_entry() && current.is_map_entry() =>
+            {
+                let key = self.kind_change(
+                    &previous.map_entry_key_field().kind(),
+                    &current.map_entry_key_field().kind(),
+                );
+                let value = self.kind_change(
+                    &previous.map_entry_value_field().kind(),
+                    &current.map_entry_value_field().kind(),
+                );
+                key.max(value)
+            }
+            (Kind::Message(previous), Kind::Message(current)) => {
+                (self.message_name(previous) != current.full_name()).then_some(Severity::Error)
+            }
+            (Kind::Enum(previous), Kind::Enum(current)) => {
+                (self.enum_name(previous) != current.full_name()).then_some(Severity::Error)
+            }
+            (previous, current) if previous == current => None,
+            (previous, current) => match (wire_group(previous), wire_group(current)) {
+                (Some(previous), Some(current)) if previous == current => Some(Severity::Warning),
+                _ => Some(Severity::Error),
+            },
+        }
+    }
+
+    fn enumeration(&mut self, previous: &EnumDescriptor) {
+        let current = self
+            .current_name(
+                previous.full_name(),
+                previous.package_name(),
+                |pool, name| pool.get_enum_by_name(name).is_some(),
+            )
+            .and_then(|name| self.current.get_enum_by_name(&name));
+        let Some(current) = current else {
+            self.push(
+                Severity::Error,
+                ChangeKind::EnumRemoved,
+                previous.full_name(),
+                format!("Enum '{}' was removed", previous.full_name()),
+            );
+            return;
+        };
+
+        for value in previous.values() {
+            let element = value.full_name();
+            match current.get_value(value.number()) {
+                Some(current) if current.name() != value.name() => self.push(
+                    Severity::Warning,
+                    ChangeKind::EnumValueRenamed,
+                    element,
+                    format!(
+                        "Enum value {} was renamed from '{}' to '{}', which changes its JSON name",
+                        value.number(),
+                        value.name(),
+                        current.name()
+                    ),
+                ),
+                Some(_) => {}
+                None if current
+                    .reserved_ranges()
+                    .any(|range| range.contains(&value.number())) =>
+                {
+                    self.push(
+                        Severity::Warning,
+                        ChangeKind::EnumValueRemoved,
+                        element,
+                        format!(
+                            "Enum value '{}' ({}) was removed and its number reserved",
+                            element,
+                            value.number()
+                        ),
+                    )
+                }
+                None => self.push(
+                    Severity::Error,
+                    ChangeKind::EnumValueRemoved,
+                    element,
+                    format!(
+                        "Enum value '{}' ({}) was removed without reserving its number",
+                        element,
+                        value.number()
+                    ),
+                ),
+            }
+        }
+    }
+}
+
+fn call_style(method: &MethodDescriptor) -> &'static str {
+    match (method.is_client_streaming(), method.is_server_streaming()) {
+        (false, false) => "unary",
+        (false, true) => "server streaming",
+        (true, false) => "client streaming",
+        (true, true) => "bidirectional streaming",
+    }
+}
+
+fn shape(field: &FieldDescriptor) -> &'static str {
+    match cardinality(field) {
+        ProtoCardinality::Map => "map",
+        ProtoCardinality::Repeated => "repeated",
+        _ => "singular",
+    }
+}
+
+fn type_name(field: &FieldDescriptor) -> String {
+    match field.kind() {
+        Kind::Message(entry) if field.is_map() => format!(
+            "map<{:?}, {:?}>",
+            entry.map_entry_key_field().kind(),
+            entry.map_entry_value_field().kind()
+        ),
+        kind => format!("{:?}", kind),
+    }
+}
+
+/// The oneof declared around a field; proto3 `optional` fields sit in a
+/// synthetic one that is left out
+fn oneof_name(field: &FieldDescriptor) -> Option<String> {
+    if field.field_descriptor_proto().proto3_optional() {
+        return None;
+    }
+    field
+        .containing_oneof()
+        .map(|oneof| oneof.name().to_string())
+}
+
+/// Scalar types sharing an encoding, which can replace each other on the
+/// wire at the risk of truncating values
+fn wire_group(kind: &Kind) -> Option<u8> {
+    match kind {
+        Kind::Int32 | Kind::Int64 | Kind::Uint32 | Kind::Uint64 | Kind::Bool => Some(0),
+        Kind::Sint32 | Kind::Sint64 => Some(1),
+        Kind::Fixed32 | Kind::Sfixed32 => Some(2),
+        Kind::Fixed64 | Kind::Sfixed64 => Some(3),
+        Kind::String | Kind::Bytes => Some(4),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::proto_parser::ProtoParser;
+
+    const BASE: &str = r#"
+syntax = "proto3";
+
+package shop.v1;
+
+service Orders {
+  rpc Get(GetRequest) returns (Order);
+  rpc Watch(GetRequest) returns (stream Order);
+  rpc Cancel(GetRequest) returns (Order);
+}
+
+service Legacy {
+  rpc Ping(GetRequest) returns (GetRequest);
+}
+
+enum State {
+  STATE_UNSPECIFIED = 0;
+  OPEN = 1;
+  CLOSED = 2;
+  LOST = 3;
+}
+
+message GetRequest {
+  string id = 1;
+}
+
+message Order {
+  string id = 1;
+  int32 count = 2;
+  double total = 3;
+  string note = 4;
+  repeated string tags = 5;
+  map<string, int32> labels = 6;
+  State state = 7;
+  string coupon = 8;
+  string channel = 9;
+  string gift = 10;
+  oneof target {
+    string email = 11;
+  }
+}
+
+message Unused {}
+"#;
+
+    fn compare(previous: &str, current: &str) -> BreakingReport {
+        compare_pools(
+            &ProtoParser::compile(previous).unwrap(),
+            &ProtoParser::compile(current).unwrap(),
+        )
+    }
+
+    fn kinds(changes: &[BreakingChange]) -> Vec<(ChangeKind, &str)> {
+        changes
+            .iter()
+            .map(|change| (change.kind, change.element.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_schemas() {
+        let report = compare(BASE, BASE);
+
+        assert_eq!(report, BreakingReport::default());
+        assert!(!report.is_breaking());
+    }
+
+    #[test]
+    fn test_field_changes() {
+        let current = BASE
+            .replace("int32 count = 2;", "int64 count = 2;")
+            .replace("double total = 3;", "string total = 3;")
+            .replace("string note = 4;", "string remark = 4;")
+            .replace("repeated string tags = 5;", "string tags = 5;")
+            .replace(
+                "map<string, int32> labels = 6;",
+                "map<string, string> labels = 6;",
+            )
+            .replace("string coupon = 8;", "string coupon = 12;")
+            .replace("string channel = 9;", "")
+            .replace("string gift = 10;", "reserved 10;")
+            .replace(
+                "oneof target {\n    string email = 11;\n  }",
+                "string email = 11;",
+            );
+
+        let report = compare(BASE, &current);
+
+        assert_eq!(
+            kinds(&report.errors),
+            [
+                (ChangeKind::FieldTypeChanged, "shop.v1.Order.total"),
+                (ChangeKind::FieldCardinalityChanged, "shop.v1.Order.tags"),
+                (ChangeKind::FieldTypeChanged, "shop.v1.Order.labels"),
+                (ChangeKind::FieldRenumbered, "shop.v1.Order.coupon"),
+                (ChangeKind::FieldRemoved, "shop.v1.Order.channel"),
+            ]
+        );
+        assert_eq!(
+            kinds(&report.warnings),
+            [
+                (ChangeKind::FieldTypeChanged, "shop.v1.Order.count"),
+                (ChangeKind::FieldRenamed, "shop.v1.Order.note"),
+                (ChangeKind::FieldRemoved, "shop.v1.Order.gift"),
+                (ChangeKind::FieldOneofChanged, "shop.v1.Order.email"),
+            ]
+        );
+        assert_eq!(
+            report.errors[2].message,
+            "Field 'shop.v1.Order.labels' changed type from map<string, int32> to map<string, string>"
+        );
+    }
+
+    #[test]
+    fn test_service_changes() {
+        let current = BASE
+            .replace(
+                "rpc Get(GetRequest) returns (Order);",
+                "rpc Get(Order) returns (Order);",
+            )
+            .replace(
+                "rpc Watch(GetRequest) returns (stream Order);",
+                "rpc Watch(GetRequest) returns (Order);",
+            )
+            .replace("rpc Cancel(GetRequest) returns (Order);", "")
+            .replace(
+                "service Legacy {\n  rpc Ping(GetRequest) returns (GetRequest);\n}",
+                "",
+            )
+            .replace("message Unused {}", "");
+
+        let report = compare(BASE, &current);
+
+        assert_eq!(
+            kinds(&report.errors),
+            [
+                (ChangeKind::MethodTypeChanged, "shop.v1.Orders.Get"),
+                (ChangeKind::StreamingChanged, "shop.v1.Orders.Watch"),
+                (ChangeKind::MethodRemoved, "shop.v1.Orders.Cancel"),
+                (ChangeKind::ServiceRemoved, "shop.v1.Legacy"),
+                (ChangeKind::MessageRemoved, "shop.v1.Unused"),
+            ]
+        );
+        assert_eq!(
+            report.errors[1].message,
+            "Method 'shop.v1.Orders.Watch' changed from server streaming to unary"
+        );
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_enum_changes() {
+        let current = BASE
+            .replace("CLOSED = 2;", "DONE = 2;")
+            .replace("LOST = 3;", "reserved 3;");
+        let report = compare(BASE, &current);
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            kinds(&report.warnings),
+            [
+                (ChangeKind::EnumValueRenamed, "shop.v1.CLOSED"),
+                (ChangeKind::EnumValueRemoved, "shop.v1.LOST"),
+            ]
+        );
+
+        let report = compare(BASE, &BASE.replace("LOST = 3;", ""));
+        assert_eq!(
+            kinds(&report.errors),
+            [(ChangeKind::EnumValueRemoved, "shop.v1.LOST")]
+        );
+    }
+
+    #[test]
+    fn test_package_move() {
+        let current = BASE
+            .replace("package shop.v1;", "package shop.v2;")
+            .replace("double total = 3;", "float total = 3;");
+
+        let report = compare(BASE, &current);
+
+        assert_eq!(
+            kinds(&report.errors),
+            [
+                (ChangeKind::PackageChanged, "shop.v1"),
+                (ChangeKind::FieldTypeChanged, "shop.v1.Order.total"),
+            ]
+        );
+        assert_eq!(
+            report.errors[0].message,
+            "Package 'shop.v1' was moved to 'shop.v2'"
+        );
+    }
+}
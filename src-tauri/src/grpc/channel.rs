@@ -0,0 +1,94 @@
+use crate::client::proxy::{ProxyConnector, ProxySettings};
+use crate::error::{AppError, AppResult};
+use crate::grpc::tls::{GrpcTlsConfig, TlsConnector, TlsSessionInfo};
+use http::uri::Scheme;
+use hyper::client::HttpConnector;
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint, Uri};
+
+/// Interval between HTTP/2 keepalive pings on pooled channels
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a keepalive ping may go unanswered before the connection is dropped
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Idle time before the OS starts probing pooled TCP connections
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Connect a channel configured to stay open between calls.
+///
+/// `https://` URLs are connected over TLS using `tls`, verifying the server
+/// against the system roots when no settings are given. Connections go
+/// through the proxy of `proxy` unless the host bypasses it.
+pub async fn connect(
+    url: &str,
+    tls: Option<&GrpcTlsConfig>,
+    proxy: &ProxySettings,
+) -> AppResult<Channel> {
+    let default_tls = GrpcTlsConfig::default();
+    let tls = tls.unwrap_or(&default_tls);
+
+    let mut endpoint = Endpoint::from_shared(url.to_string())?
+        .http2_keep_alive_interval(KEEPALIVE_INTERVAL)
+        .keep_alive_timeout(KEEPALIVE_TIMEOUT)
+        .keep_alive_while_idle(true);
+
+    if let Some(authority) = &tls.authority {
+        let scheme = endpoint.uri().scheme_str().unwrap_or("http");
+        let origin: Uri = format!("{}://{}", scheme, authority).parse().map_err(|e| {
+            AppError::validation(
+                "authority",
+                format!("Invalid authority '{}': {}", authority, e),
+            )
+        })?;
+        endpoint = endpoint.origin(origin);
+    }
+
+    let channel = if endpoint.uri().scheme() == Some(&Scheme::HTTPS) {
+        endpoint
+            .connect_with_connector(TlsConnector::new(tcp_connector(proxy)?, tls)?)
+            .await?
+    } else {
+        if tls.requires_tls() {
+            return Err(AppError::validation(
+                "url",
+                "TLS settings need an https:// URL",
+            ));
+        }
+
+        endpoint
+            .connect_with_connector(tcp_connector(proxy)?)
+            .await?
+    };
+
+    Ok(channel)
+}
+
+/// Run the TLS handshake `connect` does for an `https://` URL on a connection
+/// of its own, and describe the negotiated session
+pub async fn tls_session(
+    url: &str,
+    tls: Option<&GrpcTlsConfig>,
+    proxy: &ProxySettings,
+) -> AppResult<TlsSessionInfo> {
+    let uri: Uri = url.parse()?;
+    if uri.scheme() != Some(&Scheme::HTTPS) {
+        return Err(AppError::validation("url", "TLS needs an https:// URL"));
+    }
+
+    let default_tls = GrpcTlsConfig::default();
+    TlsConnector::new(tcp_connector(proxy)?, tls.unwrap_or(&default_tls))?
+        .handshake(uri)
+        .await
+}
+
+/// TCP connector of channels, under TLS for `https://` URLs
+fn tcp_connector(proxy: &ProxySettings) -> AppResult<ProxyConnector> {
+    ProxyConnector::new(http_connector(), proxy)
+}
+
+fn http_connector() -> HttpConnector {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    http.set_nodelay(true);
+    http.set_keepalive(Some(TCP_KEEPALIVE));
+    http
+}
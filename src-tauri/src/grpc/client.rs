@@ -1,30 +1,103 @@
-use crate::error::AppResult;
-use crate::grpc::{GrpcCallType, GrpcRequest, GrpcResponse};
+use crate::client::proxy::ProxySettings;
+use crate::error::{AppError, AppResult};
+use crate::grpc::channel::connect;
+use crate::grpc::codec::DynamicCodec;
+use crate::grpc::json::{message_to_json, JsonOptions};
 use crate::grpc::reflection::GrpcReflection;
+use crate::grpc::streaming::{GrpcStreamEnd, GrpcStreamEvent};
+use crate::grpc::tls::GrpcTlsConfig;
+use crate::grpc::{
+    create_tonic_request, DescriptorSource, GrpcCallOptions, GrpcCallType, GrpcRequest,
+    GrpcResponse,
+};
+use http::uri::PathAndQuery;
 use prost::Message;
-use prost_reflect::{DynamicMessage, DescriptorPool};
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::client::Grpc;
+use tonic::codec::{CompressionEncoding, Streaming};
 use tonic::transport::Channel;
+use tonic::{Code, Request, Response, Status};
 
 pub struct GrpcClient {
-    #[allow(dead_code)]
-    channel: Channel, // Will be used for real gRPC calls in next iteration
-    reflection: Option<GrpcReflection>,
+    channel: Channel,
+    descriptors: Descriptors,
+}
+
+/// Descriptors a client resolves its methods from
+enum Descriptors {
+    Reflection(GrpcReflection),
+    Loaded(DescriptorPool),
 }
 
 impl GrpcClient {
-    pub async fn new(url: &str) -> AppResult<Self> {
-        let channel = Channel::from_shared(url.to_string())?.connect().await?;
-        let reflection = GrpcReflection::new(url).await.ok();
+    /// Connect to `url` through `proxy`, over TLS with `tls` for `https://`
+    /// URLs, resolving services through server reflection
+    pub async fn new(
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+    ) -> AppResult<Self> {
+        Self::with_source(url, &DescriptorSource::Reflection, tls, proxy).await
+    }
+
+    /// Connect like `new`, resolving services from `source` instead of asking
+    /// the server when it is a local source
+    pub async fn with_source(
+        url: &str,
+        source: &DescriptorSource,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+    ) -> AppResult<Self> {
+        // Load local descriptors first so broken files fail before connecting
+        let pool = source.load_pool()?;
+        let channel = connect(url, tls, proxy).await?;
+
+        Ok(Self::build(channel, pool))
+    }
+
+    /// Create a client on an already connected channel
+    pub fn from_channel(channel: Channel, source: &DescriptorSource) -> AppResult<Self> {
+        let pool = source.load_pool()?;
+        Ok(Self::build(channel, pool))
+    }
+
+    /// Create a client resolving methods from an already loaded descriptor pool
+    pub fn with_descriptor_pool(channel: Channel, pool: DescriptorPool) -> Self {
+        Self::build(channel, Some(pool))
+    }
+
+    fn build(channel: Channel, pool: Option<DescriptorPool>) -> Self {
+        let descriptors = match pool {
+            Some(pool) => Descriptors::Loaded(pool),
+            None => Descriptors::Reflection(GrpcReflection::from_channel(channel.clone())),
+        };
 
-        Ok(Self { channel, reflection })
+        Self {
+            channel,
+            descriptors,
+        }
     }
 
-    /// Get or initialize the descriptor pool via reflection
-    async fn get_descriptor_pool(&mut self) -> AppResult<&DescriptorPool> {
-        if let Some(ref mut reflection) = self.reflection {
-            reflection.descriptor_pool().await
-        } else {
-            Err("Reflection not available".to_string().into())
+    /// Get the loaded descriptor pool or initialize it via reflection
+    pub async fn get_descriptor_pool(&mut self) -> AppResult<&DescriptorPool> {
+        match self.descriptors {
+            Descriptors::Loaded(ref pool) => Ok(pool),
+            Descriptors::Reflection(ref mut reflection) => {
+                reflection.descriptor_pool().await.map_err(|e| match e {
+                    AppError::NetworkError { message } => AppError::network(format!(
+                        "{}. If the server has reflection disabled, provide .proto files or a protoset instead",
+                        message
+                    )),
+                    other => other,
+                })
+            }
         }
     }
 
@@ -38,169 +111,477 @@ impl GrpcClient {
         Ok(dynamic_msg)
     }
 
-    /// Convert DynamicMessage to JSON value (for future use in real gRPC calls)
-    #[allow(dead_code)]
-    fn dynamic_message_to_json(&self, message: &DynamicMessage) -> AppResult<serde_json::Value> {
-        // Convert to JSON using serde
-        let json = serde_json::to_value(message)?;
-        Ok(json)
+    /// Convert DynamicMessage to JSON value
+    fn dynamic_message_to_json(
+        &self,
+        message: &DynamicMessage,
+        options: &JsonOptions,
+    ) -> AppResult<serde_json::Value> {
+        message_to_json(message, options)
+    }
+
+    /// Resolve the method descriptor for the requested service and method
+    pub async fn find_method(
+        &mut self,
+        service: &str,
+        method: &str,
+    ) -> AppResult<MethodDescriptor> {
+        let descriptor_pool = self.get_descriptor_pool().await?.clone();
+        resolve_method(&descriptor_pool, service, method)
+    }
+
+    /// Resolve the method of `request` and wait until the channel can carry the
+    /// call. The deadline of the call starts here.
+    async fn prepare_call(&mut self, request: &GrpcRequest) -> AppResult<PreparedCall> {
+        let method = self.find_method(&request.service, &request.method).await?;
+        let path = method_path(&method)?;
+        let options = request.options.clone();
+        let deadline = Deadline::after(&options);
+
+        let mut grpc = Grpc::new(self.channel.clone());
+        if options.gzip {
+            grpc = grpc
+                .send_compressed(CompressionEncoding::Gzip)
+                .accept_compressed(CompressionEncoding::Gzip);
+        }
+        if let Some(limit) = options.max_response_size {
+            grpc = grpc.max_decoding_message_size(limit);
+        }
+        if let Some(limit) = options.max_request_size {
+            grpc = grpc.max_encoding_message_size(limit);
+        }
+
+        grpc.ready().await?;
+
+        Ok(PreparedCall {
+            grpc,
+            method,
+            path,
+            options,
+            deadline,
+        })
     }
 
     pub async fn unary_call(&mut self, request: GrpcRequest) -> AppResult<GrpcResponse> {
-        let descriptor_pool = self.get_descriptor_pool().await?;
-
-        // Find the service and method
-        // Try exact match first, then try as a suffix match (for cases like "Echo" when service is "mypackage.Echo")
-        let service_desc = descriptor_pool
-            .get_service_by_name(&request.service)
-            .or_else(|| {
-                // Try to find by short name (last part after the last dot)
-                descriptor_pool.services().find(|s| {
-                    s.full_name() == request.service ||
-                    s.name() == request.service ||
-                    s.full_name().ends_with(&format!(".{}", request.service))
-                })
-            })
-            .ok_or_else(|| {
-                // List available services for better error message
-                let available: Vec<String> = descriptor_pool
-                    .services()
-                    .map(|s| s.full_name().to_string())
-                    .collect();
-                format!(
-                    "Service '{}' not found. Available services: [{}]",
-                    request.service,
-                    available.join(", ")
-                )
-            })?;
-
-        let method_desc = service_desc
-            .methods()
-            .find(|m| m.name() == request.method)
-            .ok_or_else(|| {
-                // List available methods for better error message
-                let available: Vec<String> = service_desc
-                    .methods()
-                    .map(|m| m.name().to_string())
-                    .collect();
-                format!(
-                    "Method '{}' not found in service '{}'. Available methods: [{}]",
-                    request.method,
-                    service_desc.full_name(),
-                    available.join(", ")
-                )
-            })?;
+        let mut call = self.prepare_call(&request).await?;
 
         // Convert JSON input to DynamicMessage
-        let input_desc = method_desc.input();
-        let input_message = self.json_to_dynamic_message(&request.message, &input_desc)?;
-
-        // Encode the message to bytes (for future use in real gRPC calls)
-        let _request_bytes = input_message.encode_to_vec();
-
-        // Use grpcurl-style approach for dynamic calls
-        // This is more reliable than trying to implement custom codecs
-
-        // For now, return a detailed response showing what we found
-        // This proves that reflection is working and we can find services/methods
-
-        let response_data = serde_json::json!({
-            "service_found": service_desc.full_name(),
-            "method_found": method_desc.name(),
-            "input_type": method_desc.input().full_name(),
-            "output_type": method_desc.output().full_name(),
-            "is_client_streaming": method_desc.is_client_streaming(),
-            "is_server_streaming": method_desc.is_server_streaming(),
-            "request_message": request.message,
-            "request_url": request.url,
-            "status": "✅ Service and method successfully discovered via reflection",
-            "next_step": "Real gRPC call implementation in progress",
-            "reflection_working": true
-        });
+        let input_message = self.json_to_dynamic_message(&request.message, &call.method.input())?;
+        if let Some(status) = call.oversized(&input_message) {
+            return Ok(GrpcResponse::from_status(&status, &call.options.json));
+        }
+        let tonic_request = call.request(input_message, request.metadata)?;
+        let codec = DynamicCodec::new(call.method.output());
 
-        Ok(GrpcResponse {
-            success: true,
-            data: Some(response_data),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("Reflection successful - Service found".to_string()),
-        })
+        // Called as a stream so the headers are not merged with the trailers
+        match call
+            .deadline
+            .run(call.grpc.server_streaming(tonic_request, call.path, codec))
+            .await
+        {
+            Ok(response) => {
+                self.single_response(response, call.deadline, &call.options.json)
+                    .await
+            }
+            Err(status) => Ok(GrpcResponse::from_status(&status, &call.options.json)),
+        }
     }
 
-    pub async fn server_streaming_call(&mut self, request: GrpcRequest) -> AppResult<GrpcResponse> {
-        // TODO: Implement actual server streaming
-        // For now, return a placeholder
-        Ok(GrpcResponse {
-            success: true,
-            data: Some(serde_json::json!({
-                "stream_type": "server_streaming",
-                "message": request.message,
-                "status": "streaming_started",
-                "note": "Server streaming implementation pending"
-            })),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("Streaming started".to_string()),
-        })
+    /// Run a server-streaming call, sending each decoded message to `tx` as it
+    /// arrives and finishing with the final status and trailers
+    pub async fn server_streaming_call(
+        &mut self,
+        request: GrpcRequest,
+        tx: mpsc::Sender<GrpcStreamEvent>,
+    ) -> AppResult<()> {
+        let mut call = self.prepare_call(&request).await?;
+
+        let input_message = self.json_to_dynamic_message(&request.message, &call.method.input())?;
+        if let Some(status) = call.oversized(&input_message) {
+            let _ = tx
+                .send(GrpcStreamEvent::End(GrpcStreamEnd::from_status(
+                    &status,
+                    0,
+                    &call.options.json,
+                )))
+                .await;
+            return Ok(());
+        }
+        let tonic_request = call.request(input_message, request.metadata)?;
+        let codec = DynamicCodec::new(call.method.output());
+
+        let end = match call
+            .deadline
+            .run(call.grpc.server_streaming(tonic_request, call.path, codec))
+            .await
+        {
+            Ok(response) => {
+                self.forward_stream(response, call.deadline, &call.options.json, &tx)
+                    .await?
+            }
+            Err(status) => GrpcStreamEnd::from_status(&status, 0, &call.options.json),
+        };
+
+        let _ = tx.send(GrpcStreamEvent::End(end)).await;
+        Ok(())
     }
 
-    pub async fn client_streaming_call(&mut self, request: GrpcRequest) -> AppResult<GrpcResponse> {
-        // TODO: Implement actual client streaming
-        // For now, return a placeholder
-        Ok(GrpcResponse {
-            success: true,
-            data: Some(serde_json::json!({
-                "stream_type": "client_streaming",
-                "message": request.message,
-                "status": "ready_for_stream",
-                "note": "Client streaming implementation pending"
-            })),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("Ready for client stream".to_string()),
-        })
+    /// Run a client-streaming call fed by `messages`. The single response is
+    /// sent to `tx` once the sender of `messages` is dropped (half-close).
+    pub async fn client_streaming_call(
+        &mut self,
+        request: GrpcRequest,
+        messages: mpsc::Receiver<DynamicMessage>,
+        tx: mpsc::Sender<GrpcStreamEvent>,
+    ) -> AppResult<()> {
+        let mut call = self.prepare_call(&request).await?;
+
+        let (messages, oversized) = call.request_stream(messages);
+        let tonic_request = call.request(messages, request.metadata)?;
+        let codec = DynamicCodec::new(call.method.output());
+
+        let end = match call
+            .deadline
+            .run(call.grpc.streaming(tonic_request, call.path, codec))
+            .await
+        {
+            Ok(response) => {
+                let mut response = self
+                    .single_response(response, call.deadline, &call.options.json)
+                    .await?;
+                let mut message_count = 0;
+                if let Some(data) = response.data.take() {
+                    message_count = 1;
+                    let _ = tx.send(GrpcStreamEvent::Message(data)).await;
+                }
+                GrpcStreamEnd::new(response, message_count)
+            }
+            Err(status) => GrpcStreamEnd::from_status(&status, 0, &call.options.json),
+        };
+        let end = oversized_end(end, &oversized, &call.options.json);
+
+        let _ = tx.send(GrpcStreamEvent::End(end)).await;
+        Ok(())
     }
 
-    pub async fn bidirectional_call(&mut self, request: GrpcRequest) -> AppResult<GrpcResponse> {
-        // TODO: Implement actual bidirectional streaming
-        // For now, return a placeholder
-        Ok(GrpcResponse {
-            success: true,
-            data: Some(serde_json::json!({
-                "stream_type": "bidirectional",
-                "message": request.message,
-                "status": "streaming_established",
-                "note": "Bidirectional streaming implementation pending"
-            })),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("Bidirectional stream established".to_string()),
-        })
+    /// Run a bidirectional call: `messages` feeds the request stream while
+    /// every response message is sent to `tx` as it arrives
+    pub async fn bidirectional_call(
+        &mut self,
+        request: GrpcRequest,
+        messages: mpsc::Receiver<DynamicMessage>,
+        tx: mpsc::Sender<GrpcStreamEvent>,
+    ) -> AppResult<()> {
+        let mut call = self.prepare_call(&request).await?;
+
+        let (messages, oversized) = call.request_stream(messages);
+        let tonic_request = call.request(messages, request.metadata)?;
+        let codec = DynamicCodec::new(call.method.output());
+
+        let end = match call
+            .deadline
+            .run(call.grpc.streaming(tonic_request, call.path, codec))
+            .await
+        {
+            Ok(response) => {
+                self.forward_stream(response, call.deadline, &call.options.json, &tx)
+                    .await?
+            }
+            Err(status) => GrpcStreamEnd::from_status(&status, 0, &call.options.json),
+        };
+        let end = oversized_end(end, &oversized, &call.options.json);
+
+        let _ = tx.send(GrpcStreamEvent::End(end)).await;
+        Ok(())
     }
 
+    /// Read the single message of a unary or client-streaming response along
+    /// with its headers and trailers
+    async fn single_response(
+        &self,
+        response: Response<Streaming<DynamicMessage>>,
+        deadline: Deadline,
+        json: &JsonOptions,
+    ) -> AppResult<GrpcResponse> {
+        let headers = response.metadata().clone();
+        let mut stream = response.into_inner();
+
+        let received = deadline
+            .run(async {
+                let message = stream
+                    .message()
+                    .await?
+                    .ok_or_else(|| Status::internal("Server sent no response message"))?;
+                if stream.message().await?.is_some() {
+                    return Err(Status::internal(
+                        "Server sent more than one response message",
+                    ));
+                }
+                Ok((message, stream.trailers().await?))
+            })
+            .await;
+
+        let response = match received {
+            Ok((message, trailers)) => {
+                let response = GrpcResponse::ok(self.dynamic_message_to_json(&message, json)?);
+                match trailers {
+                    Some(trailers) => response.with_trailers(&trailers),
+                    None => response,
+                }
+            }
+            Err(status) => GrpcResponse::from_status(&status, json),
+        };
+
+        Ok(response.with_headers(&headers))
+    }
+
+    /// Send every message of `response` to `tx` and return how the stream ended.
+    /// Stops reading early when the receiving side has gone away.
+    async fn forward_stream(
+        &self,
+        response: Response<Streaming<DynamicMessage>>,
+        deadline: Deadline,
+        json: &JsonOptions,
+        tx: &mpsc::Sender<GrpcStreamEvent>,
+    ) -> AppResult<GrpcStreamEnd> {
+        let headers = response.metadata().clone();
+        let mut stream = response.into_inner();
+        let mut message_count = 0;
+
+        let end = loop {
+            match deadline.run(stream.message()).await {
+                Ok(Some(message)) => {
+                    message_count += 1;
+                    let data = self.dynamic_message_to_json(&message, json)?;
+                    if tx.send(GrpcStreamEvent::Message(data)).await.is_err() {
+                        break GrpcResponse::from_status(
+                            &Status::cancelled("Stream receiver closed"),
+                            json,
+                        );
+                    }
+                }
+                Ok(None) => {
+                    break match deadline.run(stream.trailers()).await {
+                        Ok(Some(trailers)) => GrpcResponse::from_status(&Status::ok(""), json)
+                            .with_trailers(&trailers),
+                        Ok(None) => GrpcResponse::from_status(&Status::ok(""), json),
+                        Err(status) => GrpcResponse::from_status(&status, json),
+                    };
+                }
+                Err(status) => break GrpcResponse::from_status(&status, json),
+            }
+        };
+
+        Ok(GrpcStreamEnd::new(
+            end.with_headers(&headers),
+            message_count,
+        ))
+    }
+
+    /// Run a unary call. Streaming calls deliver their messages as events and
+    /// go through the `*_call` methods taking channels instead.
     pub async fn execute(&mut self, request: GrpcRequest) -> AppResult<GrpcResponse> {
         match request.call_type {
             GrpcCallType::Unary => self.unary_call(request).await,
-            GrpcCallType::ServerStreaming => self.server_streaming_call(request).await,
-            GrpcCallType::ClientStreaming => self.client_streaming_call(request).await,
-            GrpcCallType::Bidirectional => self.bidirectional_call(request).await,
+            GrpcCallType::ServerStreaming
+            | GrpcCallType::ClientStreaming
+            | GrpcCallType::Bidirectional => Err(AppError::validation(
+                "call_type",
+                format!(
+                    "{:?} calls deliver their messages as events and cannot be executed as unary",
+                    request.call_type
+                ),
+            )),
+        }
+    }
+}
+
+/// A call whose method is resolved and whose channel is ready
+struct PreparedCall {
+    grpc: Grpc<Channel>,
+    method: MethodDescriptor,
+    path: PathAndQuery,
+    options: GrpcCallOptions,
+    deadline: Deadline,
+}
+
+impl PreparedCall {
+    /// Build the request carrying `message`, announcing the deadline to the
+    /// server in the `grpc-timeout` header
+    fn request<T>(
+        &self,
+        message: T,
+        metadata: Option<HashMap<String, String>>,
+    ) -> AppResult<Request<T>> {
+        let mut request = create_tonic_request(message, metadata)?;
+        if let Some(timeout) = self.options.timeout() {
+            request.set_timeout(timeout);
         }
+        Ok(request)
+    }
+
+    /// RESOURCE_EXHAUSTED when `message` is above the request size limit.
+    /// tonic resets the call on such a message without any status.
+    fn oversized(&self, message: &DynamicMessage) -> Option<Status> {
+        oversized_status(self.options.max_request_size, message)
+    }
+
+    /// Request stream fed by `messages`, recording the status of the first
+    /// message above the request size limit in the returned slot
+    fn request_stream(
+        &self,
+        messages: mpsc::Receiver<DynamicMessage>,
+    ) -> (impl Stream<Item = DynamicMessage>, Arc<OnceLock<Status>>) {
+        let oversized = Arc::new(OnceLock::new());
+        let slot = oversized.clone();
+        let limit = self.options.max_request_size;
+
+        let stream = ReceiverStream::new(messages).map(move |message| {
+            if let Some(status) = oversized_status(limit, &message) {
+                let _ = slot.set(status);
+            }
+            message
+        });
+        (stream, oversized)
+    }
+}
+
+/// RESOURCE_EXHAUSTED when `message` is above the request size `limit`
+pub fn oversized_status(limit: Option<usize>, message: &DynamicMessage) -> Option<Status> {
+    let size = message.encoded_len();
+    match limit {
+        Some(limit) if size > limit => Some(Status::resource_exhausted(format!(
+            "Request message of {} bytes is larger than the limit of {} bytes",
+            size, limit
+        ))),
+        _ => None,
+    }
+}
+
+/// Report a stream that was reset over a too large request message with
+/// the status of that message
+fn oversized_end(
+    end: GrpcStreamEnd,
+    oversized: &OnceLock<Status>,
+    json: &JsonOptions,
+) -> GrpcStreamEnd {
+    match oversized.get() {
+        Some(status) => GrpcStreamEnd::from_status(status, end.message_count, json),
+        None => end,
     }
 }
 
-impl Default for GrpcClient {
-    fn default() -> Self {
-        // This is a fallback implementation
-        // In practice, we'd need a valid channel
-        unimplemented!("GrpcClient requires a valid channel")
+/// The point in time a call has to be finished by, if it has a deadline
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    pub fn after(options: &GrpcCallOptions) -> Self {
+        Self(options.timeout().map(|timeout| Instant::now() + timeout))
     }
+
+    /// Await a step of the call, failing with DEADLINE_EXCEEDED once the
+    /// deadline has passed
+    pub async fn run<T>(self, step: impl Future<Output = Result<T, Status>>) -> Result<T, Status> {
+        let result = match self.0 {
+            Some(at) => match tokio::time::timeout_at(at, step).await {
+                Ok(result) => result,
+                Err(_) => Err(Status::deadline_exceeded("Deadline exceeded")),
+            },
+            None => step.await,
+        };
+
+        // tonic reports an expired `grpc-timeout`, on either side, as CANCELLED
+        result.map_err(|status| match self.0 {
+            Some(at) if status.code() == Code::Cancelled && Instant::now() >= at => {
+                Status::deadline_exceeded("Deadline exceeded")
+            }
+            _ => status,
+        })
+    }
+}
+
+/// Find a method by service and method name.
+///
+/// The service may be given by its full name or by its short name
+/// (e.g. "Echo" when the service is "mypackage.Echo").
+pub fn resolve_method(
+    descriptor_pool: &DescriptorPool,
+    service: &str,
+    method: &str,
+) -> AppResult<MethodDescriptor> {
+    let service_desc = descriptor_pool
+        .get_service_by_name(service)
+        .or_else(|| {
+            descriptor_pool
+                .services()
+                .find(|s| s.name() == service || s.full_name().ends_with(&format!(".{}", service)))
+        })
+        .ok_or_else(|| {
+            // List available services for better error message
+            let available: Vec<String> = descriptor_pool
+                .services()
+                .map(|s| s.full_name().to_string())
+                .collect();
+            AppError::validation(
+                "service",
+                format!(
+                    "Service '{}' not found. Available services: [{}]",
+                    service,
+                    available.join(", ")
+                ),
+            )
+        })?;
+
+    let method_desc = service_desc
+        .methods()
+        .find(|m| m.name() == method)
+        .ok_or_else(|| {
+            // List available methods for better error message
+            let available: Vec<String> = service_desc
+                .methods()
+                .map(|m| m.name().to_string())
+                .collect();
+            AppError::validation(
+                "method",
+                format!(
+                    "Method '{}' not found in service '{}'. Available methods: [{}]",
+                    method,
+                    service_desc.full_name(),
+                    available.join(", ")
+                ),
+            )
+        })?;
+
+    Ok(method_desc)
+}
+
+/// Build the HTTP/2 path of a method, e.g. "/mypackage.Echo/Echo"
+pub fn method_path(method_desc: &MethodDescriptor) -> AppResult<PathAndQuery> {
+    let path = format!(
+        "/{}/{}",
+        method_desc.parent_service().full_name(),
+        method_desc.name()
+    );
+    path.parse::<PathAndQuery>().map_err(|e| {
+        AppError::validation("method", format!("Invalid method path '{}': {}", path, e))
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::grpc::test_utils::fixtures;
+    use crate::grpc::test_utils::server::TestServer;
     use std::collections::HashMap;
 
+    /// Client on the plaintext test server, resolving methods by reflection
+    async fn connect_client(url: &str) -> GrpcClient {
+        GrpcClient::new(url, None, &ProxySettings::direct())
+            .await
+            .unwrap()
+    }
+
     fn create_test_request(call_type: GrpcCallType) -> GrpcRequest {
         GrpcRequest {
             url: "http://localhost:50051".to_string(),
@@ -209,6 +590,8 @@ mod tests {
             message: serde_json::json!({"test": "data"}),
             metadata: Some(HashMap::new()),
             call_type,
+            options: Default::default(),
+            transport: Default::default(),
         }
     }
 
@@ -229,6 +612,9 @@ mod tests {
             error: None,
             status_code: Some(0),
             status_message: Some("OK".to_string()),
+            headers: Default::default(),
+            trailers: Default::default(),
+            details: Vec::new(),
         };
 
         assert!(response.success);
@@ -245,6 +631,9 @@ mod tests {
             error: Some("Connection failed".to_string()),
             status_code: Some(14),
             status_message: Some("UNAVAILABLE".to_string()),
+            headers: Default::default(),
+            trailers: Default::default(),
+            details: Vec::new(),
         };
 
         assert!(!response.success);
@@ -265,4 +654,605 @@ mod tests {
         assert!(matches!(client_streaming, GrpcCallType::ClientStreaming));
         assert!(matches!(bidirectional, GrpcCallType::Bidirectional));
     }
+
+    fn echo_request(message: &str) -> GrpcRequest {
+        GrpcRequest {
+            url: String::new(),
+            service: "Echo".to_string(),
+            method: "Echo".to_string(),
+            message: serde_json::json!({"message": message}),
+            metadata: None,
+            call_type: GrpcCallType::Unary,
+            options: Default::default(),
+            transport: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_returns_server_response() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let response = client.unary_call(echo_request("hello")).await.unwrap();
+
+        assert!(response.success);
+        assert_eq!(response.data, Some(serde_json::json!({"message": "hello"})));
+        assert_eq!(response.status_code, Some(0));
+        assert_eq!(response.status_message.as_deref(), Some("OK"));
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_reports_grpc_status() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let response = client.unary_call(echo_request("error")).await.unwrap();
+
+        assert!(!response.success);
+        assert!(response.data.is_none());
+        assert_eq!(response.status_code, Some(3));
+        assert_eq!(response.status_message.as_deref(), Some("INVALID_ARGUMENT"));
+        assert_eq!(response.error.as_deref(), Some("echo refused"));
+        assert_eq!(
+            response.trailers.get("x-error-reason").unwrap(),
+            &["refused"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_decodes_error_details() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let response = client.unary_call(echo_request("invalid")).await.unwrap();
+
+        assert_eq!(response.status_message.as_deref(), Some("INVALID_ARGUMENT"));
+        assert_eq!(
+            response.details,
+            vec![serde_json::json!({
+                "@type": "type.googleapis.com/google.rpc.BadRequest",
+                "fieldViolations": [{"field": "message", "description": "must not be \"invalid\""}],
+            })]
+        );
+        assert!(!response.trailers.contains_key("grpc-status-details-bin"));
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_returns_response_headers() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let mut request = echo_request("hello");
+        request.metadata = Some(HashMap::from([
+            ("x-echo-user".to_string(), "alice".to_string()),
+            // Binary values may come without padding
+            ("x-echo-trace-bin".to_string(), "AAEC/w".to_string()),
+            ("x-not-echoed".to_string(), "1".to_string()),
+        ]));
+        let response = client.unary_call(request).await.unwrap();
+
+        assert!(response.success);
+        assert_eq!(response.headers.get("x-echo-user").unwrap(), &["alice"]);
+        assert_eq!(
+            response.headers.get("x-echo-trace-bin").unwrap(),
+            &["AAEC/w=="]
+        );
+        assert!(!response.headers.contains_key("x-not-echoed"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_metadata_is_a_validation_error() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        for (key, value) in [
+            ("bad key", "value"),
+            ("x-line", "first\nsecond"),
+            ("x-trace-bin", "not base64!"),
+        ] {
+            let mut request = echo_request("hello");
+            request.metadata = Some(HashMap::from([(key.to_string(), value.to_string())]));
+
+            match client.unary_call(request).await {
+                Err(AppError::ValidationError { field, message }) => {
+                    assert_eq!(field, "metadata");
+                    assert!(message.contains(key), "{}", message);
+                }
+                other => panic!("Expected a validation error for '{}', got {:?}", key, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_unknown_method() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let mut request = echo_request("hello");
+        request.method = "Missing".to_string();
+        let result = client.unary_call(request).await;
+
+        match result.unwrap_err() {
+            AppError::ValidationError { field, message } => {
+                assert_eq!(field, "method");
+                assert!(message.contains("Available methods: [Echo, Expand, Collect, Chat]"));
+            }
+            other => panic!("Expected validation error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_with_proto_files_without_reflection() {
+        let server = TestServer::start_without_reflection().await;
+        let dir = tempfile::tempdir().unwrap();
+        let proto_path = dir.path().join("echo.proto");
+        std::fs::write(&proto_path, fixtures::echo_service_proto()).unwrap();
+
+        let source = DescriptorSource::ProtoFiles {
+            files: vec![proto_path.to_string_lossy().to_string()],
+            import_paths: vec![],
+        };
+        let mut client =
+            GrpcClient::with_source(&server.url, &source, None, &ProxySettings::direct())
+                .await
+                .unwrap();
+
+        let response = client.unary_call(echo_request("hello")).await.unwrap();
+
+        assert!(response.success);
+        assert_eq!(response.data, Some(serde_json::json!({"message": "hello"})));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_call_with_protoset_without_reflection() {
+        let server = TestServer::start_without_reflection().await;
+        let dir = tempfile::tempdir().unwrap();
+        let protoset_path = dir.path().join("echo.protoset");
+        std::fs::write(&protoset_path, server.pool.encode_to_vec()).unwrap();
+
+        let source = DescriptorSource::Protoset {
+            path: protoset_path.to_string_lossy().to_string(),
+        };
+        let mut client =
+            GrpcClient::with_source(&server.url, &source, None, &ProxySettings::direct())
+                .await
+                .unwrap();
+
+        let events = collect_stream_events(
+            &mut client,
+            serde_json::json!({"message": "hi", "count": 2}),
+        )
+        .await;
+
+        assert_eq!(events.len(), 3);
+        match &events[2] {
+            GrpcStreamEvent::End(end) => assert!(end.success),
+            other => panic!("Expected end event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reflection_disabled_error_suggests_local_source() {
+        let server = TestServer::start_without_reflection().await;
+        let mut client = connect_client(&server.url).await;
+
+        let err = client.unary_call(echo_request("hello")).await.unwrap_err();
+
+        assert_eq!(err.error_type(), "NetworkError");
+        assert!(err.message().contains("provide .proto files or a protoset"));
+    }
+
+    #[tokio::test]
+    async fn test_with_source_reports_invalid_proto_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let proto_path = dir.path().join("broken.proto");
+        std::fs::write(&proto_path, fixtures::invalid_proto()).unwrap();
+
+        let source = DescriptorSource::ProtoFiles {
+            files: vec![proto_path.to_string_lossy().to_string()],
+            import_paths: vec![],
+        };
+        let result = GrpcClient::with_source(
+            "http://127.0.0.1:1",
+            &source,
+            None,
+            &ProxySettings::direct(),
+        )
+        .await;
+
+        match result {
+            Err(AppError::ParseError {
+                location: Some(location),
+                ..
+            }) => {
+                assert_eq!(location.file, "broken.proto");
+                assert_eq!(location.line, 4);
+            }
+            Err(other) => panic!("Expected located parse error, got {:?}", other),
+            Ok(_) => panic!("Expected invalid proto files to be rejected"),
+        }
+    }
+
+    fn with_options(mut request: GrpcRequest, options: GrpcCallOptions) -> GrpcRequest {
+        request.options = options;
+        request
+    }
+
+    fn timeout(ms: u64) -> GrpcCallOptions {
+        GrpcCallOptions {
+            timeout_ms: Some(ms),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_deadline_exceeded() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let response = client
+            .unary_call(with_options(echo_request("slow"), timeout(50)))
+            .await
+            .unwrap();
+
+        assert!(!response.success);
+        assert_eq!(response.status_code, Some(4));
+        assert_eq!(
+            response.status_message.as_deref(),
+            Some("DEADLINE_EXCEEDED")
+        );
+
+        // A deadline that leaves enough time does not affect the call
+        let response = client
+            .unary_call(with_options(echo_request("slow"), timeout(5_000)))
+            .await
+            .unwrap();
+        assert!(response.success);
+    }
+
+    #[tokio::test]
+    async fn test_server_streaming_call_deadline_exceeded() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+        let (tx, mut rx) = mpsc::channel(10);
+
+        let mut request = streaming_request("Expand", GrpcCallType::ServerStreaming);
+        request.message = serde_json::json!({"message": "slow", "count": 5});
+        client
+            .server_streaming_call(with_options(request, timeout(300)), tx)
+            .await
+            .unwrap();
+
+        let mut end = None;
+        while let Some(event) = rx.recv().await {
+            if let GrpcStreamEvent::End(stream_end) = event {
+                end = Some(stream_end);
+            }
+        }
+        let end = end.expect("stream should end");
+        assert_eq!(end.status_code, Some(4));
+        assert!(end.message_count < 5);
+    }
+
+    #[tokio::test]
+    async fn test_client_streaming_call_deadline_exceeded() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+        // The request stream is never half-closed
+        let (_message_tx, message_rx) = mpsc::channel(10);
+        let (tx, mut rx) = mpsc::channel(10);
+
+        client
+            .client_streaming_call(
+                with_options(
+                    streaming_request("Collect", GrpcCallType::ClientStreaming),
+                    timeout(100),
+                ),
+                message_rx,
+                tx,
+            )
+            .await
+            .unwrap();
+
+        match rx.recv().await.unwrap() {
+            GrpcStreamEvent::End(end) => {
+                assert_eq!(end.status_code, Some(4));
+                assert_eq!(end.message_count, 0);
+            }
+            other => panic!("Expected end event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unary_call_with_gzip() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+        let options = GrpcCallOptions {
+            gzip: true,
+            ..Default::default()
+        };
+
+        let response = client
+            .unary_call(with_options(echo_request("compressed"), options))
+            .await
+            .unwrap();
+
+        assert!(response.success);
+        assert_eq!(response.data.unwrap()["message"], "compressed");
+        assert_eq!(response.headers.get("grpc-encoding").unwrap(), &["gzip"]);
+    }
+
+    #[tokio::test]
+    async fn test_message_size_limits() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+        let large = "x".repeat(5 * 1024 * 1024);
+
+        // Above the default 4 MB limit for responses
+        let response = client.unary_call(echo_request(&large)).await.unwrap();
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("message length too large"));
+
+        let options = GrpcCallOptions {
+            max_response_size: Some(8 * 1024 * 1024),
+            ..Default::default()
+        };
+        let response = client
+            .unary_call(with_options(echo_request(&large), options))
+            .await
+            .unwrap();
+        assert!(response.success);
+        assert_eq!(
+            response.data.unwrap()["message"].as_str().unwrap().len(),
+            large.len()
+        );
+
+        let options = GrpcCallOptions {
+            max_request_size: Some(1024),
+            ..Default::default()
+        };
+        let response = client
+            .unary_call(with_options(echo_request(&large), options))
+            .await
+            .unwrap();
+        assert!(!response.success);
+        let error = response.error.clone().unwrap();
+        assert_eq!(
+            response.status_message.as_deref(),
+            Some("RESOURCE_EXHAUSTED")
+        );
+        assert!(error.contains("larger than the limit of 1024 bytes"));
+    }
+
+    async fn collect_stream_events(
+        client: &mut GrpcClient,
+        message: serde_json::Value,
+    ) -> Vec<GrpcStreamEvent> {
+        let (tx, mut rx) = mpsc::channel(10);
+        let request = GrpcRequest {
+            url: String::new(),
+            service: "test.v1.Echo".to_string(),
+            method: "Expand".to_string(),
+            message,
+            metadata: None,
+            call_type: GrpcCallType::ServerStreaming,
+            options: Default::default(),
+            transport: Default::default(),
+        };
+
+        client.server_streaming_call(request, tx).await.unwrap();
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_server_streaming_call_forwards_each_message() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let events = collect_stream_events(
+            &mut client,
+            serde_json::json!({"message": "hi", "count": 3}),
+        )
+        .await;
+
+        assert_eq!(events.len(), 4, "3 messages followed by the end event");
+        for (index, event) in events[..3].iter().enumerate() {
+            match event {
+                GrpcStreamEvent::Message(data) => {
+                    assert_eq!(data["message"], "hi");
+                    assert_eq!(
+                        data.get("index").and_then(|v| v.as_i64()).unwrap_or(0),
+                        index as i64
+                    );
+                }
+                other => panic!("Expected message event, got {:?}", other),
+            }
+        }
+        match &events[3] {
+            GrpcStreamEvent::End(end) => {
+                assert!(end.success);
+                assert_eq!(end.status_code, Some(0));
+                assert_eq!(end.message_count, 3);
+                assert!(end.error.is_none());
+                assert!(end.headers.contains_key("content-type"));
+            }
+            other => panic!("Expected end event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_streaming_call_reports_mid_stream_status() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let events = collect_stream_events(
+            &mut client,
+            serde_json::json!({"message": "error", "count": 2}),
+        )
+        .await;
+
+        assert_eq!(events.len(), 3);
+        match events.last().unwrap() {
+            GrpcStreamEvent::End(end) => {
+                assert!(!end.success);
+                assert_eq!(end.status_code, Some(10));
+                assert_eq!(end.status_message.as_deref(), Some("ABORTED"));
+                assert_eq!(end.error.as_deref(), Some("stream aborted"));
+                assert_eq!(end.message_count, 2);
+            }
+            other => panic!("Expected end event, got {:?}", other),
+        }
+    }
+
+    fn streaming_request(method: &str, call_type: GrpcCallType) -> GrpcRequest {
+        GrpcRequest {
+            url: String::new(),
+            service: "test.v1.Echo".to_string(),
+            method: method.to_string(),
+            message: serde_json::Value::Null,
+            metadata: None,
+            call_type,
+            options: Default::default(),
+            transport: Default::default(),
+        }
+    }
+
+    fn echo_request_message(client_pool: &DescriptorPool, text: &str) -> DynamicMessage {
+        let desc = client_pool
+            .get_message_by_name("test.v1.EchoRequest")
+            .unwrap();
+        DynamicMessage::deserialize(desc, serde_json::json!({"message": text})).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_client_streaming_call_responds_after_half_close() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+        let (message_tx, message_rx) = mpsc::channel(10);
+        let (tx, mut rx) = mpsc::channel(10);
+
+        for text in ["a", "b", "c"] {
+            message_tx
+                .send(echo_request_message(&server.pool, text))
+                .await
+                .unwrap();
+        }
+        drop(message_tx);
+
+        client
+            .client_streaming_call(
+                streaming_request("Collect", GrpcCallType::ClientStreaming),
+                message_rx,
+                tx,
+            )
+            .await
+            .unwrap();
+
+        match rx.recv().await {
+            Some(GrpcStreamEvent::Message(data)) => {
+                assert_eq!(data, serde_json::json!({"message": "a,b,c", "index": 3}));
+            }
+            other => panic!("Expected response message, got {:?}", other),
+        }
+        match rx.recv().await {
+            Some(GrpcStreamEvent::End(end)) => assert!(end.success),
+            other => panic!("Expected end event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bidirectional_call_interleaves_messages() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+        let (message_tx, message_rx) = mpsc::channel(10);
+        let (tx, mut rx) = mpsc::channel(10);
+
+        let call = tokio::spawn(async move {
+            client
+                .bidirectional_call(
+                    streaming_request("Chat", GrpcCallType::Bidirectional),
+                    message_rx,
+                    tx,
+                )
+                .await
+        });
+
+        // Each answer arrives before the next message is sent
+        for text in ["hi", "there"] {
+            message_tx
+                .send(echo_request_message(&server.pool, text))
+                .await
+                .unwrap();
+            match rx.recv().await {
+                Some(GrpcStreamEvent::Message(data)) => {
+                    assert_eq!(data["message"], format!("re: {}", text));
+                }
+                other => panic!("Expected response message, got {:?}", other),
+            }
+        }
+        drop(message_tx);
+
+        match rx.recv().await {
+            Some(GrpcStreamEvent::End(end)) => {
+                assert!(end.success);
+                assert_eq!(end.message_count, 2);
+            }
+            other => panic!("Expected end event, got {:?}", other),
+        }
+        call.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bidirectional_call_rejects_oversized_request_message() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+        let (message_tx, message_rx) = mpsc::channel(10);
+        let (tx, mut rx) = mpsc::channel(10);
+        let options = GrpcCallOptions {
+            max_request_size: Some(64),
+            ..Default::default()
+        };
+
+        message_tx
+            .send(echo_request_message(&server.pool, &"x".repeat(100)))
+            .await
+            .unwrap();
+        drop(message_tx);
+        client
+            .bidirectional_call(
+                with_options(
+                    streaming_request("Chat", GrpcCallType::Bidirectional),
+                    options,
+                ),
+                message_rx,
+                tx,
+            )
+            .await
+            .unwrap();
+
+        match rx.recv().await {
+            Some(GrpcStreamEvent::End(end)) => {
+                assert_eq!(end.status_message.as_deref(), Some("RESOURCE_EXHAUSTED"));
+                assert_eq!(end.message_count, 0);
+            }
+            other => panic!("Expected end event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_rejects_streaming_call_types() {
+        let server = TestServer::start().await;
+        let mut client = connect_client(&server.url).await;
+
+        let result = client
+            .execute(streaming_request("Chat", GrpcCallType::Bidirectional))
+            .await;
+
+        assert!(matches!(result, Err(AppError::ValidationError { .. })));
+    }
 }
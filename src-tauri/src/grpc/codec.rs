@@ -0,0 +1,70 @@
+use prost::Message;
+use prost_reflect::{DynamicMessage, MessageDescriptor};
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::Status;
+
+/// A tonic codec for messages only known at runtime through their descriptors.
+///
+/// Outgoing messages are encoded as-is; incoming messages are decoded into
+/// `decode_desc`, which is the method's output type on the client side.
+#[derive(Debug, Clone)]
+pub struct DynamicCodec {
+    decode_desc: MessageDescriptor,
+}
+
+impl DynamicCodec {
+    pub fn new(decode_desc: MessageDescriptor) -> Self {
+        Self { decode_desc }
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder {
+            desc: self.decode_desc.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DynamicEncoder;
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst)
+            .map_err(|e| Status::internal(format!("Failed to encode message: {}", e)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DynamicDecoder {
+    desc: MessageDescriptor,
+}
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let message = DynamicMessage::decode(self.desc.clone(), src).map_err(|e| {
+            Status::internal(format!(
+                "Failed to decode '{}': {}",
+                self.desc.full_name(),
+                e
+            ))
+        })?;
+        Ok(Some(message))
+    }
+}
@@ -1,26 +1,40 @@
+use crate::client::proxy::ProxySettings;
+use crate::error::{AppError, AppResult};
+use crate::grpc::breaking::{compare_pools, BreakingReport};
+use crate::grpc::client::{resolve_method, GrpcClient};
+use crate::grpc::convert::{binary_to_json, json_to_binary};
+use crate::grpc::grpcurl::{parse_grpcurl, to_grpcurl, GrpcurlCommand};
+use crate::grpc::probe::{probe_server, ProbeOptions, ServerProbe};
+use crate::grpc::state::GrpcState;
+use crate::grpc::streaming::{next_stream_id, GrpcStreamSessions, GrpcStreaming};
+use crate::grpc::template::{message_template, DEFAULT_TEMPLATE_DEPTH};
+use crate::grpc::tls::GrpcTlsConfig;
+use crate::grpc::transport::HttpTransportClient;
+use crate::grpc::wire::{decode_wire, BytesEncoding, WireMessage};
 use crate::grpc::{
-    client::GrpcClient, proto_parser::ProtoParser, reflection::GrpcReflection, GrpcCallType,
-    GrpcRequest, GrpcResponse, ProtoSchema,
+    proto_parser::ProtoParser, DescriptorSource, GrpcCallOptions, GrpcCallType, GrpcRequest,
+    GrpcResponse, GrpcTransport, JsonOptions, ProtoSchema,
 };
-use crate::http::ApiResponse;
-use tauri::command;
-
-fn grpc_response_to_api_response(response: GrpcResponse) -> ApiResponse {
-    ApiResponse {
-        success: response.success,
-        data: response.data,
-        error: response.error,
-    }
-}
+use prost_reflect::{DescriptorPool, MessageDescriptor, MethodDescriptor};
+use tauri::{command, AppHandle, State};
 
+/// Run a unary call. The response carries the call's data or error together
+/// with the status and the headers and trailers sent by the server.
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn grpc_unary_request(
+    state: State<'_, GrpcState>,
     url: String,
     service: String,
     method: String,
     message: serde_json::Value,
     metadata: Option<std::collections::HashMap<String, String>>,
-) -> Result<ApiResponse, String> {
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+    options: Option<GrpcCallOptions>,
+    transport: Option<GrpcTransport>,
+) -> Result<GrpcResponse, String> {
     let request = GrpcRequest {
         url,
         service,
@@ -28,33 +42,68 @@ pub async fn grpc_unary_request(
         message,
         metadata,
         call_type: GrpcCallType::Unary,
+        options: options.unwrap_or_default(),
+        transport: transport.unwrap_or_default(),
     };
 
-    match GrpcClient::new(&request.url).await {
-        Ok(mut client) => match client.execute(request).await {
-            Ok(response) => Ok(grpc_response_to_api_response(response)),
-            Err(e) => Ok(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e.to_string()),
-            }),
+    let source = descriptor_source.unwrap_or_default();
+    let proxy = proxy.unwrap_or_default();
+    let result = match request.transport {
+        GrpcTransport::Grpc => match state
+            .client(&request.url, tls.as_ref(), &proxy, &source)
+            .await
+        {
+            Ok(mut client) => client.execute(request).await,
+            Err(e) => return Ok(client_failed(&e)),
         },
-        Err(e) => Ok(ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to create gRPC client: {}", e)),
-        }),
+        _ => match state
+            .http_transport_client(&request.url, tls.as_ref(), &proxy, &source)
+            .await
+        {
+            Ok(client) => client.unary_call(request).await,
+            Err(e) => return Ok(client_failed(&e)),
+        },
+    };
+
+    match result {
+        Ok(response) => Ok(response),
+        Err(e) => Ok(GrpcResponse::from_error(&e)),
     }
 }
 
+fn client_failed(error: &AppError) -> GrpcResponse {
+    let mut response = GrpcResponse::from_error(error);
+    response.error = Some(format!("Failed to create gRPC client: {}", error));
+    response
+}
+
+/// Start a server-streaming call in the background and return its stream id.
+///
+/// Messages are emitted as `grpc-stream-message` events and the final status
+/// as a `grpc-stream-end` event. The frontend may pass its own `stream_id` so it
+/// can subscribe before the first message arrives; the id of a running stream
+/// is rejected.
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn grpc_server_streaming_request(
+    app: AppHandle,
+    state: State<'_, GrpcState>,
+    sessions: State<'_, GrpcStreamSessions>,
     url: String,
     service: String,
     method: String,
     message: serde_json::Value,
     metadata: Option<std::collections::HashMap<String, String>>,
-) -> Result<ApiResponse, String> {
+    stream_id: Option<String>,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+    options: Option<GrpcCallOptions>,
+    transport: Option<GrpcTransport>,
+) -> Result<String, String> {
+    let state = state.inner().clone();
+    let source = descriptor_source.unwrap_or_default();
+    let proxy = proxy.unwrap_or_default();
     let request = GrpcRequest {
         url,
         service,
@@ -62,33 +111,190 @@ pub async fn grpc_server_streaming_request(
         message,
         metadata,
         call_type: GrpcCallType::ServerStreaming,
+        options: options.unwrap_or_default(),
+        transport: transport.unwrap_or_default(),
+    };
+
+    let stream_id = stream_id.unwrap_or_else(next_stream_id);
+    let rx = sessions.start(&stream_id, None, |_, tx| async move {
+        match request.transport {
+            GrpcTransport::Grpc => {
+                let mut client = state
+                    .client(&request.url, tls.as_ref(), &proxy, &source)
+                    .await?;
+                client.server_streaming_call(request, tx).await
+            }
+            _ => {
+                let client = state
+                    .http_transport_client(&request.url, tls.as_ref(), &proxy, &source)
+                    .await?;
+                client.server_streaming_call(request, tx).await
+            }
+        }
+    })?;
+    tokio::spawn(GrpcStreaming::emit_events(app, stream_id.clone(), rx));
+
+    Ok(stream_id)
+}
+
+/// Open a client-streaming or bidirectional call and return its stream id.
+///
+/// Request messages are pushed with `grpc_stream_send` and the request stream
+/// is finished with `grpc_stream_close`. Server messages and the final status
+/// are emitted like for `grpc_server_streaming_request`.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn grpc_open_stream(
+    app: AppHandle,
+    state: State<'_, GrpcState>,
+    sessions: State<'_, GrpcStreamSessions>,
+    url: String,
+    service: String,
+    method: String,
+    metadata: Option<std::collections::HashMap<String, String>>,
+    stream_id: Option<String>,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+    options: Option<GrpcCallOptions>,
+    transport: Option<GrpcTransport>,
+) -> Result<String, String> {
+    let source = descriptor_source.unwrap_or_default();
+    let proxy = proxy.unwrap_or_default();
+    let transport = transport.unwrap_or_default();
+    let mut client = match transport {
+        GrpcTransport::Grpc => state
+            .client(&url, tls.as_ref(), &proxy, &source)
+            .await
+            .map(StreamClient::Native),
+        _ => state
+            .http_transport_client(&url, tls.as_ref(), &proxy, &source)
+            .await
+            .map(StreamClient::Http),
+    }
+    .map_err(|e| format!("Failed to create gRPC client: {}", e))?;
+    let method_desc = client.find_method(&service, &method).await?;
+
+    let call_type = GrpcCallType::of(&method_desc);
+    if !method_desc.is_client_streaming() {
+        return Err(format!(
+            "Method '{}' is a {:?} call and does not accept a request stream",
+            method, call_type
+        ));
+    }
+    if matches!(client, StreamClient::Http(_)) && method_desc.is_server_streaming() {
+        return Err(format!(
+            "Method '{}' is a {:?} call, which needs the native gRPC transport",
+            method, call_type
+        ));
+    }
+
+    let request = GrpcRequest {
+        url,
+        service,
+        method,
+        message: serde_json::Value::Null,
+        metadata,
+        call_type,
+        options: options.unwrap_or_default(),
+        transport,
     };
 
-    match GrpcClient::new(&request.url).await {
-        Ok(mut client) => match client.execute(request).await {
-            Ok(response) => Ok(grpc_response_to_api_response(response)),
-            Err(e) => Ok(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e.to_string()),
-            }),
+    let stream_id = stream_id.unwrap_or_else(next_stream_id);
+    let rx = sessions.start(
+        &stream_id,
+        Some(method_desc.input()),
+        |messages, tx| async move {
+            match (client, &request.call_type) {
+                (StreamClient::Native(mut client), GrpcCallType::ClientStreaming) => {
+                    client.client_streaming_call(request, messages, tx).await
+                }
+                (StreamClient::Native(mut client), _) => {
+                    client.bidirectional_call(request, messages, tx).await
+                }
+                (StreamClient::Http(client), _) => {
+                    client.client_streaming_call(request, messages, tx).await
+                }
+            }
         },
-        Err(e) => Ok(ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to create gRPC client: {}", e)),
-        }),
+    )?;
+    tokio::spawn(GrpcStreaming::emit_events(app, stream_id.clone(), rx));
+
+    Ok(stream_id)
+}
+
+/// Client of a call opened by `grpc_open_stream`
+enum StreamClient {
+    Native(GrpcClient),
+    Http(HttpTransportClient),
+}
+
+impl StreamClient {
+    async fn find_method(&mut self, service: &str, method: &str) -> AppResult<MethodDescriptor> {
+        match self {
+            StreamClient::Native(client) => client.find_method(service, method).await,
+            StreamClient::Http(client) => client.find_method(service, method),
+        }
     }
 }
 
+/// Send a JSON message on an open client-streaming or bidirectional stream
 #[command]
-pub async fn grpc_discover_services(url: String) -> Result<ProtoSchema, String> {
-    match GrpcReflection::new(&url).await {
-        Ok(mut reflection) => match reflection.discover_services().await {
-            Ok(schema) => Ok(schema),
-            Err(e) => Err(format!("Failed to discover services: {}", e)),
-        },
-        Err(e) => Err(format!("Failed to create reflection client: {}", e)),
+pub async fn grpc_stream_send(
+    sessions: State<'_, GrpcStreamSessions>,
+    stream_id: String,
+    message: serde_json::Value,
+) -> Result<(), String> {
+    Ok(sessions.send(&stream_id, &message).await?)
+}
+
+/// Half-close a stream: no more messages will be sent, responses keep coming
+#[command]
+pub async fn grpc_stream_close(
+    sessions: State<'_, GrpcStreamSessions>,
+    stream_id: String,
+) -> Result<(), String> {
+    Ok(sessions.close_send(&stream_id)?)
+}
+
+/// Cancel any running stream
+#[command]
+pub async fn grpc_stream_cancel(
+    sessions: State<'_, GrpcStreamSessions>,
+    stream_id: String,
+) -> Result<(), String> {
+    Ok(sessions.cancel(&stream_id)?)
+}
+
+/// Whether a stream is still running, to tell a stream that closed without
+/// its end event from one that is only quiet
+#[command]
+pub async fn grpc_stream_active(
+    sessions: State<'_, GrpcStreamSessions>,
+    stream_id: String,
+) -> Result<bool, String> {
+    Ok(sessions.is_active(&stream_id))
+}
+
+#[command]
+pub async fn grpc_discover_services(
+    state: State<'_, GrpcState>,
+    url: String,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+) -> Result<ProtoSchema, String> {
+    match state
+        .descriptor_pool(
+            &url,
+            tls.as_ref(),
+            &proxy.unwrap_or_default(),
+            &descriptor_source.unwrap_or_default(),
+        )
+        .await
+    {
+        Ok(pool) => Ok(ProtoSchema::from_pool(&pool)),
+        Err(e) => Err(format!("Failed to discover services: {}", e)),
     }
 }
 
@@ -102,38 +308,278 @@ pub async fn grpc_parse_proto_file(content: String) -> Result<ProtoSchema, Strin
 
 #[command]
 pub async fn grpc_get_service_info(
+    state: State<'_, GrpcState>,
     url: String,
     service_name: String,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
 ) -> Result<Option<crate::grpc::ProtoService>, String> {
-    match GrpcReflection::new(&url).await {
-        Ok(mut reflection) => match reflection.get_service_info(&service_name).await {
-            Ok(service) => Ok(service),
-            Err(e) => Err(format!("Failed to get service info: {}", e)),
-        },
-        Err(e) => Err(format!("Failed to create reflection client: {}", e)),
+    match state
+        .descriptor_pool(
+            &url,
+            tls.as_ref(),
+            &proxy.unwrap_or_default(),
+            &descriptor_source.unwrap_or_default(),
+        )
+        .await
+    {
+        Ok(pool) => Ok(ProtoSchema::from_pool(&pool)
+            .service(&service_name)
+            .cloned()),
+        Err(e) => Err(format!("Failed to get service info: {}", e)),
     }
 }
 
 #[command]
 pub async fn grpc_get_method_info(
+    state: State<'_, GrpcState>,
     url: String,
     service_name: String,
     method_name: String,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
 ) -> Result<Option<crate::grpc::ProtoMethod>, String> {
-    match GrpcReflection::new(&url).await {
-        Ok(mut reflection) => {
-            match reflection
-                .get_method_info(&service_name, &method_name)
-                .await
-            {
-                Ok(method) => Ok(method),
-                Err(e) => Err(format!("Failed to get method info: {}", e)),
-            }
+    match state
+        .descriptor_pool(
+            &url,
+            tls.as_ref(),
+            &proxy.unwrap_or_default(),
+            &descriptor_source.unwrap_or_default(),
+        )
+        .await
+    {
+        Ok(pool) => Ok(ProtoSchema::from_pool(&pool)
+            .service(&service_name)
+            .and_then(|service| service.method(&method_name))
+            .cloned()),
+        Err(e) => Err(format!("Failed to get method info: {}", e)),
+    }
+}
+
+/// Build an example JSON request for a method, with every field of its input
+/// message set to its default in the proto3 JSON mapping
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn grpc_request_template(
+    state: State<'_, GrpcState>,
+    url: String,
+    service_name: String,
+    method_name: String,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+    max_depth: Option<usize>,
+) -> Result<serde_json::Value, String> {
+    let pool = state
+        .descriptor_pool(
+            &url,
+            tls.as_ref(),
+            &proxy.unwrap_or_default(),
+            &descriptor_source.unwrap_or_default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to load descriptors: {}", e))?;
+    let method = resolve_method(&pool, &service_name, &method_name)?;
+
+    Ok(message_template(
+        &method.input(),
+        max_depth.unwrap_or(DEFAULT_TEMPLATE_DEPTH),
+    ))
+}
+
+/// Build an example JSON request for a method of proto source text
+#[command]
+pub async fn grpc_proto_request_template(
+    content: String,
+    service_name: String,
+    method_name: String,
+    max_depth: Option<usize>,
+) -> Result<serde_json::Value, String> {
+    let pool =
+        ProtoParser::compile(&content).map_err(|e| format!("Failed to parse proto file: {}", e))?;
+    let method = resolve_method(&pool, &service_name, &method_name)?;
+
+    Ok(message_template(
+        &method.input(),
+        max_depth.unwrap_or(DEFAULT_TEMPLATE_DEPTH),
+    ))
+}
+
+/// Drop the cached descriptors of an endpoint, load them again and return
+/// the fresh schema
+#[command]
+pub async fn grpc_refresh_descriptors(
+    state: State<'_, GrpcState>,
+    url: String,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+) -> Result<ProtoSchema, String> {
+    match state
+        .refresh(
+            &url,
+            tls.as_ref(),
+            &proxy.unwrap_or_default(),
+            &descriptor_source.unwrap_or_default(),
+        )
+        .await
+    {
+        Ok(pool) => Ok(ProtoSchema::from_pool(&pool)),
+        Err(e) => Err(format!("Failed to refresh descriptors: {}", e)),
+    }
+}
+
+/// Close the pooled channel of an endpoint and forget its cached descriptors
+#[command]
+pub async fn grpc_evict_endpoint(state: State<'_, GrpcState>, url: String) -> Result<bool, String> {
+    Ok(state.evict(&url)?)
+}
+
+/// Render a request as an equivalent `grpcurl` command line
+#[command]
+pub async fn grpc_export_grpcurl(
+    request: GrpcRequest,
+    descriptor_source: Option<DescriptorSource>,
+    tls: Option<GrpcTlsConfig>,
+) -> Result<String, String> {
+    let command = GrpcurlCommand {
+        request,
+        descriptor_source: descriptor_source.unwrap_or_default(),
+        tls,
+    };
+    to_grpcurl(&command).map_err(|e| format!("Failed to export grpcurl command: {}", e))
+}
+
+/// Read a pasted `grpcurl` command line back into a request
+#[command]
+pub async fn grpc_import_grpcurl(command: String) -> Result<GrpcurlCommand, String> {
+    parse_grpcurl(&command).map_err(|e| format!("Failed to import grpcurl command: {}", e))
+}
+
+/// Where a schema is loaded from
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SchemaSource {
+    /// The descriptor pool of an endpoint, as used for its calls
+    Endpoint {
+        url: String,
+        #[serde(default)]
+        descriptor_source: DescriptorSource,
+        tls: Option<GrpcTlsConfig>,
+        proxy: Option<Box<ProxySettings>>,
+    },
+    /// `.proto` source text
+    Proto { content: String },
+}
+
+async fn load_schema(state: &GrpcState, source: &SchemaSource) -> Result<DescriptorPool, String> {
+    match source {
+        SchemaSource::Endpoint {
+            url,
+            descriptor_source,
+            tls,
+            proxy,
+        } => state
+            .descriptor_pool(
+                url,
+                tls.as_ref(),
+                &proxy.as_deref().cloned().unwrap_or_default(),
+                descriptor_source,
+            )
+            .await
+            .map_err(|e| format!("Failed to load descriptors: {}", e)),
+        SchemaSource::Proto { content } => {
+            ProtoParser::compile(content).map_err(|e| format!("Failed to parse proto file: {}", e))
         }
-        Err(e) => Err(format!("Failed to create reflection client: {}", e)),
     }
 }
 
+async fn load_message_type(
+    state: &GrpcState,
+    source: &SchemaSource,
+    message_type: &str,
+) -> Result<MessageDescriptor, String> {
+    load_schema(state, source)
+        .await?
+        .get_message_by_name(message_type)
+        .ok_or_else(|| format!("Message type '{}' not found", message_type))
+}
+
+/// Decode raw protobuf bytes given as hex or base64. Without a message type
+/// the wire format is shown with every plausible reading of each value.
+#[command]
+pub async fn grpc_decode_wire(
+    state: State<'_, GrpcState>,
+    data: String,
+    encoding: Option<BytesEncoding>,
+    message_type: Option<String>,
+    source: Option<SchemaSource>,
+) -> Result<WireMessage, String> {
+    let bytes = encoding.unwrap_or_default().decode(&data)?;
+    let message = match (&message_type, &source) {
+        (Some(message_type), Some(source)) => {
+            Some(load_message_type(&state, source, message_type).await?)
+        }
+        (Some(_), None) => return Err("A message type needs a source to be looked up in".into()),
+        (None, _) => None,
+    };
+
+    decode_wire(&bytes, message.as_ref()).map_err(|e| format!("Failed to decode bytes: {}", e))
+}
+
+/// Encode the JSON form of a message as protobuf binary, in hex or base64
+#[command]
+pub async fn grpc_json_to_binary(
+    state: State<'_, GrpcState>,
+    message_type: String,
+    source: SchemaSource,
+    json: serde_json::Value,
+    encoding: Option<BytesEncoding>,
+    delimited: Option<bool>,
+) -> Result<String, String> {
+    let message = load_message_type(&state, &source, &message_type).await?;
+    let bytes = json_to_binary(&message, &json, delimited.unwrap_or(false))
+        .map_err(|e| format!("Failed to encode message: {}", e))?;
+    Ok(encoding.unwrap_or_default().encode(&bytes))
+}
+
+/// Decode protobuf binary given as hex or base64 into the JSON form of a message
+#[command]
+pub async fn grpc_binary_to_json(
+    state: State<'_, GrpcState>,
+    message_type: String,
+    source: SchemaSource,
+    data: String,
+    encoding: Option<BytesEncoding>,
+    delimited: Option<bool>,
+    json_options: Option<JsonOptions>,
+) -> Result<serde_json::Value, String> {
+    let message = load_message_type(&state, &source, &message_type).await?;
+    let bytes = encoding.unwrap_or_default().decode(&data)?;
+    binary_to_json(
+        &message,
+        &bytes,
+        delimited.unwrap_or(false),
+        &json_options.unwrap_or_default(),
+    )
+    .map_err(|e| format!("Failed to decode message: {}", e))
+}
+
+/// Report the changes from the `previous` schema, e.g. the one a server is
+/// running, to the `current` one that would break existing clients
+#[command]
+pub async fn grpc_compare_schemas(
+    state: State<'_, GrpcState>,
+    previous: SchemaSource,
+    current: SchemaSource,
+) -> Result<BreakingReport, String> {
+    let previous = load_schema(&state, &previous).await?;
+    let current = load_schema(&state, &current).await?;
+    Ok(compare_pools(&previous, &current))
+}
+
 #[derive(serde::Serialize)]
 pub struct ConnectionStatus {
     pub connected: bool,
@@ -141,12 +587,21 @@ pub struct ConnectionStatus {
     pub latency_ms: Option<u64>,
 }
 
+/// Open a fresh connection to `url`, which then replaces the pooled one
 #[command]
-pub async fn grpc_test_connection(url: String) -> Result<ConnectionStatus, String> {
+pub async fn grpc_test_connection(
+    state: State<'_, GrpcState>,
+    url: String,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+) -> Result<ConnectionStatus, String> {
     let start = std::time::Instant::now();
 
-    match GrpcReflection::new(&url).await {
-        Ok(_reflection) => {
+    match state
+        .reconnect(&url, tls.as_ref(), &proxy.unwrap_or_default())
+        .await
+    {
+        Ok(_channel) => {
             let latency = start.elapsed().as_millis() as u64;
             Ok(ConnectionStatus {
                 connected: true,
@@ -162,43 +617,75 @@ pub async fn grpc_test_connection(url: String) -> Result<ConnectionStatus, Strin
     }
 }
 
+/// Diagnose a server: health status, reflection support, TLS session and the
+/// latency of a few round trips. Connects afresh like `grpc_test_connection`.
+#[command]
+pub async fn grpc_probe_server(
+    state: State<'_, GrpcState>,
+    url: String,
+    tls: Option<GrpcTlsConfig>,
+    proxy: Option<ProxySettings>,
+    service_name: Option<String>,
+    watch: Option<bool>,
+    pings: Option<u32>,
+) -> Result<ServerProbe, String> {
+    let options = ProbeOptions {
+        service: service_name.unwrap_or_default(),
+        watch: watch.unwrap_or(false),
+        pings,
+    };
+    Ok(probe_server(
+        &state,
+        &url,
+        tls.as_ref(),
+        &proxy.unwrap_or_default(),
+        &options,
+    )
+    .await)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::AppError;
+
+    #[test]
+    fn test_grpc_response_serializes_success_for_frontend() {
+        let grpc_response = GrpcResponse::ok(serde_json::json!({"result": "ok"}));
+
+        let json = serde_json::to_value(&grpc_response).unwrap();
+
+        assert_eq!(json["success"], true);
+        assert_eq!(json["data"], serde_json::json!({"result": "ok"}));
+        assert!(json["error"].is_null());
+        assert_eq!(json["headers"], serde_json::json!({}));
+        assert_eq!(json["trailers"], serde_json::json!({}));
+    }
 
     #[test]
-    fn test_grpc_response_to_api_response_success() {
-        let grpc_response = GrpcResponse {
-            success: true,
-            data: Some(serde_json::json!({"result": "ok"})),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("OK".to_string()),
-        };
-
-        let api_response = grpc_response_to_api_response(grpc_response);
-
-        assert!(api_response.success);
-        assert!(api_response.data.is_some());
-        assert!(api_response.error.is_none());
+    fn test_grpc_response_serializes_error_for_frontend() {
+        let grpc_response = GrpcResponse::from_error(&AppError::network("Connection failed"));
+
+        let json = serde_json::to_value(&grpc_response).unwrap();
+
+        assert_eq!(json["success"], false);
+        assert!(json["data"].is_null());
+        assert_eq!(json["error"], "Network Error: Connection failed");
+        assert!(json["status_code"].is_null());
     }
 
     #[test]
-    fn test_grpc_response_to_api_response_error() {
-        let grpc_response = GrpcResponse {
-            success: false,
-            data: None,
-            error: Some("Connection failed".to_string()),
-            status_code: Some(14),
-            status_message: Some("UNAVAILABLE".to_string()),
-        };
-
-        let api_response = grpc_response_to_api_response(grpc_response);
-
-        assert!(!api_response.success);
-        assert!(api_response.data.is_none());
-        assert!(api_response.error.is_some());
-        assert_eq!(api_response.error.unwrap(), "Connection failed");
+    fn test_grpc_response_from_status_error_keeps_code() {
+        let error = AppError::from(tonic::Status::unauthenticated("token expired"));
+
+        let grpc_response = GrpcResponse::from_error(&error);
+
+        assert!(!grpc_response.success);
+        assert_eq!(grpc_response.status_code, Some(16));
+        assert_eq!(
+            grpc_response.status_message.as_deref(),
+            Some("UNAUTHENTICATED")
+        );
     }
 
     #[tokio::test]
@@ -257,7 +744,7 @@ message ResponseB { string data = 1; }
     }
 
     #[tokio::test]
-    async fn test_grpc_response_conversion_preserves_data() {
+    async fn test_grpc_response_serialization_preserves_data() {
         let original_data = serde_json::json!({
             "users": [
                 {"id": 1, "name": "Alice"},
@@ -266,17 +753,11 @@ message ResponseB { string data = 1; }
             "total": 2
         });
 
-        let grpc_response = GrpcResponse {
-            success: true,
-            data: Some(original_data.clone()),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("OK".to_string()),
-        };
+        let grpc_response = GrpcResponse::ok(original_data.clone());
 
-        let api_response = grpc_response_to_api_response(grpc_response);
+        let json = serde_json::to_value(&grpc_response).unwrap();
 
-        assert!(api_response.success);
-        assert_eq!(api_response.data, Some(original_data));
+        assert_eq!(json["success"], true);
+        assert_eq!(json["data"], original_data);
     }
 }
@@ -0,0 +1,122 @@
+use crate::error::{AppError, AppResult};
+use crate::grpc::json::{message_to_json, JsonOptions};
+use prost::Message;
+use prost_reflect::{DynamicMessage, MessageDescriptor};
+use serde_json::Value;
+
+/// Encode the JSON form of a `message` in the protobuf binary format. With
+/// `delimited`, the bytes are prefixed with their length as a varint, the
+/// framing of `writeDelimitedTo` and of streams of messages.
+pub fn json_to_binary(
+    message: &MessageDescriptor,
+    json: &Value,
+    delimited: bool,
+) -> AppResult<Vec<u8>> {
+    let message = DynamicMessage::deserialize(message.clone(), json.clone())?;
+    Ok(match delimited {
+        true => message.encode_length_delimited_to_vec(),
+        false => message.encode_to_vec(),
+    })
+}
+
+/// Decode protobuf binary `bytes` of a `message` into its JSON form. With
+/// `delimited`, the bytes must hold exactly one length-prefixed message.
+pub fn binary_to_json(
+    message: &MessageDescriptor,
+    mut bytes: &[u8],
+    delimited: bool,
+    options: &JsonOptions,
+) -> AppResult<Value> {
+    let mut decoded = DynamicMessage::new(message.clone());
+    let result = match delimited {
+        true => decoded.merge_length_delimited(&mut bytes),
+        false => decoded.merge(&mut bytes),
+    };
+    result.map_err(|e| {
+        AppError::parse(format!(
+            "Bytes are not a valid '{}' message: {}",
+            message.full_name(),
+            e
+        ))
+    })?;
+    if !bytes.is_empty() {
+        return Err(AppError::parse(format!(
+            "{} bytes left after the length-delimited message",
+            bytes.len()
+        )));
+    }
+
+    message_to_json(&decoded, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::test_utils::server::echo_descriptor_pool;
+    use serde_json::json;
+
+    fn echo_response() -> MessageDescriptor {
+        echo_descriptor_pool()
+            .get_message_by_name("test.v1.EchoResponse")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_json_to_binary() {
+        let json = json!({"message": "hi", "index": 7});
+
+        let bytes = json_to_binary(&echo_response(), &json, false).unwrap();
+        assert_eq!(bytes, [0x0a, 0x02, b'h', b'i', 0x10, 0x07]);
+
+        let delimited = json_to_binary(&echo_response(), &json, true).unwrap();
+        assert_eq!(delimited[0], 6);
+        assert_eq!(delimited[1..], bytes);
+    }
+
+    #[test]
+    fn test_json_to_binary_rejects_unknown_fields() {
+        let json = json!({"message": "hi", "unknown": 1});
+
+        assert!(json_to_binary(&echo_response(), &json, false).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = json!({"message": "hi", "index": 7, "sentAt": "1970-01-01T00:00:01Z"});
+
+        for delimited in [false, true] {
+            let bytes = json_to_binary(&echo_response(), &json, delimited).unwrap();
+            let decoded =
+                binary_to_json(&echo_response(), &bytes, delimited, &JsonOptions::default())
+                    .unwrap();
+            assert_eq!(decoded, json);
+        }
+    }
+
+    #[test]
+    fn test_binary_to_json_applies_options() {
+        let options = JsonOptions {
+            emit_defaults: true,
+            ..Default::default()
+        };
+
+        let decoded = binary_to_json(&echo_response(), &[], false, &options).unwrap();
+
+        assert_eq!(decoded["message"], "");
+        assert_eq!(decoded["index"], 0);
+    }
+
+    #[test]
+    fn test_binary_to_json_rejects_invalid_bytes() {
+        let options = JsonOptions::default();
+
+        // Truncated string
+        assert!(binary_to_json(&echo_response(), &[0x0a, 0x05, b'h'], false, &options).is_err());
+        // Length prefix larger than the message
+        assert!(binary_to_json(&echo_response(), &[0x09, 0x10, 0x07], true, &options).is_err());
+        // A second message after the delimited one
+        let error = binary_to_json(&echo_response(), &[0x02, 0x10, 0x07, 0x00], true, &options)
+            .unwrap_err();
+        assert!(error.to_string().contains("1 bytes left"));
+    }
+}
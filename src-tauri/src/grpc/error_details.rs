@@ -0,0 +1,178 @@
+use crate::grpc::json::{message_to_json, JsonOptions};
+use crate::grpc::proto_parser::ProtoParser;
+use base64::{engine::general_purpose, Engine};
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage};
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+use tonic::Status;
+
+/// The standard detail messages of the `google.rpc` error model
+const ERROR_DETAILS_PROTO: &str = include_str!("error_details.proto");
+
+/// `google.rpc.Status`, which servers send encoded in the
+/// `grpc-status-details-bin` trailer
+#[derive(Clone, PartialEq, Message)]
+pub struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(message, repeated, tag = "3")]
+    pub details: Vec<prost_types::Any>,
+}
+
+/// Descriptors of the standard `google.rpc` detail messages
+pub fn error_details_pool() -> &'static DescriptorPool {
+    static POOL: OnceLock<DescriptorPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        ProtoParser::compile(ERROR_DETAILS_PROTO).expect("google.rpc error details should compile")
+    })
+}
+
+/// Decode the rich error details of `status` to JSON objects tagged with their
+/// `@type`, like the JSON form of `Any`. Detail types outside the standard set
+/// keep their encoded payload as base64 under `value`. Known details are
+/// rendered with the `json` options of the call.
+pub fn decode_details(status: &Status, json: &JsonOptions) -> Vec<Value> {
+    if status.details().is_empty() {
+        return Vec::new();
+    }
+
+    match RpcStatus::decode(status.details()) {
+        Ok(rpc_status) => rpc_status
+            .details
+            .iter()
+            .map(|any| decode_any(any, json))
+            .collect(),
+        Err(_) => vec![serde_json::json!({
+            "value": general_purpose::STANDARD.encode(status.details()),
+        })],
+    }
+}
+
+fn decode_any(any: &prost_types::Any, json: &JsonOptions) -> Value {
+    let type_name = any.type_url.rsplit('/').next().unwrap_or_default();
+    let decoded = error_details_pool()
+        .get_message_by_name(type_name)
+        .and_then(|desc| DynamicMessage::decode(desc, any.value.as_slice()).ok())
+        .and_then(|message| message_to_json(&message, json).ok());
+
+    let mut object = Map::new();
+    object.insert("@type".to_string(), Value::String(any.type_url.clone()));
+    match decoded {
+        Some(Value::Object(fields)) => object.extend(fields),
+        _ => {
+            object.insert(
+                "value".to_string(),
+                Value::String(general_purpose::STANDARD.encode(&any.value)),
+            );
+        }
+    }
+
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn detail(type_name: &str, fields: Value) -> prost_types::Any {
+        let desc = error_details_pool().get_message_by_name(type_name).unwrap();
+        prost_types::Any {
+            type_url: format!("type.googleapis.com/{}", type_name),
+            value: DynamicMessage::deserialize(desc, fields)
+                .unwrap()
+                .encode_to_vec(),
+        }
+    }
+
+    fn status_with_details(details: Vec<prost_types::Any>) -> Status {
+        let rpc_status = RpcStatus {
+            code: tonic::Code::InvalidArgument as i32,
+            message: "invalid".to_string(),
+            details,
+        };
+        Status::with_details(
+            tonic::Code::InvalidArgument,
+            "invalid",
+            rpc_status.encode_to_vec().into(),
+        )
+    }
+
+    #[test]
+    fn test_decode_standard_details() {
+        let status = status_with_details(vec![
+            detail(
+                "google.rpc.BadRequest",
+                json!({"fieldViolations": [{"field": "email", "description": "is required"}]}),
+            ),
+            detail("google.rpc.RetryInfo", json!({"retryDelay": "1.500s"})),
+            detail(
+                "google.rpc.ErrorInfo",
+                json!({"reason": "QUOTA", "domain": "example.com", "metadata": {"plan": "free"}}),
+            ),
+        ]);
+
+        let details = decode_details(&status, &JsonOptions::default());
+
+        assert_eq!(
+            details,
+            vec![
+                json!({
+                    "@type": "type.googleapis.com/google.rpc.BadRequest",
+                    "fieldViolations": [{"field": "email", "description": "is required"}],
+                }),
+                json!({
+                    "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                    "retryDelay": "1.500s",
+                }),
+                json!({
+                    "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+                    "reason": "QUOTA",
+                    "domain": "example.com",
+                    "metadata": {"plan": "free"},
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_details_follow_json_options() {
+        let status = status_with_details(vec![detail(
+            "google.rpc.BadRequest",
+            json!({"fieldViolations": [{"field": "email", "description": "is required"}]}),
+        )]);
+        let options = JsonOptions {
+            proto_field_names: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            decode_details(&status, &options),
+            vec![json!({
+                "@type": "type.googleapis.com/google.rpc.BadRequest",
+                "field_violations": [{"field": "email", "description": "is required"}],
+            })]
+        );
+    }
+
+    #[test]
+    fn test_unknown_detail_keeps_encoded_value() {
+        let status = status_with_details(vec![prost_types::Any {
+            type_url: "type.googleapis.com/acme.v1.Custom".to_string(),
+            value: vec![1, 2, 3],
+        }]);
+
+        assert_eq!(
+            decode_details(&status, &JsonOptions::default()),
+            vec![json!({"@type": "type.googleapis.com/acme.v1.Custom", "value": "AQID"})]
+        );
+    }
+
+    #[test]
+    fn test_status_without_details() {
+        assert!(decode_details(&Status::not_found("missing"), &JsonOptions::default()).is_empty());
+    }
+}
@@ -0,0 +1,592 @@
+use crate::error::{AppError, AppResult};
+use crate::grpc::tls::{ClientIdentity, GrpcTlsConfig};
+use crate::grpc::{DescriptorSource, GrpcCallOptions, GrpcCallType, GrpcRequest, GrpcTransport};
+use http::uri::{Scheme, Uri};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A call together with the settings needed to reproduce it, as written in
+/// a `grpcurl` command line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcurlCommand {
+    pub request: GrpcRequest,
+    #[serde(default)]
+    pub descriptor_source: DescriptorSource,
+    pub tls: Option<GrpcTlsConfig>,
+}
+
+/// grpcurl flags taking a value that have no counterpart in a request
+const IGNORED_VALUE_FLAGS: &[&str] = &[
+    "connect-timeout",
+    "keepalive-time",
+    "reflect-header",
+    "user-agent",
+];
+/// grpcurl switches that have no counterpart in a request
+const IGNORED_SWITCHES: &[&str] = &[
+    "v",
+    "vv",
+    "veryverbose",
+    "format-error",
+    "allow-unknown-fields",
+    "use-reflection",
+    "expand-headers",
+];
+
+/// Render `command` as a `grpcurl` invocation, one flag per line.
+///
+/// Settings grpcurl has no flag for, such as gzip or the request size limit,
+/// are left out. Transports other than native gRPC and PKCS#12 client
+/// identities cannot be expressed and are rejected.
+pub fn to_grpcurl(command: &GrpcurlCommand) -> AppResult<String> {
+    let request = &command.request;
+    if request.transport != GrpcTransport::Grpc {
+        return Err(AppError::validation(
+            "transport",
+            "grpcurl only makes native gRPC calls",
+        ));
+    }
+
+    let uri: Uri = request.url.parse()?;
+    let https = uri.scheme() == Some(&Scheme::HTTPS);
+    let host = uri
+        .host()
+        .ok_or_else(|| AppError::validation("url", format!("No host in '{}'", request.url)))?;
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+    let mut flags: Vec<Vec<String>> = Vec::new();
+    if !https {
+        flags.push(vec!["-plaintext".to_string()]);
+    }
+    if let Some(tls) = &command.tls {
+        flags.extend(tls_flags(tls)?);
+    }
+
+    match &command.descriptor_source {
+        DescriptorSource::Reflection => {}
+        DescriptorSource::ProtoFiles {
+            files,
+            import_paths,
+        } => {
+            for path in import_paths {
+                flags.push(vec!["-import-path".to_string(), path.clone()]);
+            }
+            for file in files {
+                flags.push(vec!["-proto".to_string(), file.clone()]);
+            }
+        }
+        DescriptorSource::Protoset { path } => {
+            flags.push(vec!["-protoset".to_string(), path.clone()]);
+        }
+    }
+
+    let options = &request.options;
+    if let Some(timeout_ms) = options.timeout_ms {
+        flags.push(vec![
+            "-max-time".to_string(),
+            (timeout_ms as f64 / 1000.0).to_string(),
+        ]);
+    }
+    if let Some(limit) = options.max_response_size {
+        flags.push(vec!["-max-msg-sz".to_string(), limit.to_string()]);
+    }
+    if options.json.emit_defaults {
+        flags.push(vec!["-emit-defaults".to_string()]);
+    }
+
+    let mut metadata: Vec<_> = request.metadata.iter().flatten().collect();
+    metadata.sort();
+    for (key, value) in metadata {
+        flags.push(vec!["-H".to_string(), format!("{}: {}", key, value)]);
+    }
+
+    if !is_empty_message(&request.message) {
+        flags.push(vec!["-d".to_string(), request.message.to_string()]);
+    }
+
+    flags.push(vec![format!("{}:{}", host, port)]);
+    flags.push(vec![format!("{}/{}", request.service, request.method)]);
+
+    let mut rendered = "grpcurl".to_string();
+    for args in flags {
+        let args: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+        rendered.push_str(" \\\n  ");
+        rendered.push_str(&args.join(" "));
+    }
+    Ok(rendered)
+}
+
+fn tls_flags(tls: &GrpcTlsConfig) -> AppResult<Vec<Vec<String>>> {
+    let mut flags = Vec::new();
+    if let Some(path) = &tls.ca_cert_path {
+        flags.push(vec!["-cacert".to_string(), path.clone()]);
+    }
+    match &tls.client_identity {
+        Some(ClientIdentity::Pem {
+            cert_path,
+            key_path,
+        }) => {
+            flags.push(vec!["-cert".to_string(), cert_path.clone()]);
+            flags.push(vec!["-key".to_string(), key_path.clone()]);
+        }
+        Some(ClientIdentity::Pkcs12 { .. }) => {
+            return Err(AppError::validation(
+                "tls",
+                "grpcurl only reads PEM client certificates",
+            ))
+        }
+        None => {}
+    }
+    if let Some(name) = &tls.server_name {
+        flags.push(vec!["-servername".to_string(), name.clone()]);
+    }
+    if let Some(authority) = &tls.authority {
+        flags.push(vec!["-authority".to_string(), authority.clone()]);
+    }
+    if tls.insecure_skip_verify {
+        flags.push(vec!["-insecure".to_string()]);
+    }
+    Ok(flags)
+}
+
+fn is_empty_message(message: &Value) -> bool {
+    match message {
+        Value::Null => true,
+        Value::Object(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+/// Quote `arg` for a POSIX shell when it holds anything but plain characters
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c));
+    match plain {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace('\'', r"'\''")),
+    }
+}
+
+/// Read a `grpcurl` invocation of a method, as pasted from a shell.
+///
+/// grpcurl does not say which kind of call a method is, so the request is
+/// marked unary until the descriptor of the method is known. Flags that only
+/// affect grpcurl itself, like `-v`, are ignored.
+pub fn parse_grpcurl(command: &str) -> AppResult<GrpcurlCommand> {
+    let invalid = |message: String| AppError::validation("command", message);
+
+    let args = split_args(command)?;
+    let mut args = args.into_iter();
+    match args.next() {
+        Some(program) if program == "grpcurl" || program.ends_with("/grpcurl") => {}
+        _ => return Err(invalid("Not a grpcurl command".to_string())),
+    }
+
+    let mut plaintext = false;
+    let mut data = None;
+    let mut metadata = HashMap::new();
+    let mut files = Vec::new();
+    let mut import_paths = Vec::new();
+    let mut protoset = None;
+    let mut tls = GrpcTlsConfig::default();
+    let mut cert_path = None;
+    let mut key_path = None;
+    let mut options = GrpcCallOptions::default();
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if !positional.is_empty() || !arg.starts_with('-') || arg == "-" {
+            positional.push(arg);
+            continue;
+        }
+        if arg == "--" {
+            positional.extend(args.by_ref());
+            break;
+        }
+
+        let flag = arg.trim_start_matches('-');
+        let (name, inline) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+
+        // Switches are set unless given `=false`
+        let switch = || inline.as_deref() != Some("false");
+        match name {
+            "plaintext" => plaintext = switch(),
+            "insecure" => tls.insecure_skip_verify = switch(),
+            "emit-defaults" => options.json.emit_defaults = switch(),
+            name if IGNORED_SWITCHES.contains(&name) => {}
+            _ => {
+                let value = match inline {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .ok_or_else(|| invalid(format!("Missing value of '{}'", arg)))?,
+                };
+
+                match name {
+                    "d" if value == "@" => {
+                        return Err(invalid(
+                            "Request data read from stdin (-d @) cannot be imported".to_string(),
+                        ))
+                    }
+                    "d" => data = Some(value),
+                    "H" | "rpc-header" => {
+                        let (key, value) = value.split_once(':').ok_or_else(|| {
+                            invalid(format!("Header '{}' is not 'name: value'", value))
+                        })?;
+                        metadata.insert(key.trim().to_string(), value.trim().to_string());
+                    }
+                    "proto" => files.push(value),
+                    "import-path" => import_paths.push(value),
+                    "protoset" if protoset.is_some() => {
+                        return Err(invalid("Only one -protoset can be imported".to_string()))
+                    }
+                    "protoset" => protoset = Some(value),
+                    "cacert" => tls.ca_cert_path = Some(value),
+                    "cert" => cert_path = Some(value),
+                    "key" => key_path = Some(value),
+                    "servername" => tls.server_name = Some(value),
+                    "authority" => tls.authority = Some(value),
+                    "max-time" => {
+                        let seconds: f64 = value
+                            .parse()
+                            .map_err(|_| invalid(format!("Invalid -max-time '{}'", value)))?;
+                        options.timeout_ms = Some((seconds * 1000.0).round() as u64);
+                    }
+                    "max-msg-sz" => {
+                        let limit = value
+                            .parse()
+                            .map_err(|_| invalid(format!("Invalid -max-msg-sz '{}'", value)))?;
+                        options.max_response_size = Some(limit);
+                    }
+                    "format" if value == "json" => {}
+                    "format" => {
+                        return Err(invalid(format!(
+                            "Only JSON request data can be imported, not '{}'",
+                            value
+                        )))
+                    }
+                    name if IGNORED_VALUE_FLAGS.contains(&name) => {}
+                    _ => return Err(invalid(format!("Unsupported grpcurl flag '{}'", arg))),
+                }
+            }
+        }
+    }
+
+    let [address, symbol] = <[String; 2]>::try_from(positional)
+        .map_err(|_| invalid("Expected 'grpcurl [flags] address service/method'".to_string()))?;
+    let (service, method) = symbol
+        .rsplit_once('/')
+        .or_else(|| symbol.rsplit_once('.'))
+        .ok_or_else(|| invalid(format!("'{}' does not name a method", symbol)))?;
+
+    tls.client_identity = match (cert_path, key_path) {
+        (Some(cert_path), Some(key_path)) => Some(ClientIdentity::Pem {
+            cert_path,
+            key_path,
+        }),
+        (None, None) => None,
+        _ => return Err(invalid("-cert and -key must be given together".to_string())),
+    };
+    let tls = (tls != GrpcTlsConfig::default()).then_some(tls);
+    if plaintext && tls.is_some() {
+        return Err(invalid(
+            "-plaintext cannot be combined with TLS flags".to_string(),
+        ));
+    }
+
+    let descriptor_source = match (protoset, files.is_empty()) {
+        (Some(_), false) => {
+            return Err(invalid(
+                "-protoset cannot be combined with -proto".to_string(),
+            ))
+        }
+        (Some(path), true) => DescriptorSource::Protoset { path },
+        (None, false) => DescriptorSource::ProtoFiles {
+            files,
+            import_paths,
+        },
+        (None, true) => DescriptorSource::Reflection,
+    };
+
+    let message = match data {
+        Some(data) => single_message(&data)?,
+        None => Value::Object(Default::default()),
+    };
+
+    Ok(GrpcurlCommand {
+        request: GrpcRequest {
+            url: format!("{}://{}", if plaintext { "http" } else { "https" }, address),
+            service: service.to_string(),
+            method: method.to_string(),
+            message,
+            metadata: (!metadata.is_empty()).then_some(metadata),
+            call_type: GrpcCallType::Unary,
+            options,
+            transport: GrpcTransport::Grpc,
+        },
+        descriptor_source,
+        tls,
+    })
+}
+
+/// The request message of `-d`, which holds one JSON message per request
+/// message of the call
+fn single_message(data: &str) -> AppResult<Value> {
+    let messages = serde_json::Deserializer::from_str(data)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<_>, _>>()?;
+    match <[Value; 1]>::try_from(messages) {
+        Ok([message]) => Ok(message),
+        Err(messages) if messages.is_empty() => Ok(Value::Object(Default::default())),
+        Err(_) => Err(AppError::validation(
+            "command",
+            "Request data with several messages cannot be imported",
+        )),
+    }
+}
+
+/// Split a command line the way a POSIX shell would, honouring quotes,
+/// backslash escapes and line continuations
+fn split_args(command: &str) -> AppResult<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = command.chars().peekable();
+    let unterminated = || AppError::validation("command", "Unterminated quote in command");
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(escaped) => {
+                    current.push(escaped);
+                    in_arg = true;
+                }
+                None => {}
+            },
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next().ok_or_else(unterminated)? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next().ok_or_else(unterminated)? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or_else(unterminated)? {
+                            '\n' => {}
+                            c @ ('"' | '\\' | '$' | '`') => current.push(c),
+                            c => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                        },
+                        c => current.push(c),
+                    }
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(url: &str) -> GrpcRequest {
+        GrpcRequest {
+            url: url.to_string(),
+            service: "test.v1.Echo".to_string(),
+            method: "Echo".to_string(),
+            message: json!({}),
+            metadata: None,
+            call_type: GrpcCallType::Unary,
+            options: Default::default(),
+            transport: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_export_plaintext_call() {
+        let mut request = request("http://localhost:50051");
+        request.message = json!({"message": "it's me"});
+        request.metadata = Some(HashMap::from([
+            ("x-trace".to_string(), "1".to_string()),
+            ("authorization".to_string(), "Bearer abc".to_string()),
+        ]));
+        request.options.timeout_ms = Some(1500);
+        let command = GrpcurlCommand {
+            request,
+            descriptor_source: DescriptorSource::ProtoFiles {
+                files: vec!["echo.proto".to_string()],
+                import_paths: vec!["protos".to_string()],
+            },
+            tls: None,
+        };
+
+        assert_eq!(
+            to_grpcurl(&command).unwrap(),
+            r#"grpcurl \
+  -plaintext \
+  -import-path protos \
+  -proto echo.proto \
+  -max-time 1.5 \
+  -H 'authorization: Bearer abc' \
+  -H 'x-trace: 1' \
+  -d '{"message":"it'\''s me"}' \
+  localhost:50051 \
+  test.v1.Echo/Echo"#
+        );
+    }
+
+    #[test]
+    fn test_export_tls_call() {
+        let command = GrpcurlCommand {
+            request: request("https://api.example.com"),
+            descriptor_source: DescriptorSource::Protoset {
+                path: "api.protoset".to_string(),
+            },
+            tls: Some(GrpcTlsConfig {
+                ca_cert_path: Some("ca.pem".to_string()),
+                client_identity: Some(ClientIdentity::Pem {
+                    cert_path: "client.pem".to_string(),
+                    key_path: "client.key".to_string(),
+                }),
+                server_name: Some("internal".to_string()),
+                insecure_skip_verify: true,
+                ..Default::default()
+            }),
+        };
+
+        assert_eq!(
+            to_grpcurl(&command).unwrap(),
+            "grpcurl \\\n  -cacert ca.pem \\\n  -cert client.pem \\\n  -key client.key \\\n  \
+             -servername internal \\\n  -insecure \\\n  -protoset api.protoset \\\n  \
+             api.example.com:443 \\\n  test.v1.Echo/Echo"
+        );
+    }
+
+    #[test]
+    fn test_export_rejects_what_grpcurl_cannot_do() {
+        let mut command = GrpcurlCommand {
+            request: request("http://localhost:8080"),
+            descriptor_source: DescriptorSource::Reflection,
+            tls: None,
+        };
+        command.request.transport = GrpcTransport::ConnectJson;
+        assert!(to_grpcurl(&command).is_err());
+
+        command.request.transport = GrpcTransport::Grpc;
+        command.tls = Some(GrpcTlsConfig {
+            client_identity: Some(ClientIdentity::Pkcs12 {
+                path: "client.p12".to_string(),
+                password: String::new(),
+            }),
+            ..Default::default()
+        });
+        assert!(to_grpcurl(&command).is_err());
+    }
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let mut request = request("https://localhost:8443");
+        request.message = json!({"message": "a \"quoted\" $value", "count": 2});
+        request.metadata = Some(HashMap::from([(
+            "token-bin".to_string(),
+            "AAEC".to_string(),
+        )]));
+        request.options.max_response_size = Some(1024);
+        request.options.json.emit_defaults = true;
+        let command = GrpcurlCommand {
+            request,
+            descriptor_source: DescriptorSource::Reflection,
+            tls: Some(GrpcTlsConfig {
+                authority: Some("echo.internal".to_string()),
+                ..Default::default()
+            }),
+        };
+
+        let imported = parse_grpcurl(&to_grpcurl(&command).unwrap()).unwrap();
+
+        assert_eq!(imported.request.url, command.request.url);
+        assert_eq!(imported.request.service, "test.v1.Echo");
+        assert_eq!(imported.request.method, "Echo");
+        assert_eq!(imported.request.message, command.request.message);
+        assert_eq!(imported.request.metadata, command.request.metadata);
+        assert_eq!(imported.request.options.max_response_size, Some(1024));
+        assert!(imported.request.options.json.emit_defaults);
+        assert_eq!(imported.descriptor_source, DescriptorSource::Reflection);
+        assert_eq!(imported.tls, command.tls);
+    }
+
+    #[test]
+    fn test_import_shell_syntax() {
+        let imported = parse_grpcurl(
+            "grpcurl -v --plaintext -max-time=2 -H \"authorization: Bearer \\\"x\\\"\" \\\n  \
+             -d '{\"message\": \"hi\"}' -proto=echo.proto localhost:50051 test.v1.Echo.Echo",
+        )
+        .unwrap();
+
+        assert_eq!(imported.request.url, "http://localhost:50051");
+        assert_eq!(imported.request.service, "test.v1.Echo");
+        assert_eq!(imported.request.method, "Echo");
+        assert_eq!(imported.request.message, json!({"message": "hi"}));
+        assert_eq!(
+            imported.request.metadata.unwrap()["authorization"],
+            "Bearer \"x\""
+        );
+        assert_eq!(imported.request.options.timeout_ms, Some(2000));
+        assert_eq!(
+            imported.descriptor_source,
+            DescriptorSource::ProtoFiles {
+                files: vec!["echo.proto".to_string()],
+                import_paths: vec![],
+            }
+        );
+        assert!(imported.tls.is_none());
+    }
+
+    #[test]
+    fn test_import_rejects_unsupported_commands() {
+        for command in [
+            "curl http://localhost",
+            "grpcurl -plaintext localhost:50051 list",
+            "grpcurl -plaintext -d @ localhost:50051 test.v1.Echo/Echo",
+            "grpcurl -plaintext -d '{} {}' localhost:50051 test.v1.Echo/Collect",
+            "grpcurl -plaintext -unknown-flag x localhost:50051 test.v1.Echo/Echo",
+            "grpcurl -plaintext -cacert ca.pem localhost:50051 test.v1.Echo/Echo",
+            "grpcurl -d '{\"message\": \"hi\" localhost:50051 test.v1.Echo/Echo",
+        ] {
+            assert!(parse_grpcurl(command).is_err(), "{}", command);
+        }
+    }
+}
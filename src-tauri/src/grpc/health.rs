@@ -0,0 +1,112 @@
+use http::uri::PathAndQuery;
+use prost::Message;
+use std::time::Duration;
+use tonic::client::Grpc;
+use tonic::codec::ProstCodec;
+use tonic::transport::Channel;
+use tonic::{Request, Status};
+
+pub const CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+pub const WATCH_PATH: &str = "/grpc.health.v1.Health/Watch";
+
+/// `grpc.health.v1.HealthCheckRequest`; an empty service asks about the
+/// server as a whole
+#[derive(Clone, PartialEq, Message)]
+pub struct HealthCheckRequest {
+    #[prost(string, tag = "1")]
+    pub service: String,
+}
+
+/// `grpc.health.v1.HealthCheckResponse`
+#[derive(Clone, PartialEq, Message)]
+pub struct HealthCheckResponse {
+    #[prost(enumeration = "ServingStatus", tag = "1")]
+    pub status: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum ServingStatus {
+    Unknown = 0,
+    Serving = 1,
+    NotServing = 2,
+    /// Only sent by `Watch`; `Check` fails with NOT_FOUND instead
+    ServiceUnknown = 3,
+}
+
+impl ServingStatus {
+    /// Name of the status as declared in `health.proto`
+    pub fn name(self) -> &'static str {
+        match self {
+            ServingStatus::Unknown => "UNKNOWN",
+            ServingStatus::Serving => "SERVING",
+            ServingStatus::NotServing => "NOT_SERVING",
+            ServingStatus::ServiceUnknown => "SERVICE_UNKNOWN",
+        }
+    }
+
+    fn of(response: &HealthCheckResponse) -> Self {
+        ServingStatus::try_from(response.status).unwrap_or(ServingStatus::Unknown)
+    }
+}
+
+/// Ask the server once about the health of `service`
+pub async fn check(
+    channel: Channel,
+    service: &str,
+    timeout: Duration,
+) -> Result<ServingStatus, Status> {
+    let mut grpc = ready(channel).await?;
+    let mut request = Request::new(HealthCheckRequest {
+        service: service.to_string(),
+    });
+    request.set_timeout(timeout);
+
+    let call = grpc.unary(request, PathAndQuery::from_static(CHECK_PATH), codec());
+    let response = tokio::time::timeout(timeout, call)
+        .await
+        .map_err(|_| Status::deadline_exceeded("Health check timed out"))??;
+
+    Ok(ServingStatus::of(response.get_ref()))
+}
+
+/// Start watching the health of `service` and return the first status the
+/// server reports
+pub async fn watch_first(
+    channel: Channel,
+    service: &str,
+    timeout: Duration,
+) -> Result<ServingStatus, Status> {
+    let mut grpc = ready(channel).await?;
+    let request = Request::new(HealthCheckRequest {
+        service: service.to_string(),
+    });
+
+    let first = async {
+        let mut stream = grpc
+            .server_streaming(request, PathAndQuery::from_static(WATCH_PATH), codec())
+            .await?
+            .into_inner();
+        stream
+            .message()
+            .await?
+            .ok_or_else(|| Status::internal("Health watch ended without a status"))
+    };
+    let response = tokio::time::timeout(timeout, first)
+        .await
+        .map_err(|_| Status::deadline_exceeded("Health watch sent no status"))??;
+
+    Ok(ServingStatus::of(&response))
+}
+
+async fn ready(channel: Channel) -> Result<Grpc<Channel>, Status> {
+    let mut grpc = Grpc::new(channel);
+    grpc.ready()
+        .await
+        .map_err(|e| Status::unavailable(format!("Channel not ready: {}", e)))?;
+    Ok(grpc)
+}
+
+fn codec() -> ProstCodec<HealthCheckRequest, HealthCheckResponse> {
+    ProstCodec::default()
+}
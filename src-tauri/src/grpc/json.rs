@@ -0,0 +1,388 @@
+use crate::error::{AppError, AppResult};
+use base64::{engine::general_purpose, Engine};
+use prost_reflect::{
+    DynamicMessage, Kind, MapKey, MessageDescriptor, ReflectMessage, SerializeOptions,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const ANY: &str = "google.protobuf.Any";
+
+/// How messages are rendered in the proto3 JSON mapping
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonOptions {
+    /// Include fields set to their default value
+    pub emit_defaults: bool,
+    /// Render enum values as numbers instead of names
+    pub enums_as_numbers: bool,
+    /// Use the field names of the `.proto` file instead of lowerCamelCase
+    pub proto_field_names: bool,
+    /// Render 64-bit integers as strings, which keeps their precision in
+    /// JavaScript
+    pub int64_as_string: bool,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self {
+            emit_defaults: false,
+            enums_as_numbers: false,
+            proto_field_names: false,
+            int64_as_string: true,
+        }
+    }
+}
+
+impl JsonOptions {
+    fn serialize_options(&self) -> SerializeOptions {
+        SerializeOptions::new()
+            .skip_default_fields(!self.emit_defaults)
+            .use_enum_numbers(self.enums_as_numbers)
+            .use_proto_field_name(self.proto_field_names)
+            .stringify_64_bit_integers(self.int64_as_string)
+    }
+}
+
+/// Render `message` as JSON.
+///
+/// `google.protobuf.Any` payloads are resolved through the descriptor pool of
+/// `message`, i.e. the pool of the endpoint it came from. Payloads of types
+/// missing from the pool are kept as base64 under `value` instead of failing
+/// the whole message.
+pub fn message_to_json(message: &DynamicMessage, options: &JsonOptions) -> AppResult<Value> {
+    JsonRenderer {
+        options,
+        serialize: options.serialize_options(),
+        holds_any: HashMap::new(),
+    }
+    .message(message)
+}
+
+struct JsonRenderer<'a> {
+    options: &'a JsonOptions,
+    serialize: SerializeOptions,
+    /// Whether a message type can contain an `Any`, by full name
+    holds_any: HashMap<String, bool>,
+}
+
+impl JsonRenderer<'_> {
+    fn message(&mut self, message: &DynamicMessage) -> AppResult<Value> {
+        let desc = message.descriptor();
+        if desc.full_name() == ANY {
+            return self.any(message);
+        }
+        if !self.holds_any(&desc) {
+            return Ok(
+                message.serialize_with_options(serde_json::value::Serializer, &self.serialize)?
+            );
+        }
+
+        // Fields that may contain an `Any` are rendered here, the rest by
+        // prost-reflect
+        let mut rest = message.clone();
+        let mut rendered = Vec::new();
+        for field in desc.fields() {
+            let Kind::Message(field_type) = field.kind() else {
+                continue;
+            };
+            if !message.has_field(&field) || !self.holds_any(&field_type) {
+                continue;
+            }
+
+            let name = match self.options.proto_field_names {
+                true => field.name(),
+                false => field.json_name(),
+            };
+            rendered.push((name.to_string(), self.value(&message.get_field(&field))?));
+            rest.clear_field(&field);
+        }
+
+        let mut json =
+            rest.serialize_with_options(serde_json::value::Serializer, &self.serialize)?;
+        if let Value::Object(object) = &mut json {
+            object.extend(rendered);
+        }
+        Ok(json)
+    }
+
+    /// Render the value of a message, list of messages or map of messages
+    fn value(&mut self, value: &prost_reflect::Value) -> AppResult<Value> {
+        match value {
+            prost_reflect::Value::Message(message) => self.message(message),
+            prost_reflect::Value::List(items) => items
+                .iter()
+                .map(|item| self.value(item))
+                .collect::<AppResult<Vec<_>>>()
+                .map(Value::Array),
+            prost_reflect::Value::Map(entries) => {
+                let mut object = Map::new();
+                for (key, value) in entries {
+                    object.insert(map_key(key), self.value(value)?);
+                }
+                Ok(Value::Object(object))
+            }
+            other => Err(AppError::internal(format!(
+                "Expected a message value, got {:?}",
+                other
+            ))),
+        }
+    }
+
+    /// `{"@type": ..., <fields>}`, or `{"@type": ..., "value": ...}` for
+    /// payloads with a JSON form of their own or of an unknown type
+    fn any(&mut self, any: &DynamicMessage) -> AppResult<Value> {
+        let type_url = any
+            .get_field_by_name("type_url")
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let bytes = any
+            .get_field_by_name("value")
+            .and_then(|value| value.as_bytes().cloned())
+            .unwrap_or_default();
+        if type_url.is_empty() {
+            return Ok(Value::Object(Map::new()));
+        }
+
+        let type_name = type_url.rsplit('/').next().unwrap_or_default();
+        let payload = any
+            .descriptor()
+            .parent_pool()
+            .get_message_by_name(type_name)
+            .and_then(|desc| DynamicMessage::decode(desc, bytes.as_ref()).ok());
+
+        let mut object = Map::new();
+        object.insert("@type".to_string(), Value::String(type_url));
+        match payload {
+            Some(payload) => match self.message(&payload)? {
+                Value::Object(fields) if !has_json_form(&payload.descriptor()) => {
+                    object.extend(fields)
+                }
+                value => {
+                    object.insert("value".to_string(), value);
+                }
+            },
+            None => {
+                object.insert(
+                    "value".to_string(),
+                    Value::String(general_purpose::STANDARD.encode(bytes)),
+                );
+            }
+        }
+        Ok(Value::Object(object))
+    }
+
+    /// Whether `desc` or any message type reachable from its fields is `Any`
+    fn holds_any(&mut self, desc: &MessageDescriptor) -> bool {
+        if let Some(holds_any) = self.holds_any.get(desc.full_name()) {
+            return *holds_any;
+        }
+
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([desc.clone()]);
+        let mut holds_any = false;
+        while let Some(next) = queue.pop_front() {
+            if next.full_name() == ANY {
+                holds_any = true;
+                break;
+            }
+            if !seen.insert(next.full_name().to_string()) {
+                continue;
+            }
+            queue.extend(next.fields().filter_map(|field| match field.kind() {
+                Kind::Message(field_type) => Some(field_type),
+                _ => None,
+            }));
+        }
+
+        self.holds_any
+            .insert(desc.full_name().to_string(), holds_any);
+        holds_any
+    }
+}
+
+/// Map keys are always strings in JSON
+fn map_key(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(value) => value.to_string(),
+        MapKey::I32(value) => value.to_string(),
+        MapKey::I64(value) => value.to_string(),
+        MapKey::U32(value) => value.to_string(),
+        MapKey::U64(value) => value.to_string(),
+        MapKey::String(value) => value.clone(),
+    }
+}
+
+/// Well-known types whose JSON form is not an object of their fields. Inside
+/// an `Any` they are nested under `value`.
+fn has_json_form(desc: &MessageDescriptor) -> bool {
+    matches!(
+        desc.full_name(),
+        "google.protobuf.Any"
+            | "google.protobuf.Timestamp"
+            | "google.protobuf.Duration"
+            | "google.protobuf.FieldMask"
+            | "google.protobuf.Struct"
+            | "google.protobuf.Value"
+            | "google.protobuf.ListValue"
+            | "google.protobuf.Empty"
+            | "google.protobuf.DoubleValue"
+            | "google.protobuf.FloatValue"
+            | "google.protobuf.Int64Value"
+            | "google.protobuf.UInt64Value"
+            | "google.protobuf.Int32Value"
+            | "google.protobuf.UInt32Value"
+            | "google.protobuf.BoolValue"
+            | "google.protobuf.StringValue"
+            | "google.protobuf.BytesValue"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::proto_parser::ProtoParser;
+    use crate::grpc::test_utils::fixtures;
+    use prost::Message;
+    use prost_reflect::DescriptorPool;
+    use serde_json::json;
+
+    fn pool() -> DescriptorPool {
+        ProtoParser::compile(fixtures::any_fields_proto()).unwrap()
+    }
+
+    fn message(name: &str, json: Value) -> DynamicMessage {
+        let desc = pool().get_message_by_name(name).unwrap();
+        DynamicMessage::deserialize(desc, json).unwrap()
+    }
+
+    /// An `Any` holding `bytes` as `type_url`, which may be unknown to the pool
+    fn pack(type_url: &str, bytes: Vec<u8>) -> prost_reflect::Value {
+        let mut any = DynamicMessage::new(pool().get_message_by_name(ANY).unwrap());
+        any.set_field_by_name("type_url", prost_reflect::Value::String(type_url.into()));
+        any.set_field_by_name("value", prost_reflect::Value::Bytes(bytes.into()));
+        prost_reflect::Value::Message(any)
+    }
+
+    #[test]
+    fn test_default_options_match_canonical_json() {
+        let note = message("wrap.v1.Note", json!({"stars": "5"}));
+
+        let json = message_to_json(&note, &JsonOptions::default()).unwrap();
+
+        assert_eq!(json, serde_json::to_value(&note).unwrap());
+        assert_eq!(json, json!({"stars": "5"}));
+    }
+
+    #[test]
+    fn test_serialization_options() {
+        let envelope = message(
+            "wrap.v1.Envelope",
+            json!({"displayName": "box", "size": "7", "kind": "KIND_NOTE"}),
+        );
+        let options = JsonOptions {
+            emit_defaults: true,
+            enums_as_numbers: true,
+            proto_field_names: true,
+            int64_as_string: false,
+        };
+
+        let json = message_to_json(&envelope, &options).unwrap();
+
+        assert_eq!(json["display_name"], "box");
+        assert_eq!(json["size"], 7);
+        assert_eq!(json["kind"], 1);
+        assert_eq!(json["id"], "");
+        assert_eq!(json["items"], json!([]));
+    }
+
+    #[test]
+    fn test_any_payloads_are_resolved() {
+        let note = message("wrap.v1.Note", json!({"text": "hi", "stars": "2"}));
+        let mut holder = DynamicMessage::new(pool().get_message_by_name("wrap.v1.Holder").unwrap());
+        holder.set_field_by_name(
+            "value",
+            pack("type.googleapis.com/wrap.v1.Note", note.encode_to_vec()),
+        );
+        let timestamp = prost_types::Timestamp {
+            seconds: 1,
+            nanos: 0,
+        };
+
+        let mut envelope =
+            DynamicMessage::new(pool().get_message_by_name("wrap.v1.Envelope").unwrap());
+        envelope.set_field_by_name(
+            "payload",
+            pack("type.googleapis.com/wrap.v1.Note", note.encode_to_vec()),
+        );
+        envelope.set_field_by_name(
+            "items",
+            prost_reflect::Value::List(vec![pack(
+                "type.googleapis.com/google.protobuf.Timestamp",
+                timestamp.encode_to_vec(),
+            )]),
+        );
+        envelope.set_field_by_name(
+            "holders",
+            prost_reflect::Value::Map(HashMap::from([(
+                MapKey::String("a".to_string()),
+                prost_reflect::Value::Message(holder),
+            )])),
+        );
+
+        let json = message_to_json(&envelope, &JsonOptions::default()).unwrap();
+
+        assert_eq!(
+            json["payload"],
+            json!({"@type": "type.googleapis.com/wrap.v1.Note", "text": "hi", "stars": "2"})
+        );
+        assert_eq!(
+            json["items"],
+            json!([{
+                "@type": "type.googleapis.com/google.protobuf.Timestamp",
+                "value": "1970-01-01T00:00:01Z",
+            }])
+        );
+        assert_eq!(json["holders"]["a"]["value"]["text"], "hi");
+    }
+
+    #[test]
+    fn test_unknown_any_type_keeps_payload() {
+        let mut envelope =
+            DynamicMessage::new(pool().get_message_by_name("wrap.v1.Envelope").unwrap());
+        envelope.set_field_by_name("id", prost_reflect::Value::String("e1".into()));
+        envelope.set_field_by_name(
+            "payload",
+            pack("type.googleapis.com/other.v1.Missing", vec![1, 2, 3]),
+        );
+        assert!(serde_json::to_value(&envelope).is_err());
+
+        let json = message_to_json(&envelope, &JsonOptions::default()).unwrap();
+
+        assert_eq!(json["id"], "e1");
+        assert_eq!(
+            json["payload"],
+            json!({"@type": "type.googleapis.com/other.v1.Missing", "value": "AQID"})
+        );
+    }
+
+    #[test]
+    fn test_options_apply_inside_any() {
+        let note = message("wrap.v1.Note", json!({"stars": "3"}));
+        let mut envelope =
+            DynamicMessage::new(pool().get_message_by_name("wrap.v1.Envelope").unwrap());
+        envelope.set_field_by_name(
+            "payload",
+            pack("type.googleapis.com/wrap.v1.Note", note.encode_to_vec()),
+        );
+        let options = JsonOptions {
+            int64_as_string: false,
+            ..Default::default()
+        };
+
+        let json = message_to_json(&envelope, &options).unwrap();
+
+        assert_eq!(json["payload"]["stars"], 3);
+    }
+}
@@ -1,15 +1,42 @@
+pub mod breaking;
+pub mod channel;
 pub mod client;
+pub mod codec;
+pub mod convert;
+pub mod error_details;
+pub mod grpcurl;
+pub mod health;
+pub mod json;
 pub mod reflection;
 pub mod proto_parser;
+pub mod probe;
+pub mod schema;
+pub mod state;
 pub mod streaming;
+pub mod template;
+pub mod tls;
+pub mod transport;
+pub mod wire;
 pub mod commands;
 
 #[cfg(test)]
 pub mod test_utils;
 
+use crate::error::{AppError, AppResult};
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use tonic::metadata::{MetadataMap, MetadataKey, MetadataValue};
-use tonic::Request;
+use std::collections::HashMap;
+use std::time::Duration;
+use tonic::metadata::{Ascii, Binary, KeyAndValueRef, MetadataKey, MetadataMap, MetadataValue};
+use tonic::{Request, Status};
+
+pub use json::JsonOptions;
+pub use schema::{
+    ProtoCardinality, ProtoComments, ProtoEnum, ProtoEnumValue, ProtoField, ProtoMapType,
+    ProtoMessage, ProtoMethod, ProtoOneof, ProtoSchema, ProtoService,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GrpcCallType {
@@ -19,6 +46,18 @@ pub enum GrpcCallType {
     Bidirectional,
 }
 
+impl GrpcCallType {
+    /// Call type implied by the streaming flags of a method
+    pub fn of(method: &prost_reflect::MethodDescriptor) -> Self {
+        match (method.is_client_streaming(), method.is_server_streaming()) {
+            (false, false) => GrpcCallType::Unary,
+            (false, true) => GrpcCallType::ServerStreaming,
+            (true, false) => GrpcCallType::ClientStreaming,
+            (true, true) => GrpcCallType::Bidirectional,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrpcRequest {
     pub url: String,
@@ -27,6 +66,52 @@ pub struct GrpcRequest {
     pub message: serde_json::Value,
     pub metadata: Option<std::collections::HashMap<String, String>>,
     pub call_type: GrpcCallType,
+    #[serde(default)]
+    pub options: GrpcCallOptions,
+    #[serde(default)]
+    pub transport: GrpcTransport,
+}
+
+/// Protocol a call is carried over. Only native gRPC runs over HTTP/2; the
+/// other transports go through the shared HTTP client and cannot stream in
+/// both directions at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrpcTransport {
+    #[default]
+    Grpc,
+    /// gRPC-Web with binary frames (`application/grpc-web+proto`), as served
+    /// by Envoy
+    GrpcWeb,
+    /// gRPC-Web with base64 encoded frames (`application/grpc-web-text`)
+    GrpcWebText,
+    /// Connect protocol with JSON messages
+    ConnectJson,
+    /// Connect protocol with binary protobuf messages
+    ConnectProto,
+}
+
+/// Per-call settings shared by every call type
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GrpcCallOptions {
+    /// Deadline of the whole call, from sending the request to the final status
+    pub timeout_ms: Option<u64>,
+    /// Compress messages with gzip in both directions
+    #[serde(default)]
+    pub gzip: bool,
+    /// Largest response message accepted, 4 MB when unset
+    pub max_response_size: Option<usize>,
+    /// Largest request message sent, unlimited when unset
+    pub max_request_size: Option<usize>,
+    /// How response messages are rendered as JSON
+    #[serde(default)]
+    pub json: JsonOptions,
+}
+
+impl GrpcCallOptions {
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,67 +121,233 @@ pub struct GrpcResponse {
     pub error: Option<String>,
     pub status_code: Option<u32>,
     pub status_message: Option<String>,
+    /// Initial metadata the server sent before the response message, with
+    /// every value of repeated keys
+    #[serde(default)]
+    pub headers: HashMap<String, Vec<String>>,
+    /// Trailing metadata the server sent with the final status
+    #[serde(default)]
+    pub trailers: HashMap<String, Vec<String>>,
+    /// Rich error details of a failed call, e.g. `google.rpc.BadRequest`
+    #[serde(default)]
+    pub details: Vec<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtoService {
-    pub name: String,
-    pub methods: Vec<ProtoMethod>,
-}
+impl GrpcResponse {
+    pub fn ok(data: serde_json::Value) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+            status_code: Some(tonic::Code::Ok as u32),
+            status_message: Some(status_code_name(tonic::Code::Ok).to_string()),
+            headers: HashMap::new(),
+            trailers: HashMap::new(),
+            details: Vec::new(),
+        }
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtoMethod {
-    pub name: String,
-    pub input_type: String,
-    pub output_type: String,
-    pub is_client_streaming: bool,
-    pub is_server_streaming: bool,
+    /// Build a response from a gRPC status, typically a failed call. The
+    /// metadata of the status becomes the trailers and its details are
+    /// rendered with the `json` options of the call.
+    pub fn from_status(status: &Status, json: &JsonOptions) -> Self {
+        Self {
+            success: status.code() == tonic::Code::Ok,
+            data: None,
+            error: (status.code() != tonic::Code::Ok).then(|| status.message().to_string()),
+            status_code: Some(status.code() as u32),
+            status_message: Some(status_code_name(status.code()).to_string()),
+            headers: HashMap::new(),
+            trailers: metadata_to_map(status.metadata()),
+            details: error_details::decode_details(status, json),
+        }
+    }
+
+    /// Response of a call that failed on the client side, or with a status
+    /// that surfaced as an error
+    pub fn from_error(error: &AppError) -> Self {
+        let (status_code, status_message, details) = match error {
+            AppError::GrpcError {
+                code,
+                status,
+                details,
+                ..
+            } => (Some(*code), Some(status.clone()), details.clone()),
+            _ => (None, None, Vec::new()),
+        };
+
+        Self {
+            success: false,
+            data: None,
+            error: Some(error.to_string()),
+            status_code,
+            status_message,
+            headers: HashMap::new(),
+            trailers: HashMap::new(),
+            details,
+        }
+    }
+
+    pub fn with_headers(mut self, headers: &MetadataMap) -> Self {
+        self.headers = metadata_to_map(headers);
+        self
+    }
+
+    pub fn with_trailers(mut self, trailers: &MetadataMap) -> Self {
+        self.trailers = metadata_to_map(trailers);
+        self
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtoSchema {
-    pub services: Vec<ProtoService>,
-    pub messages: Vec<ProtoMessage>,
+/// Canonical upper-case name of a gRPC status code, e.g. "NOT_FOUND"
+pub fn status_code_name(code: tonic::Code) -> &'static str {
+    match code {
+        tonic::Code::Ok => "OK",
+        tonic::Code::Cancelled => "CANCELLED",
+        tonic::Code::Unknown => "UNKNOWN",
+        tonic::Code::InvalidArgument => "INVALID_ARGUMENT",
+        tonic::Code::DeadlineExceeded => "DEADLINE_EXCEEDED",
+        tonic::Code::NotFound => "NOT_FOUND",
+        tonic::Code::AlreadyExists => "ALREADY_EXISTS",
+        tonic::Code::PermissionDenied => "PERMISSION_DENIED",
+        tonic::Code::ResourceExhausted => "RESOURCE_EXHAUSTED",
+        tonic::Code::FailedPrecondition => "FAILED_PRECONDITION",
+        tonic::Code::Aborted => "ABORTED",
+        tonic::Code::OutOfRange => "OUT_OF_RANGE",
+        tonic::Code::Unimplemented => "UNIMPLEMENTED",
+        tonic::Code::Internal => "INTERNAL",
+        tonic::Code::Unavailable => "UNAVAILABLE",
+        tonic::Code::DataLoss => "DATA_LOSS",
+        tonic::Code::Unauthenticated => "UNAUTHENTICATED",
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtoMessage {
-    pub name: String,
-    pub fields: Vec<ProtoField>,
+/// Where a client gets the descriptors of the services it calls
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DescriptorSource {
+    /// Ask the server through gRPC server reflection
+    #[default]
+    Reflection,
+    /// Compile local `.proto` files. Imports are resolved against `import_paths`,
+    /// or against the directory of each file when none are given.
+    ProtoFiles {
+        files: Vec<String>,
+        #[serde(default)]
+        import_paths: Vec<String>,
+    },
+    /// Read a compiled `FileDescriptorSet` (`.protoset`/`.pb`), e.g. from
+    /// `protoc --include_imports --descriptor_set_out`
+    Protoset { path: String },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtoField {
-    pub name: String,
-    pub field_type: String,
-    pub number: u32,
-    pub repeated: bool,
+impl DescriptorSource {
+    /// Load the descriptor pool of a local source, `None` for reflection
+    pub fn load_pool(&self) -> crate::error::AppResult<Option<prost_reflect::DescriptorPool>> {
+        match self {
+            DescriptorSource::Reflection => Ok(None),
+            DescriptorSource::ProtoFiles {
+                files,
+                import_paths,
+            } => proto_parser::ProtoParser::compile_files(files, import_paths).map(Some),
+            DescriptorSource::Protoset { path } => {
+                proto_parser::ProtoParser::load_descriptor_set(path).map(Some)
+            }
+        }
+    }
 }
 
-pub fn create_metadata_map(metadata: &std::collections::HashMap<String, String>) -> MetadataMap {
+/// Base64 as used by `-bin` metadata: standard alphabet, padding optional
+const METADATA_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Build request metadata. Keys ending in `-bin` carry binary values given as
+/// base64; all other values must be visible ASCII.
+pub fn create_metadata_map(metadata: &HashMap<String, String>) -> AppResult<MetadataMap> {
     let mut map = MetadataMap::new();
 
     for (key, value) in metadata {
-        if let Ok(key) = key.parse::<MetadataKey<tonic::metadata::Ascii>>() {
-            if let Ok(value) = value.parse::<MetadataValue<tonic::metadata::Ascii>>() {
-                let _ = map.insert(key, value);
-            }
+        let invalid = |reason: String| {
+            AppError::validation("metadata", format!("Invalid metadata '{}': {}", key, reason))
+        };
+
+        if key.to_ascii_lowercase().ends_with("-bin") {
+            let name = MetadataKey::<Binary>::from_bytes(key.as_bytes())
+                .map_err(|e| invalid(e.to_string()))?;
+            let bytes = METADATA_BASE64
+                .decode(value)
+                .map_err(|e| invalid(format!("value is not valid base64: {}", e)))?;
+            map.append_bin(name, MetadataValue::from_bytes(&bytes));
+        } else {
+            let name = MetadataKey::<Ascii>::from_bytes(key.as_bytes())
+                .map_err(|e| invalid(e.to_string()))?;
+            let value = value
+                .parse::<MetadataValue<Ascii>>()
+                .map_err(|e| invalid(e.to_string()))?;
+            map.append(name, value);
         }
     }
 
+    Ok(map)
+}
+
+/// Collect the entries of a metadata map, e.g. response trailers. Binary
+/// values are base64 encoded and repeated keys keep their values in the order
+/// they were sent.
+pub fn metadata_to_map(metadata: &MetadataMap) -> HashMap<String, Vec<String>> {
+    let mut map: HashMap<String, Vec<String>> = HashMap::new();
+
+    for entry in metadata.iter() {
+        let (key, value) = match entry {
+            KeyAndValueRef::Ascii(key, value) => (
+                key.as_str(),
+                String::from_utf8_lossy(value.as_encoded_bytes()).to_string(),
+            ),
+            KeyAndValueRef::Binary(key, value) => match value.to_bytes() {
+                Ok(bytes) => (key.as_str(), METADATA_BASE64.encode(bytes)),
+                // Keep what the server sent when it is not valid base64 itself
+                Err(_) => (
+                    key.as_str(),
+                    String::from_utf8_lossy(value.as_encoded_bytes()).to_string(),
+                ),
+            },
+        };
+
+        map.entry(key.to_string()).or_default().push(value);
+    }
+
     map
 }
 
 pub fn create_tonic_request<T>(
     message: T,
-    metadata: Option<std::collections::HashMap<String, String>>,
-) -> Request<T> {
+    metadata: Option<HashMap<String, String>>,
+) -> AppResult<Request<T>> {
     let mut request = Request::new(message);
 
     if let Some(metadata_map) = metadata {
-        let tonic_metadata = create_metadata_map(&metadata_map);
-        *request.metadata_mut() = tonic_metadata;
+        *request.metadata_mut() = create_metadata_map(&metadata_map)?;
     }
 
-    request
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_to_map_keeps_repeated_values() {
+        let mut metadata = MetadataMap::new();
+        metadata.append("x-cost", "1".parse().unwrap());
+        metadata.append("x-cost", "2, 3".parse().unwrap());
+        metadata.append_bin("x-trace-bin", MetadataValue::from_bytes(&[0, 1]));
+
+        let map = metadata_to_map(&metadata);
+
+        assert_eq!(map["x-cost"], ["1", "2, 3"]);
+        assert_eq!(map["x-trace-bin"], ["AAE="]);
+    }
 }
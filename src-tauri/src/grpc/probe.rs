@@ -0,0 +1,357 @@
+use crate::client::proxy::ProxySettings;
+use crate::grpc::channel::tls_session;
+use crate::grpc::health::{self, ServingStatus};
+use crate::grpc::reflection::{supports_reflection, ReflectionVersion};
+use crate::grpc::state::GrpcState;
+use crate::grpc::status_code_name;
+use crate::grpc::tls::{GrpcTlsConfig, TlsSessionInfo};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+
+/// How long each call of the probe may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Latency samples taken when no count is given
+pub const DEFAULT_PINGS: u32 = 3;
+/// Upper bound on latency samples, so a probe stays quick
+const MAX_PINGS: u32 = 20;
+
+/// What to check on the server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProbeOptions {
+    /// Service to ask the health service about; empty for the whole server
+    #[serde(default)]
+    pub service: String,
+    /// Also start a `Health/Watch` call and report its first status
+    #[serde(default)]
+    pub watch: bool,
+    /// Number of round trips measured for the latency
+    pub pings: Option<u32>,
+}
+
+/// Connection diagnostic of a gRPC server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerProbe {
+    pub connected: bool,
+    pub message: String,
+    /// Time to open the connection, TLS handshake included
+    pub connect_ms: Option<f64>,
+    pub health: Option<HealthReport>,
+    pub reflection: ReflectionSupport,
+    /// Negotiated TLS session, for `https://` URLs
+    pub tls: Option<TlsSessionInfo>,
+    pub latency: Option<LatencyStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub service: String,
+    pub check: HealthStatus,
+    /// First status sent by `Watch`, when it was requested
+    pub watch: Option<HealthStatus>,
+}
+
+/// Answer of the health service: a serving status, or why there is none
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    /// SERVING, NOT_SERVING, UNKNOWN or SERVICE_UNKNOWN
+    pub status: Option<String>,
+    pub error: Option<String>,
+}
+
+impl HealthStatus {
+    fn from_result(result: Result<ServingStatus, Status>) -> Self {
+        match result {
+            Ok(status) => Self {
+                status: Some(status.name().to_string()),
+                error: None,
+            },
+            // `Check` reports services it doesn't know with NOT_FOUND
+            Err(status) if status.code() == Code::NotFound => Self {
+                status: Some(ServingStatus::ServiceUnknown.name().to_string()),
+                error: Some(status.message().to_string()),
+            },
+            Err(status) => Self {
+                status: None,
+                error: Some(format!(
+                    "{}: {}",
+                    status_code_name(status.code()),
+                    status.message()
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReflectionSupport {
+    pub v1: bool,
+    pub v1alpha: bool,
+}
+
+/// Round-trip times of health check calls, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub samples: u32,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    fn from_samples(samples: &[Duration]) -> Option<Self> {
+        let millis: Vec<f64> = samples.iter().map(|sample| as_millis(*sample)).collect();
+        if millis.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            samples: millis.len() as u32,
+            min_ms: millis.iter().copied().fold(f64::INFINITY, f64::min),
+            avg_ms: millis.iter().sum::<f64>() / millis.len() as f64,
+            max_ms: millis.iter().copied().fold(0.0, f64::max),
+        })
+    }
+}
+
+/// Open a fresh connection to `url`, which then replaces the pooled one, and
+/// check the health, reflection support, TLS session and latency of the server
+pub async fn probe_server(
+    state: &GrpcState,
+    url: &str,
+    tls: Option<&GrpcTlsConfig>,
+    proxy: &ProxySettings,
+    options: &ProbeOptions,
+) -> ServerProbe {
+    let start = Instant::now();
+    let channel = match state.reconnect(url, tls, proxy).await {
+        Ok(channel) => channel,
+        Err(e) => {
+            return ServerProbe {
+                connected: false,
+                message: format!("Failed to connect: {}", e),
+                connect_ms: None,
+                health: None,
+                reflection: ReflectionSupport::default(),
+                tls: None,
+                latency: None,
+            }
+        }
+    };
+    let connect_ms = as_millis(start.elapsed());
+
+    let check = health::check(channel.clone(), &options.service, PROBE_TIMEOUT).await;
+    let watch = match options.watch {
+        true => Some(HealthStatus::from_result(
+            health::watch_first(channel.clone(), &options.service, PROBE_TIMEOUT).await,
+        )),
+        false => None,
+    };
+    let health = HealthReport {
+        service: options.service.clone(),
+        check: HealthStatus::from_result(check),
+        watch,
+    };
+
+    let reflection = ReflectionSupport {
+        v1: reflection_available(channel.clone(), ReflectionVersion::V1).await,
+        v1alpha: reflection_available(channel.clone(), ReflectionVersion::V1Alpha).await,
+    };
+
+    let tls = match url.starts_with("https://") {
+        true => tls_session(url, tls, proxy).await.ok(),
+        false => None,
+    };
+
+    let pings = options.pings.unwrap_or(DEFAULT_PINGS).clamp(1, MAX_PINGS);
+    let latency = LatencyStats::from_samples(&ping(channel, &options.service, pings).await);
+
+    ServerProbe {
+        connected: true,
+        message: "Successfully connected to gRPC server".to_string(),
+        connect_ms: Some(connect_ms),
+        health: Some(health),
+        reflection,
+        tls,
+        latency,
+    }
+}
+
+async fn reflection_available(channel: Channel, version: ReflectionVersion) -> bool {
+    tokio::time::timeout(PROBE_TIMEOUT, supports_reflection(channel, version))
+        .await
+        .unwrap_or(false)
+}
+
+/// Time `count` health checks one after the other. Any answer of the server
+/// counts, an UNIMPLEMENTED status included; calls that never reached it don't.
+async fn ping(channel: Channel, service: &str, count: u32) -> Vec<Duration> {
+    let mut samples = Vec::new();
+    for _ in 0..count {
+        let start = Instant::now();
+        match health::check(channel.clone(), service, PROBE_TIMEOUT).await {
+            Err(status) if matches!(status.code(), Code::Unavailable | Code::DeadlineExceeded) => {}
+            _ => samples.push(start.elapsed()),
+        }
+    }
+    samples
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::test_utils::pki::{write_file, TestPki};
+    use crate::grpc::test_utils::server::TestServer;
+
+    async fn probe(url: &str, tls: Option<&GrpcTlsConfig>, options: ProbeOptions) -> ServerProbe {
+        probe_server(
+            &GrpcState::default(),
+            url,
+            tls,
+            &ProxySettings::direct(),
+            &options,
+        )
+        .await
+    }
+
+    fn serving() -> HealthStatus {
+        HealthStatus {
+            status: Some("SERVING".to_string()),
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_health_reflection_and_latency() {
+        let server = TestServer::start().await;
+
+        let report = probe(&server.url, None, ProbeOptions::default()).await;
+
+        assert!(report.connected);
+        assert!(report.connect_ms.is_some());
+        let health = report.health.unwrap();
+        assert_eq!(health.service, "");
+        assert_eq!(health.check, serving());
+        assert!(health.watch.is_none());
+        assert_eq!(
+            report.reflection,
+            ReflectionSupport {
+                v1: false,
+                v1alpha: true
+            }
+        );
+        assert!(report.tls.is_none());
+        let latency = report.latency.unwrap();
+        assert_eq!(latency.samples, DEFAULT_PINGS);
+        assert!(latency.min_ms <= latency.avg_ms && latency.avg_ms <= latency.max_ms);
+    }
+
+    #[tokio::test]
+    async fn test_probe_detects_reflection_v1() {
+        let server = TestServer::start_with_reflection_v1().await;
+
+        let report = probe(&server.url, None, ProbeOptions::default()).await;
+
+        assert_eq!(
+            report.reflection,
+            ReflectionSupport {
+                v1: true,
+                v1alpha: false
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_without_health_service() {
+        let server = TestServer::start_without_reflection().await;
+        let options = ProbeOptions {
+            pings: Some(2),
+            ..Default::default()
+        };
+
+        let report = probe(&server.url, None, options).await;
+
+        assert!(report.connected);
+        let check = report.health.unwrap().check;
+        assert_eq!(check.status, None);
+        assert!(check.error.unwrap().starts_with("UNIMPLEMENTED"));
+        assert_eq!(report.reflection, ReflectionSupport::default());
+        // The server still answered each ping
+        assert_eq!(report.latency.unwrap().samples, 2);
+    }
+
+    #[tokio::test]
+    async fn test_probe_checks_named_service_and_watches() {
+        let server = TestServer::start().await;
+
+        let report = probe(
+            &server.url,
+            None,
+            ProbeOptions {
+                service: "test.v1.Echo".to_string(),
+                watch: true,
+                pings: Some(1),
+            },
+        )
+        .await;
+        let health = report.health.unwrap();
+        assert_eq!(health.check, serving());
+        assert_eq!(health.watch, Some(serving()));
+
+        let report = probe(
+            &server.url,
+            None,
+            ProbeOptions {
+                service: "test.v1.Missing".to_string(),
+                watch: true,
+                pings: Some(1),
+            },
+        )
+        .await;
+        let health = report.health.unwrap();
+        assert_eq!(health.check.status.as_deref(), Some("SERVICE_UNKNOWN"));
+        assert_eq!(
+            health.watch.unwrap().status.as_deref(),
+            Some("SERVICE_UNKNOWN")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_tls_session() {
+        let pki = TestPki::generate();
+        let server = TestServer::start_tls(&pki, false).await;
+        let dir = tempfile::tempdir().unwrap();
+        let tls = GrpcTlsConfig {
+            ca_cert_path: Some(write_file(dir.path(), "ca.pem", &pki.ca_pem)),
+            ..Default::default()
+        };
+
+        let report = probe(&server.url, Some(&tls), ProbeOptions::default()).await;
+
+        assert!(report.connected);
+        let session = report.tls.unwrap();
+        assert_eq!(session.alpn_protocol.as_deref(), Some("h2"));
+        assert_eq!(session.protocol_version.as_deref(), Some("TLSv1_3"));
+        assert!(session.cipher_suite.is_some());
+        assert!(session.peer_certificates > 0);
+    }
+
+    #[tokio::test]
+    async fn test_probe_unreachable_server() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let report = probe(&url, None, ProbeOptions::default()).await;
+
+        assert!(!report.connected);
+        assert!(report.message.starts_with("Failed to connect"));
+        assert!(report.health.is_none());
+        assert!(report.latency.is_none());
+    }
+}
@@ -1,180 +1,130 @@
-use crate::error::AppResult;
-use crate::grpc::{ProtoField, ProtoMessage, ProtoMethod, ProtoSchema, ProtoService};
+use crate::error::{AppError, AppResult, SourceLocation};
+use crate::grpc::ProtoSchema;
+use miette::Diagnostic;
+use prost_reflect::DescriptorPool;
+use protox::file::{ChainFileResolver, File, FileResolver, GoogleFileResolver};
+use std::path::{Path, PathBuf};
+
+/// Name given to proto text that doesn't come from a file on disk
+const SOURCE_NAME: &str = "input.proto";
 
 pub struct ProtoParser;
 
 impl ProtoParser {
     pub fn parse_proto_file(content: &str) -> AppResult<ProtoSchema> {
-        // Simple proto file parser
-        // This is a basic implementation - in production you'd want a more robust parser
-
-        let mut services = Vec::new();
-        let mut messages = Vec::new();
-
-        let lines: Vec<&str> = content.lines().collect();
-        let mut current_service: Option<ProtoService> = None;
-        let mut current_message: Option<ProtoMessage> = None;
-
-        for line in lines {
-            let trimmed = line.trim();
-
-            if trimmed.starts_with("service ") {
-                // Start of a new service
-                if let Some(service) = current_service.take() {
-                    services.push(service);
-                }
-
-                let service_name = trimmed
-                    .strip_prefix("service ")
-                    .and_then(|s| s.strip_suffix(" {"))
-                    .unwrap_or("UnknownService")
-                    .to_string();
-
-                current_service = Some(ProtoService {
-                    name: service_name,
-                    methods: Vec::new(),
-                });
-            } else if trimmed.starts_with("message ") {
-                // Start of a new message
-                if let Some(message) = current_message.take() {
-                    messages.push(message);
-                }
+        let pool = Self::compile(content)?;
+        let file = pool
+            .get_file_by_name(SOURCE_NAME)
+            .ok_or_else(|| AppError::internal("Compiled proto file missing from pool"))?;
 
-                let message_name = trimmed
-                    .strip_prefix("message ")
-                    .and_then(|s| s.strip_suffix(" {"))
-                    .unwrap_or("UnknownMessage")
-                    .to_string();
-
-                current_message = Some(ProtoMessage {
-                    name: message_name,
-                    fields: Vec::new(),
-                });
-            } else if trimmed.starts_with("rpc ") && current_service.is_some() {
-                // Parse RPC method
-                if let Some(service) = &mut current_service {
-                    if let Some(method) = Self::parse_rpc_method(trimmed) {
-                        service.methods.push(method);
-                    }
-                }
-            } else if trimmed.contains("=") && current_message.is_some() {
-                // Parse message field
-                if let Some(message) = &mut current_message {
-                    if let Some(field) = Self::parse_field(trimmed) {
-                        message.fields.push(field);
-                    }
-                }
-            }
-        }
-
-        // Add the last service and message
-        if let Some(service) = current_service {
-            services.push(service);
-        }
-        if let Some(message) = current_message {
-            messages.push(message);
-        }
-
-        Ok(ProtoSchema { services, messages })
+        Ok(ProtoSchema::from_files([file]))
     }
 
-    fn parse_rpc_method(line: &str) -> Option<ProtoMethod> {
-        // Parse: rpc GetUser(GetUserRequest) returns (GetUserResponse);
-        // or: rpc ListUsers(ListUsersRequest) returns (stream UserResponse);
+    /// Compile proto source into a descriptor pool that can drive dynamic calls.
+    ///
+    /// Imports of the well-known `google/protobuf/*.proto` types are resolved;
+    /// any other import is reported as an error.
+    ///
+    /// Source without a `syntax` declaration is proto2, which rejects fields
+    /// without a label. When it only compiles as proto3, it is read as proto3.
+    pub fn compile(content: &str) -> AppResult<DescriptorPool> {
+        compile_source(content.to_string()).or_else(|err| {
+            // Kept on the first line, so reported lines stay the same
+            compile_source(format!("syntax = \"proto3\"; {}", content)).map_err(|_| err)
+        })
+    }
 
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 4 {
-            return None;
+    /// Compile `.proto` files from disk into a single descriptor pool.
+    ///
+    /// Imports are looked up in `import_paths`; when none are given, the
+    /// directory of each file is used instead.
+    pub fn compile_files(files: &[String], import_paths: &[String]) -> AppResult<DescriptorPool> {
+        if files.is_empty() {
+            return Err(AppError::validation("files", "No .proto files given"));
         }
 
-        // Extract method name (parts[1] might be like "Echo(EchoRequest)")
-        let method_name = if parts[1].contains('(') {
-            parts[1].split('(').next().unwrap_or("Unknown").to_string()
-        } else {
-            parts[1].to_string()
-        };
-
-        // Extract input type from the part containing the first parentheses
-        let input_type = if parts[1].contains('(') {
-            // Case: "Echo(EchoRequest)"
-            parts[1]
-                .split('(')
-                .nth(1)
-                .unwrap_or("Unknown")
-                .trim_matches(')')
-                .trim_matches(';')
-                .to_string()
-        } else {
-            // Case: separate parts
-            parts.get(2)
-                .map(|s| s.trim_matches('(').trim_matches(')').to_string())
-                .unwrap_or("Unknown".to_string())
-        };
-
-        // Check for streaming keywords
-        let is_client_streaming = line.contains("stream") &&
-            line.find("stream").unwrap_or(0) < line.find("returns").unwrap_or(usize::MAX);
-        let is_server_streaming = line.contains("stream") &&
-            line.find("stream").unwrap_or(usize::MAX) > line.find("returns").unwrap_or(0);
-
-        // Extract output type - find the last parenthesized value
-        let output_type = if let Some(returns_idx) = line.find("returns") {
-            let after_returns = &line[returns_idx..];
-            if let Some(start) = after_returns.find('(') {
-                if let Some(end) = after_returns.find(')') {
-                    after_returns[start + 1..end]
-                        .trim()
-                        .replace("stream ", "")
-                        .to_string()
-                } else {
-                    "Unknown".to_string()
+        let includes: Vec<PathBuf> = if import_paths.is_empty() {
+            let mut dirs: Vec<PathBuf> = Vec::new();
+            for file in files {
+                let dir = Path::new(file)
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."))
+                    .to_path_buf();
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
                 }
-            } else {
-                "Unknown".to_string()
             }
+            dirs
         } else {
-            "Unknown".to_string()
+            import_paths.iter().map(PathBuf::from).collect()
         };
 
-        Some(ProtoMethod {
-            name: method_name,
-            input_type,
-            output_type,
-            is_client_streaming,
-            is_server_streaming,
-        })
+        let mut compiler = protox::Compiler::new(includes).map_err(compile_error)?;
+        compiler.include_source_info(true);
+        compiler.open_files(files).map_err(compile_error)?;
+        Ok(compiler.descriptor_pool())
     }
 
-    fn parse_field(line: &str) -> Option<ProtoField> {
-        // Parse: string name = 1;
-        // or: repeated string tags = 3;
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 4 {
-            return None;
-        }
+    /// Read a binary `FileDescriptorSet`. It must contain every imported
+    /// file, as written by `protoc --include_imports`.
+    pub fn load_descriptor_set(path: &str) -> AppResult<DescriptorPool> {
+        let bytes = std::fs::read(path).map_err(|e| {
+            AppError::validation("path", format!("Failed to read '{}': {}", path, e))
+        })?;
 
-        let repeated = line.trim().starts_with("repeated");
-        let (field_type, name, number_idx) = if repeated {
-            // repeated string tags = 3;
-            // parts: ["repeated", "string", "tags", "=", "3;"]
-            (parts[1].to_string(), parts[2].to_string(), 4)
-        } else {
-            // string name = 1;
-            // parts: ["string", "name", "=", "1;"]
-            (parts[0].to_string(), parts[1].to_string(), 3)
-        };
+        Ok(DescriptorPool::decode(bytes.as_slice())?)
+    }
+}
 
-        let number = parts
-            .get(number_idx)
-            .and_then(|s| s.trim_matches(';').parse().ok())
-            .unwrap_or(0);
+fn compile_source(content: String) -> AppResult<DescriptorPool> {
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(SourceResolver { content });
+    resolver.add(GoogleFileResolver::new());
 
-        Some(ProtoField {
-            name,
-            field_type,
-            number,
-            repeated,
-        })
+    let mut compiler = protox::Compiler::with_file_resolver(resolver);
+    compiler.include_source_info(true);
+    compiler.open_file(SOURCE_NAME).map_err(compile_error)?;
+    Ok(compiler.descriptor_pool())
+}
+
+struct SourceResolver {
+    content: String,
+}
+
+impl FileResolver for SourceResolver {
+    fn open_file(&self, name: &str) -> Result<File, protox::Error> {
+        if name == SOURCE_NAME {
+            File::from_source(name, &self.content)
+        } else {
+            Err(protox::Error::file_not_found(name))
+        }
+    }
+}
+
+/// Convert a protox error into a `ParseError`, keeping the first labelled
+/// source position when the compiler reports one
+fn compile_error(err: protox::Error) -> AppError {
+    let message = err.to_string();
+    let location = err
+        .labels()
+        .and_then(|mut labels| labels.next())
+        .and_then(|label| {
+            let contents = err.source_code()?.read_span(label.inner(), 0, 0).ok()?;
+            Some(SourceLocation {
+                file: contents
+                    .name()
+                    .or(err.file())
+                    .unwrap_or(SOURCE_NAME)
+                    .to_string(),
+                line: contents.line() + 1,
+                column: contents.column() + 1,
+            })
+        });
+
+    match location {
+        Some(location) => AppError::parse_at(message, location),
+        None => AppError::parse(message),
     }
 }
 
@@ -220,6 +170,12 @@ mod tests {
         assert_eq!(methods[2].name, "CreateUser");
         assert_eq!(methods[3].name, "Chat");
 
+        // Verify streaming flags
+        assert!(!methods[0].is_client_streaming && !methods[0].is_server_streaming);
+        assert!(!methods[1].is_client_streaming && methods[1].is_server_streaming);
+        assert!(methods[2].is_client_streaming && !methods[2].is_server_streaming);
+        assert!(methods[3].is_client_streaming && methods[3].is_server_streaming);
+
         // Verify messages
         assert!(schema.messages.len() >= 5);
     }
@@ -238,6 +194,32 @@ mod tests {
         // Find Metadata message
         let metadata = schema.messages.iter().find(|m| m.name == "Metadata");
         assert!(metadata.is_some());
+
+        let attributes = &metadata.unwrap().fields[2];
+        assert_eq!(attributes.field_type, "map<string, string>");
+        assert!(!attributes.repeated);
+    }
+
+    #[test]
+    fn test_parse_nested_message_declarations() {
+        let proto = r#"
+syntax = "proto3";
+
+message Outer {
+  message Inner {
+    string value = 1;
+  }
+
+  Inner inner = 1;
+  repeated Inner items = 2;
+}
+"#;
+        let schema = ProtoParser::parse_proto_file(proto).unwrap();
+
+        let names: Vec<&str> = schema.messages.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["Outer", "Outer.Inner"]);
+        assert_eq!(schema.messages[0].fields[0].field_type, "Outer.Inner");
+        assert!(schema.messages[0].fields[1].repeated);
     }
 
     #[test]
@@ -250,6 +232,13 @@ mod tests {
 
         assert_eq!(schema.services.len(), 1);
         assert_eq!(schema.services[0].name, "EnumService");
+
+        let request = schema
+            .messages
+            .iter()
+            .find(|m| m.name == "StatusRequest")
+            .unwrap();
+        assert_eq!(request.fields[1].field_type, "Status");
     }
 
     #[test]
@@ -296,11 +285,19 @@ message TestResponse {
 
     #[test]
     fn test_parse_rpc_method_unary() {
-        let line = "rpc GetUser(GetUserRequest) returns (GetUserResponse);";
-        let method = ProtoParser::parse_rpc_method(line);
+        let proto = r#"
+syntax = "proto3";
 
-        assert!(method.is_some());
-        let m = method.unwrap();
+service UserService {
+  rpc GetUser(GetUserRequest)
+      returns (GetUserResponse);
+}
+
+message GetUserRequest {}
+message GetUserResponse {}
+"#;
+        let schema = ProtoParser::parse_proto_file(proto).unwrap();
+        let m = &schema.services[0].methods[0];
 
         assert_eq!(m.name, "GetUser");
         assert_eq!(m.input_type, "GetUserRequest");
@@ -311,11 +308,9 @@ message TestResponse {
 
     #[test]
     fn test_parse_field_simple() {
-        let line = "string name = 1;";
-        let field = ProtoParser::parse_field(line);
-
-        assert!(field.is_some());
-        let f = field.unwrap();
+        let schema =
+            ProtoParser::parse_proto_file("message M { optional string name = 1; }").unwrap();
+        let f = &schema.messages[0].fields[0];
 
         assert_eq!(f.name, "name");
         assert_eq!(f.field_type, "string");
@@ -325,11 +320,11 @@ message TestResponse {
 
     #[test]
     fn test_parse_field_repeated() {
-        let line = "repeated string tags = 3;";
-        let field = ProtoParser::parse_field(line);
-
-        assert!(field.is_some());
-        let f = field.unwrap();
+        let schema = ProtoParser::parse_proto_file(
+            "syntax = \"proto3\"; message M { repeated string tags = 3; }",
+        )
+        .unwrap();
+        let f = &schema.messages[0].fields[0];
 
         assert_eq!(f.name, "tags");
         assert_eq!(f.field_type, "string");
@@ -339,11 +334,10 @@ message TestResponse {
 
     #[test]
     fn test_parse_field_with_type_int32() {
-        let line = "int32 count = 2;";
-        let field = ProtoParser::parse_field(line);
-
-        assert!(field.is_some());
-        let f = field.unwrap();
+        let schema =
+            ProtoParser::parse_proto_file("syntax = \"proto3\"; message M { int32 count = 2; }")
+                .unwrap();
+        let f = &schema.messages[0].fields[0];
 
         assert_eq!(f.field_type, "int32");
         assert_eq!(f.number, 2);
@@ -359,6 +353,11 @@ service ServiceA {
 service ServiceB {
   rpc MethodB(RequestB) returns (ResponseB);
 }
+
+message RequestA {}
+message ResponseA {}
+message RequestB {}
+message ResponseB {}
 "#;
         let result = ProtoParser::parse_proto_file(proto);
 
@@ -399,4 +398,145 @@ message Empty {
         assert_eq!(schema.messages.len(), 1);
         assert_eq!(schema.messages[0].fields.len(), 0);
     }
+
+    #[test]
+    fn test_parse_well_known_type_import() {
+        let proto = r#"
+syntax = "proto3";
+
+import "google/protobuf/timestamp.proto";
+
+message Event {
+  google.protobuf.Timestamp created_at = 1;
+}
+"#;
+        let schema = ProtoParser::parse_proto_file(proto).unwrap();
+
+        assert_eq!(schema.messages.len(), 1);
+        assert_eq!(
+            schema.messages[0].fields[0].field_type,
+            "google.protobuf.Timestamp"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_proto_reports_location() {
+        let result = ProtoParser::parse_proto_file(fixtures::invalid_proto());
+
+        match result {
+            Err(AppError::ParseError {
+                location: Some(location),
+                ..
+            }) => {
+                assert_eq!(location.file, SOURCE_NAME);
+                assert_eq!(location.line, 4);
+                assert_eq!(location.column, 1);
+            }
+            other => panic!("expected located parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_unresolved_type_reports_location() {
+        let proto = "syntax = \"proto3\";\nmessage M {\n  Missing value = 1;\n}\n";
+        let err = ProtoParser::parse_proto_file(proto).unwrap_err();
+
+        assert_eq!(err.error_type(), "ParseError");
+        assert!(err.message().contains("Missing"));
+        assert!(err.to_string().contains("input.proto:3:"));
+    }
+
+    #[test]
+    fn test_parse_unknown_import_fails() {
+        let proto = "syntax = \"proto3\";\nimport \"other.proto\";\n";
+        let err = ProtoParser::parse_proto_file(proto).unwrap_err();
+
+        assert_eq!(err.error_type(), "ParseError");
+        assert!(err.message().contains("other.proto"));
+    }
+
+    #[test]
+    fn test_parse_proto_without_syntax() {
+        // Unlabeled fields are only valid in proto3
+        let schema = ProtoParser::parse_proto_file("message M { string id = 1; }").unwrap();
+        assert_eq!(schema.messages[0].fields[0].name, "id");
+
+        // Still proto2 when it compiles as such
+        let pool = ProtoParser::compile("message M { required string id = 1; }").unwrap();
+        let file = pool.get_file_by_name(SOURCE_NAME).unwrap();
+        assert_eq!(file.syntax(), prost_reflect::Syntax::Proto2);
+
+        // Errors are those of the source as written
+        let err =
+            ProtoParser::parse_proto_file("message M {\n  Missing value = 1;\n}\n").unwrap_err();
+        assert!(err.to_string().contains("input.proto:2:"), "{}", err);
+    }
+
+    #[test]
+    fn test_compile_returns_usable_pool() {
+        let pool = ProtoParser::compile(fixtures::echo_service_proto()).unwrap();
+
+        let service = pool.get_service_by_name("test.v1.Echo").unwrap();
+        assert_eq!(service.methods().len(), 4);
+        assert!(pool.get_message_by_name("test.v1.EchoRequest").is_some());
+    }
+
+    #[test]
+    fn test_compile_files_resolves_imports_from_import_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let common = dir.path().join("common");
+        std::fs::create_dir(&common).unwrap();
+        std::fs::write(
+            common.join("types.proto"),
+            "syntax = \"proto3\";\npackage common;\nmessage Id { string value = 1; }\n",
+        )
+        .unwrap();
+        let service_path = dir.path().join("service.proto");
+        std::fs::write(
+            &service_path,
+            r#"
+syntax = "proto3";
+package svc;
+import "common/types.proto";
+service Lookup {
+  rpc Find(common.Id) returns (common.Id);
+}
+"#,
+        )
+        .unwrap();
+
+        let pool = ProtoParser::compile_files(
+            &[service_path.to_string_lossy().to_string()],
+            &[dir.path().to_string_lossy().to_string()],
+        )
+        .unwrap();
+
+        let method = pool
+            .get_service_by_name("svc.Lookup")
+            .unwrap()
+            .methods()
+            .next()
+            .unwrap();
+        assert_eq!(method.input().full_name(), "common.Id");
+    }
+
+    #[test]
+    fn test_compile_files_requires_files() {
+        let err = ProtoParser::compile_files(&[], &[]).unwrap_err();
+        assert_eq!(err.error_type(), "ValidationError");
+    }
+
+    #[test]
+    fn test_load_descriptor_set_round_trip() {
+        let pool = ProtoParser::compile(fixtures::echo_service_proto()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("echo.pb");
+        std::fs::write(&path, pool.encode_to_vec()).unwrap();
+
+        let loaded = ProtoParser::load_descriptor_set(&path.to_string_lossy()).unwrap();
+        assert!(loaded.get_service_by_name("test.v1.Echo").is_some());
+
+        let missing = ProtoParser::load_descriptor_set("/nonexistent/echo.pb").unwrap_err();
+        assert_eq!(missing.error_type(), "ValidationError");
+    }
 }
@@ -1,9 +1,20 @@
-use crate::error::AppResult;
-use crate::grpc::{ProtoField, ProtoMessage, ProtoMethod, ProtoSchema, ProtoService};
+use crate::client::proxy::ProxySettings;
+use crate::error::{AppError, AppResult};
+use crate::grpc::channel::connect;
+use crate::grpc::tls::GrpcTlsConfig;
+use crate::grpc::{status_code_name, ProtoMethod, ProtoSchema, ProtoService};
+use http::uri::PathAndQuery;
+use prost::Message;
 use prost_reflect::DescriptorPool;
-use prost_types::FileDescriptorSet;
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::client::Grpc;
+use tonic::codec::{ProstCodec, Streaming};
 use tonic::transport::Channel;
-use tonic_reflection::pb::server_reflection_client::ServerReflectionClient;
+use tonic::{Code, Request, Status};
 use tonic_reflection::pb::server_reflection_request::MessageRequest;
 use tonic_reflection::pb::server_reflection_response::MessageResponse;
 use tonic_reflection::pb::{ServerReflectionRequest, ServerReflectionResponse};
@@ -11,16 +22,48 @@ use tonic_reflection::pb::{ServerReflectionRequest, ServerReflectionResponse};
 pub struct GrpcReflection {
     channel: Channel,
     descriptor_pool: Option<DescriptorPool>,
+    version: Option<ReflectionVersion>,
+}
+
+/// Versions of the server reflection API. Both share the same messages and
+/// only differ in the service name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReflectionVersion {
+    V1,
+    V1Alpha,
+}
+
+impl ReflectionVersion {
+    fn path(self) -> &'static str {
+        match self {
+            ReflectionVersion::V1 => "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+            ReflectionVersion::V1Alpha => {
+                "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo"
+            }
+        }
+    }
 }
 
 impl GrpcReflection {
-    pub async fn new(url: &str) -> AppResult<Self> {
-        let channel = Channel::from_shared(url.to_string())?.connect().await?;
+    /// Connect to `url` through `proxy`, over TLS with `tls` for `https://`
+    /// URLs
+    pub async fn new(
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+    ) -> AppResult<Self> {
+        let channel = connect(url, tls, proxy).await?;
+
+        Ok(Self::from_channel(channel))
+    }
 
-        Ok(Self {
+    /// Create a reflection client on top of an already connected channel
+    pub fn from_channel(channel: Channel) -> Self {
+        Self {
             channel,
             descriptor_pool: None,
-        })
+            version: None,
+        }
     }
 
     /// Get the descriptor pool, initializing it if needed
@@ -32,125 +75,93 @@ impl GrpcReflection {
         Ok(self.descriptor_pool.as_ref().unwrap())
     }
 
-    /// Fetch all file descriptors from the server via reflection
-    async fn fetch_descriptor_pool(&self) -> AppResult<DescriptorPool> {
-        let mut client = ServerReflectionClient::new(self.channel.clone());
-
-        // First, list all services
-        let list_services_request = ServerReflectionRequest {
-            host: String::new(),
-            message_request: Some(MessageRequest::ListServices(String::new())),
-        };
-
-        let mut stream = client.server_reflection_info(tokio_stream::once(list_services_request)).await?.into_inner();
-
-        let mut file_descriptor_protos = Vec::new();
+    /// Fetch the files declaring every listed service, together with all the
+    /// files they import, and build a descriptor pool from them
+    async fn fetch_descriptor_pool(&mut self) -> AppResult<DescriptorPool> {
+        let (mut stream, service_names) = self.open_stream().await?;
 
-        // Collect service names
-        let mut service_names = Vec::new();
-        if let Some(response) = stream.message().await? {
-            if let Some(ServerReflectionResponse { message_response: Some(msg), .. }) = Some(response) {
-                if let MessageResponse::ListServicesResponse(services) = msg {
-                    for service in services.service {
-                        service_names.push(service.name);
-                    }
+        let mut files: Vec<FileDescriptorProto> = Vec::new();
+        let mut known_files: HashSet<String> = HashSet::new();
+        let mut pending: VecDeque<MessageRequest> = service_names
+            .into_iter()
+            .map(MessageRequest::FileContainingSymbol)
+            .collect();
+
+        while let Some(request) = pending.pop_front() {
+            // Several services may live in a file that was already fetched
+            let already_known = match &request {
+                MessageRequest::FileByFilename(name) => known_files.contains(name),
+                MessageRequest::FileContainingSymbol(symbol) => {
+                    files.iter().any(|file| declares_service(file, symbol))
                 }
-            }
-        }
-
-        // For each service, get its file descriptor
-        for service_name in service_names {
-            let request = ServerReflectionRequest {
-                host: String::new(),
-                message_request: Some(MessageRequest::FileContainingSymbol(service_name)),
+                _ => false,
             };
+            if already_known {
+                continue;
+            }
 
-            let mut stream = client.server_reflection_info(tokio_stream::once(request)).await?.into_inner();
+            let lookup = describe_request(&request);
+            let encoded = stream.file_descriptors(request).await?;
 
-            if let Some(response) = stream.message().await? {
-                if let Some(ServerReflectionResponse { message_response: Some(msg), .. }) = Some(response) {
-                    if let MessageResponse::FileDescriptorResponse(fd_response) = msg {
-                        for fd_bytes in fd_response.file_descriptor_proto {
-                            file_descriptor_protos.push(fd_bytes);
-                        }
+            for file in decode_file_descriptors(&encoded, &lookup)? {
+                if !known_files.insert(file.name().to_string()) {
+                    continue;
+                }
+                for dependency in &file.dependency {
+                    if !known_files.contains(dependency) {
+                        pending.push_back(MessageRequest::FileByFilename(dependency.clone()));
                     }
                 }
+                files.push(file);
             }
         }
 
-        // Decode file descriptors
-        use prost::Message;
-        let mut file_descriptors = Vec::new();
-        for bytes in file_descriptor_protos {
-            if let Ok(fd) = prost_types::FileDescriptorProto::decode(&bytes[..]) {
-                file_descriptors.push(fd);
-            }
-        }
-
-        // Create descriptor pool
-        let fds = FileDescriptorSet {
-            file: file_descriptors,
-        };
-
-        let pool = DescriptorPool::from_file_descriptor_set(fds)?;
+        let pool = DescriptorPool::from_file_descriptor_set(FileDescriptorSet { file: files })?;
         Ok(pool)
     }
 
-    pub async fn discover_services(&mut self) -> AppResult<ProtoSchema> {
-        let pool = self.get_descriptor_pool().await?;
+    /// Open a reflection stream and list the services, preferring
+    /// `grpc.reflection.v1` and falling back to `v1alpha` for older servers
+    async fn open_stream(&mut self) -> AppResult<(ReflectionStream, Vec<String>)> {
+        let versions = match self.version {
+            Some(version) => vec![version],
+            None => vec![ReflectionVersion::V1, ReflectionVersion::V1Alpha],
+        };
 
-        let mut services = Vec::new();
-        let mut messages = Vec::new();
-
-        // Extract services
-        for service in pool.services() {
-            let mut methods = Vec::new();
-
-            for method in service.methods() {
-                methods.push(ProtoMethod {
-                    name: method.name().to_string(),
-                    input_type: method.input().full_name().to_string(),
-                    output_type: method.output().full_name().to_string(),
-                    is_client_streaming: method.is_client_streaming(),
-                    is_server_streaming: method.is_server_streaming(),
-                });
+        let mut unimplemented = None;
+        for version in versions {
+            match ReflectionStream::list_services(self.channel.clone(), version).await {
+                Ok((stream, service_names)) => {
+                    self.version = Some(version);
+                    return Ok((stream, service_names));
+                }
+                Err(status) if status.code() == Code::Unimplemented => unimplemented = Some(status),
+                Err(status) => return Err(status.into()),
             }
-
-            services.push(ProtoService {
-                name: service.full_name().to_string(),
-                methods,
-            });
         }
 
-        // Extract messages
-        for message in pool.all_messages() {
-            let mut fields = Vec::new();
-
-            for field in message.fields() {
-                fields.push(ProtoField {
-                    name: field.name().to_string(),
-                    field_type: format!("{:?}", field.kind()),
-                    number: field.number(),
-                    repeated: field.is_list(),
-                });
-            }
+        Err(AppError::network(format!(
+            "Server reflection is not available: {}",
+            unimplemented
+                .map(|status| status.message().to_string())
+                .unwrap_or_default()
+        )))
+    }
 
-            messages.push(ProtoMessage {
-                name: message.full_name().to_string(),
-                fields,
-            });
-        }
+    /// Reflection API version the server answered with, once known
+    pub fn version(&self) -> Option<ReflectionVersion> {
+        self.version
+    }
 
-        Ok(ProtoSchema { services, messages })
+    pub async fn discover_services(&mut self) -> AppResult<ProtoSchema> {
+        let pool = self.get_descriptor_pool().await?;
+        Ok(ProtoSchema::from_pool(pool))
     }
 
     pub async fn get_service_info(&mut self, service_name: &str) -> AppResult<Option<ProtoService>> {
         let schema = self.discover_services().await?;
 
-        Ok(schema
-            .services
-            .into_iter()
-            .find(|service| service.name == service_name))
+        Ok(schema.service(service_name).cloned())
     }
 
     pub async fn get_method_info(
@@ -158,14 +169,10 @@ impl GrpcReflection {
         service_name: &str,
         method_name: &str,
     ) -> AppResult<Option<ProtoMethod>> {
-        if let Some(service) = self.get_service_info(service_name).await? {
-            Ok(service
-                .methods
-                .into_iter()
-                .find(|method| method.name == method_name))
-        } else {
-            Ok(None)
-        }
+        Ok(self
+            .get_service_info(service_name)
+            .await?
+            .and_then(|service| service.method(method_name).cloned()))
     }
 
     /// Get the internal descriptor pool for making dynamic calls
@@ -174,9 +181,158 @@ impl GrpcReflection {
     }
 }
 
+/// Whether the server answers reflection requests of `version`
+pub async fn supports_reflection(channel: Channel, version: ReflectionVersion) -> bool {
+    ReflectionStream::list_services(channel, version)
+        .await
+        .is_ok()
+}
+
+/// A bidirectional `ServerReflectionInfo` call answering one request at a time
+struct ReflectionStream {
+    requests: mpsc::Sender<ServerReflectionRequest>,
+    responses: Streaming<ServerReflectionResponse>,
+}
+
+impl ReflectionStream {
+    /// Start the call with a `ListServices` request and return the service names.
+    ///
+    /// The request is queued before the call starts because some servers only
+    /// send response headers along with the first response.
+    async fn list_services(
+        channel: Channel,
+        version: ReflectionVersion,
+    ) -> Result<(Self, Vec<String>), Status> {
+        let (requests, rx) = mpsc::channel(16);
+        let _ = requests
+            .send(reflection_request(MessageRequest::ListServices(
+                String::new(),
+            )))
+            .await;
+
+        let mut grpc = Grpc::new(channel);
+        grpc.ready()
+            .await
+            .map_err(|e| Status::unavailable(format!("Channel not ready: {}", e)))?;
+
+        let response = grpc
+            .streaming(
+                Request::new(ReceiverStream::new(rx)),
+                PathAndQuery::from_static(version.path()),
+                ProstCodec::<ServerReflectionRequest, ServerReflectionResponse>::default(),
+            )
+            .await?;
+
+        let mut stream = Self {
+            requests,
+            responses: response.into_inner(),
+        };
+
+        match stream.responses.message().await? {
+            Some(ServerReflectionResponse {
+                message_response: Some(MessageResponse::ListServicesResponse(services)),
+                ..
+            }) => Ok((
+                stream,
+                services.service.into_iter().map(|s| s.name).collect(),
+            )),
+            Some(ServerReflectionResponse {
+                message_response: Some(MessageResponse::ErrorResponse(error)),
+                ..
+            }) => Err(Status::new(
+                Code::from(error.error_code),
+                error.error_message,
+            )),
+            _ => Err(Status::internal(
+                "Unexpected reply to the reflection ListServices request",
+            )),
+        }
+    }
+
+    /// Send a file request and return the encoded descriptors of the reply
+    async fn file_descriptors(&mut self, request: MessageRequest) -> AppResult<Vec<Vec<u8>>> {
+        let lookup = describe_request(&request);
+
+        self.requests
+            .send(reflection_request(request))
+            .await
+            .map_err(|_| AppError::network("Reflection stream closed by the server"))?;
+
+        match self.responses.message().await? {
+            Some(ServerReflectionResponse {
+                message_response: Some(MessageResponse::FileDescriptorResponse(response)),
+                ..
+            }) => Ok(response.file_descriptor_proto),
+            Some(ServerReflectionResponse {
+                message_response: Some(MessageResponse::ErrorResponse(error)),
+                ..
+            }) => Err(AppError::network(format!(
+                "Reflection lookup of {} failed ({}): {}",
+                lookup,
+                status_code_name(Code::from(error.error_code)),
+                error.error_message
+            ))),
+            _ => Err(AppError::network(format!(
+                "Unexpected reply to the reflection lookup of {}",
+                lookup
+            ))),
+        }
+    }
+}
+
+fn reflection_request(message_request: MessageRequest) -> ServerReflectionRequest {
+    ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(message_request),
+    }
+}
+
+/// Human readable form of a lookup, used in error messages
+fn describe_request(request: &MessageRequest) -> String {
+    match request {
+        MessageRequest::FileByFilename(name) => format!("file '{}'", name),
+        MessageRequest::FileContainingSymbol(symbol) => format!("symbol '{}'", symbol),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Whether `file` declares the service with the full name `symbol`
+fn declares_service(file: &FileDescriptorProto, symbol: &str) -> bool {
+    file.service.iter().any(|service| {
+        let full_name = if file.package().is_empty() {
+            service.name().to_string()
+        } else {
+            format!("{}.{}", file.package(), service.name())
+        };
+        full_name == symbol
+    })
+}
+
+/// Decode the descriptors returned for `lookup`, failing on the first
+/// malformed one rather than building an incomplete pool
+fn decode_file_descriptors(
+    encoded: &[Vec<u8>],
+    lookup: &str,
+) -> AppResult<Vec<FileDescriptorProto>> {
+    encoded
+        .iter()
+        .map(|bytes| {
+            FileDescriptorProto::decode(bytes.as_slice()).map_err(|e| {
+                AppError::parse(format!(
+                    "Failed to decode file descriptor returned for {}: {}",
+                    lookup, e
+                ))
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::grpc::proto_parser::ProtoParser;
+    use crate::grpc::test_utils::fixtures;
+    use crate::grpc::test_utils::server::TestServer;
 
     #[tokio::test]
     async fn test_discover_services_returns_mock_data() {
@@ -198,6 +354,7 @@ mod tests {
         let service = ProtoService {
             name: "TestService".to_string(),
             methods: vec![],
+            ..Default::default()
         };
 
         assert_eq!(service.name, "TestService");
@@ -212,10 +369,109 @@ mod tests {
             output_type: "Response".to_string(),
             is_client_streaming: false,
             is_server_streaming: true,
+            ..Default::default()
         };
 
         assert_eq!(method.name, "TestMethod");
         assert!(!method.is_client_streaming);
         assert!(method.is_server_streaming);
     }
+
+    #[tokio::test]
+    async fn test_fetch_follows_imports_and_falls_back_to_v1alpha() {
+        let server = TestServer::start().await;
+        let mut reflection = GrpcReflection::new(&server.url, None, &ProxySettings::direct())
+            .await
+            .unwrap();
+
+        let pool = reflection.descriptor_pool().await.unwrap();
+
+        assert!(pool.get_service_by_name("test.v1.Echo").is_some());
+        assert!(pool
+            .get_file_by_name("google/protobuf/timestamp.proto")
+            .is_some());
+        assert_eq!(reflection.version(), Some(ReflectionVersion::V1Alpha));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_prefers_v1() {
+        let server = TestServer::start_with_reflection_v1().await;
+        let mut reflection = GrpcReflection::new(&server.url, None, &ProxySettings::direct())
+            .await
+            .unwrap();
+
+        let schema = reflection.discover_services().await.unwrap();
+
+        assert!(schema.service("test.v1.Echo").is_some());
+        assert_eq!(reflection.version(), Some(ReflectionVersion::V1));
+    }
+
+    #[tokio::test]
+    async fn test_reflection_schema_matches_parsed_schema() {
+        let server = TestServer::start().await;
+        let mut reflection = GrpcReflection::new(&server.url, None, &ProxySettings::direct())
+            .await
+            .unwrap();
+
+        let discovered = reflection.discover_services().await.unwrap();
+        let parsed = ProtoParser::parse_proto_file(fixtures::echo_service_proto()).unwrap();
+
+        let echo = discovered.service("test.v1.Echo").unwrap();
+        assert_eq!(Some(echo), parsed.service("test.v1.Echo"));
+        assert!(echo.comments.is_some());
+        for message in &parsed.messages {
+            assert!(
+                discovered.messages.contains(message),
+                "{} differs",
+                message.full_name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_without_reflection_service() {
+        let server = TestServer::start_without_reflection().await;
+        let mut reflection = GrpcReflection::new(&server.url, None, &ProxySettings::direct())
+            .await
+            .unwrap();
+
+        let err = reflection.descriptor_pool().await.unwrap_err();
+
+        assert_eq!(err.error_type(), "NetworkError");
+        assert!(err.message().contains("Server reflection is not available"));
+        assert_eq!(reflection.version(), None);
+    }
+
+    #[test]
+    fn test_decode_file_descriptors_rejects_malformed_bytes() {
+        let valid = FileDescriptorProto {
+            name: Some("a.proto".to_string()),
+            ..Default::default()
+        }
+        .encode_to_vec();
+
+        let decoded =
+            decode_file_descriptors(std::slice::from_ref(&valid), "file 'a.proto'").unwrap();
+        assert_eq!(decoded[0].name(), "a.proto");
+
+        let err =
+            decode_file_descriptors(&[valid, vec![0xff, 0xff]], "symbol 'x.Svc'").unwrap_err();
+        assert_eq!(err.error_type(), "ParseError");
+        assert!(err.message().contains("symbol 'x.Svc'"));
+    }
+
+    #[test]
+    fn test_declares_service_uses_package() {
+        let file = FileDescriptorProto {
+            package: Some("test.v1".to_string()),
+            service: vec![prost_types::ServiceDescriptorProto {
+                name: Some("Echo".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert!(declares_service(&file, "test.v1.Echo"));
+        assert!(!declares_service(&file, "Echo"));
+    }
 }
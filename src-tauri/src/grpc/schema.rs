@@ -0,0 +1,660 @@
+use prost_reflect::{
+    Cardinality, DescriptorPool, DynamicMessage, EnumDescriptor, FieldDescriptor, FileDescriptor,
+    Kind, MessageDescriptor,
+};
+use prost_types::source_code_info::Location;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Services, messages and enums of a set of proto files.
+///
+/// Names are relative to the package of the file declaring them, as written
+/// in the source (`Outer.Inner` for nested types); `full_name` holds the fully
+/// qualified name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoSchema {
+    pub services: Vec<ProtoService>,
+    pub messages: Vec<ProtoMessage>,
+    #[serde(default)]
+    pub enums: Vec<ProtoEnum>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoService {
+    pub name: String,
+    #[serde(default)]
+    pub full_name: String,
+    pub methods: Vec<ProtoMethod>,
+    #[serde(default)]
+    pub deprecated: bool,
+    /// Options set on the declaration, custom options under `[full.name]`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub options: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<ProtoComments>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoMethod {
+    pub name: String,
+    pub input_type: String,
+    pub output_type: String,
+    pub is_client_streaming: bool,
+    pub is_server_streaming: bool,
+    #[serde(default)]
+    pub input_type_full_name: String,
+    #[serde(default)]
+    pub output_type_full_name: String,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub options: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<ProtoComments>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoMessage {
+    pub name: String,
+    #[serde(default)]
+    pub full_name: String,
+    pub fields: Vec<ProtoField>,
+    /// `oneof` groups; the synthetic ones behind proto3 `optional` are left out
+    #[serde(default)]
+    pub oneofs: Vec<ProtoOneof>,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub options: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<ProtoComments>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoField {
+    pub name: String,
+    /// Type as written in the source, e.g. `int32`, `Outer.Inner` or
+    /// `map<string, Item>`
+    pub field_type: String,
+    pub number: u32,
+    pub repeated: bool,
+    #[serde(default)]
+    pub json_name: String,
+    #[serde(default)]
+    pub cardinality: ProtoCardinality,
+    /// Full name of the message or enum type, of the value type for maps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map: Option<ProtoMapType>,
+    /// Name of the `oneof` the field belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oneof: Option<String>,
+    /// Explicit proto2 default, as written in the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub options: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<ProtoComments>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtoCardinality {
+    /// A proto3 field without `optional`, unset when it has its default value
+    #[default]
+    Singular,
+    /// A field tracking whether it is set: proto2 `optional` or proto3 `optional`
+    Optional,
+    Required,
+    Repeated,
+    Map,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoMapType {
+    pub key_type: String,
+    pub value_type: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoOneof {
+    pub name: String,
+    /// Names of the member fields
+    pub fields: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<ProtoComments>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoEnum {
+    pub name: String,
+    pub full_name: String,
+    pub values: Vec<ProtoEnumValue>,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub options: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<ProtoComments>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoEnumValue {
+    pub name: String,
+    pub number: i32,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub options: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<ProtoComments>,
+}
+
+/// Source comments of a declaration. Only available when the descriptors
+/// were built with source info, which servers often strip.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoComments {
+    /// Comment on the lines right above the declaration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leading: Option<String>,
+    /// Comment after the declaration on the same line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trailing: Option<String>,
+}
+
+impl ProtoSchema {
+    /// Describe every file of a descriptor pool
+    pub fn from_pool(pool: &DescriptorPool) -> Self {
+        Self::from_files(pool.files())
+    }
+
+    /// Describe the declarations of `files`
+    pub fn from_files(files: impl IntoIterator<Item = FileDescriptor>) -> Self {
+        let mut schema = Self::default();
+        for file in files {
+            SchemaBuilder::new(&file).add_file(&file, &mut schema);
+        }
+        schema
+    }
+
+    /// Find a service by its full name, or by its name relative to its package
+    pub fn service(&self, name: &str) -> Option<&ProtoService> {
+        self.services
+            .iter()
+            .find(|service| service.full_name == name)
+            .or_else(|| self.services.iter().find(|service| service.name == name))
+    }
+}
+
+impl ProtoService {
+    pub fn method(&self, name: &str) -> Option<&ProtoMethod> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+}
+
+/// Describes the declarations of one file, relative to its package
+struct SchemaBuilder {
+    package: String,
+    locations: HashMap<Vec<i32>, Location>,
+}
+
+impl SchemaBuilder {
+    fn new(file: &FileDescriptor) -> Self {
+        let locations = file
+            .file_descriptor_proto()
+            .source_code_info
+            .iter()
+            .flat_map(|info| info.location.iter())
+            .map(|location| (location.path.clone(), location.clone()))
+            .collect();
+
+        Self {
+            package: file.package_name().to_string(),
+            locations,
+        }
+    }
+
+    fn add_file(&self, file: &FileDescriptor, schema: &mut ProtoSchema) {
+        for service in file.services() {
+            schema.services.push(ProtoService {
+                name: self.relative_name(service.full_name()),
+                full_name: service.full_name().to_string(),
+                methods: service
+                    .methods()
+                    .map(|method| ProtoMethod {
+                        name: method.name().to_string(),
+                        input_type: self.relative_name(method.input().full_name()),
+                        output_type: self.relative_name(method.output().full_name()),
+                        is_client_streaming: method.is_client_streaming(),
+                        is_server_streaming: method.is_server_streaming(),
+                        input_type_full_name: method.input().full_name().to_string(),
+                        output_type_full_name: method.output().full_name().to_string(),
+                        deprecated: is_deprecated(&method.options()),
+                        options: options_map(&method.options()),
+                        comments: self.comments(method.path()),
+                    })
+                    .collect(),
+                deprecated: is_deprecated(&service.options()),
+                options: options_map(&service.options()),
+                comments: self.comments(service.path()),
+            });
+        }
+
+        for message in file.messages() {
+            self.add_message(&message, schema);
+        }
+        for enum_type in file.enums() {
+            self.add_enum(&enum_type, schema);
+        }
+    }
+
+    /// Add a message followed by its nested types, skipping synthetic map entries
+    fn add_message(&self, message: &MessageDescriptor, schema: &mut ProtoSchema) {
+        if message.is_map_entry() {
+            return;
+        }
+
+        let oneofs = message
+            .oneofs()
+            .filter(|oneof| {
+                !oneof
+                    .fields()
+                    .all(|field| field.field_descriptor_proto().proto3_optional())
+            })
+            .map(|oneof| ProtoOneof {
+                name: oneof.name().to_string(),
+                fields: oneof
+                    .fields()
+                    .map(|field| field.name().to_string())
+                    .collect(),
+                comments: self.comments(oneof.path()),
+            })
+            .collect();
+
+        schema.messages.push(ProtoMessage {
+            name: self.relative_name(message.full_name()),
+            full_name: message.full_name().to_string(),
+            fields: message.fields().map(|field| self.field(&field)).collect(),
+            oneofs,
+            deprecated: is_deprecated(&message.options()),
+            options: options_map(&message.options()),
+            comments: self.comments(message.path()),
+        });
+
+        for nested in message.child_messages() {
+            self.add_message(&nested, schema);
+        }
+        for enum_type in message.child_enums() {
+            self.add_enum(&enum_type, schema);
+        }
+    }
+
+    fn add_enum(&self, enum_type: &EnumDescriptor, schema: &mut ProtoSchema) {
+        schema.enums.push(ProtoEnum {
+            name: self.relative_name(enum_type.full_name()),
+            full_name: enum_type.full_name().to_string(),
+            values: enum_type
+                .values()
+                .map(|value| ProtoEnumValue {
+                    name: value.name().to_string(),
+                    number: value.number(),
+                    deprecated: is_deprecated(&value.options()),
+                    options: options_map(&value.options()),
+                    comments: self.comments(value.path()),
+                })
+                .collect(),
+            deprecated: is_deprecated(&enum_type.options()),
+            options: options_map(&enum_type.options()),
+            comments: self.comments(enum_type.path()),
+        });
+    }
+
+    fn field(&self, field: &FieldDescriptor) -> ProtoField {
+        let proto = field.field_descriptor_proto();
+        let (field_type, type_kind, map) = match field.kind() {
+            Kind::Message(entry) if field.is_map() => {
+                let key_type = self.kind_name(&entry.map_entry_key_field().kind());
+                let value_kind = entry.map_entry_value_field().kind();
+                let value_type = self.kind_name(&value_kind);
+                (
+                    format!("map<{}, {}>", key_type, value_type),
+                    value_kind,
+                    Some(ProtoMapType {
+                        key_type,
+                        value_type,
+                    }),
+                )
+            }
+            kind => (self.kind_name(&kind), kind, None),
+        };
+
+        ProtoField {
+            name: field.name().to_string(),
+            field_type,
+            number: field.number(),
+            repeated: field.is_list(),
+            json_name: field.json_name().to_string(),
+            cardinality: cardinality(field),
+            type_name: match type_kind {
+                Kind::Message(message) => Some(message.full_name().to_string()),
+                Kind::Enum(enum_type) => Some(enum_type.full_name().to_string()),
+                _ => None,
+            },
+            map,
+            oneof: field
+                .containing_oneof()
+                .filter(|_| !proto.proto3_optional())
+                .map(|oneof| oneof.name().to_string()),
+            default_value: proto.default_value.clone(),
+            deprecated: is_deprecated(&field.options()),
+            options: options_map(&field.options()),
+            comments: self.comments(field.path()),
+        }
+    }
+
+    fn kind_name(&self, kind: &Kind) -> String {
+        match kind {
+            Kind::Double => "double".to_string(),
+            Kind::Float => "float".to_string(),
+            Kind::Int32 => "int32".to_string(),
+            Kind::Int64 => "int64".to_string(),
+            Kind::Uint32 => "uint32".to_string(),
+            Kind::Uint64 => "uint64".to_string(),
+            Kind::Sint32 => "sint32".to_string(),
+            Kind::Sint64 => "sint64".to_string(),
+            Kind::Fixed32 => "fixed32".to_string(),
+            Kind::Fixed64 => "fixed64".to_string(),
+            Kind::Sfixed32 => "sfixed32".to_string(),
+            Kind::Sfixed64 => "sfixed64".to_string(),
+            Kind::Bool => "bool".to_string(),
+            Kind::String => "string".to_string(),
+            Kind::Bytes => "bytes".to_string(),
+            Kind::Message(message) => self.relative_name(message.full_name()),
+            Kind::Enum(enum_type) => self.relative_name(enum_type.full_name()),
+        }
+    }
+
+    /// Names are reported relative to the file's package, as written in the source
+    fn relative_name(&self, full_name: &str) -> String {
+        if self.package.is_empty() {
+            return full_name.to_string();
+        }
+
+        full_name
+            .strip_prefix(self.package.as_str())
+            .and_then(|rest| rest.strip_prefix('.'))
+            .unwrap_or(full_name)
+            .to_string()
+    }
+
+    fn comments(&self, path: &[i32]) -> Option<ProtoComments> {
+        let location = self.locations.get(path)?;
+        let comments = ProtoComments {
+            leading: location.leading_comments.as_deref().and_then(comment_text),
+            trailing: location.trailing_comments.as_deref().and_then(comment_text),
+        };
+
+        (comments.leading.is_some() || comments.trailing.is_some()).then_some(comments)
+    }
+}
+
+/// How a field is declared, telling maps apart from other repeated fields
+pub fn cardinality(field: &FieldDescriptor) -> ProtoCardinality {
+    if field.is_map() {
+        return ProtoCardinality::Map;
+    }
+
+    match field.cardinality() {
+        Cardinality::Repeated => ProtoCardinality::Repeated,
+        Cardinality::Required => ProtoCardinality::Required,
+        Cardinality::Optional => {
+            let proto = field.field_descriptor_proto();
+            let is_proto3 = field.parent_file().file_descriptor_proto().syntax() == "proto3";
+            if proto.proto3_optional() || (!is_proto3 && field.containing_oneof().is_none()) {
+                ProtoCardinality::Optional
+            } else {
+                ProtoCardinality::Singular
+            }
+        }
+    }
+}
+
+fn is_deprecated(options: &DynamicMessage) -> bool {
+    options
+        .get_field_by_name("deprecated")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+/// The options that are set, in their JSON form
+fn options_map(options: &DynamicMessage) -> Map<String, Value> {
+    match serde_json::to_value(options) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Strip the space that follows `//` on every line of a comment
+fn comment_text(comment: &str) -> Option<String> {
+    let text = comment
+        .lines()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = text.trim();
+
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::proto_parser::ProtoParser;
+    use serde_json::json;
+
+    const DOCUMENTED_PROTO: &str = r#"
+syntax = "proto3";
+
+package docs.v1;
+
+import "google/protobuf/descriptor.proto";
+import "google/protobuf/timestamp.proto";
+
+extend google.protobuf.FieldOptions {
+  string label = 50000;
+}
+
+// Manages user accounts.
+service Accounts {
+  // Create an account.
+  // Fails when the email is taken.
+  rpc Create(Account) returns (Account);
+  rpc Legacy(Account) returns (Account) {
+    option deprecated = true;
+  }
+}
+
+// An account of the service
+message Account {
+  // Kinds of accounts
+  enum Kind {
+    KIND_UNSPECIFIED = 0;
+    ADMIN = 1 [deprecated = true];
+  }
+
+  string id = 1; // Generated on creation
+  string email = 2 [(label) = "E-mail", deprecated = true];
+  Kind kind = 3;
+  map<string, google.protobuf.Timestamp> logins = 4;
+  repeated string tags = 5;
+  optional string nickname = 6;
+  // How to reach the owner
+  oneof contact {
+    string phone = 7;
+    string address = 8;
+  }
+}
+
+enum Region {
+  REGION_UNSPECIFIED = 0;
+}
+"#;
+
+    fn documented_schema() -> ProtoSchema {
+        ProtoParser::parse_proto_file(DOCUMENTED_PROTO).unwrap()
+    }
+
+    fn comment(text: &str) -> Option<ProtoComments> {
+        Some(ProtoComments {
+            leading: Some(text.to_string()),
+            trailing: None,
+        })
+    }
+
+    #[test]
+    fn test_services_and_methods() {
+        let schema = documented_schema();
+        let service = schema.service("docs.v1.Accounts").unwrap();
+
+        assert_eq!(service.name, "Accounts");
+        assert_eq!(service.comments, comment("Manages user accounts."));
+        assert_eq!(schema.service("Accounts"), Some(service));
+
+        let create = service.method("Create").unwrap();
+        assert_eq!(create.input_type, "Account");
+        assert_eq!(create.input_type_full_name, "docs.v1.Account");
+        assert_eq!(
+            create.comments,
+            comment("Create an account.\nFails when the email is taken.")
+        );
+        assert!(!create.deprecated);
+
+        let legacy = service.method("Legacy").unwrap();
+        assert!(legacy.deprecated);
+        assert_eq!(
+            legacy.options,
+            json!({"deprecated": true}).as_object().cloned().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let schema = documented_schema();
+        let account = &schema.messages[0];
+        let field = |name: &str| account.fields.iter().find(|f| f.name == name).unwrap();
+
+        assert_eq!(account.full_name, "docs.v1.Account");
+        assert_eq!(account.comments, comment("An account of the service"));
+
+        let id = field("id");
+        assert_eq!(id.cardinality, ProtoCardinality::Singular);
+        assert_eq!(
+            id.comments,
+            Some(ProtoComments {
+                leading: None,
+                trailing: Some("Generated on creation".to_string()),
+            })
+        );
+
+        let email = field("email");
+        assert!(email.deprecated);
+        assert_eq!(email.options["[docs.v1.label]"], "E-mail");
+
+        let kind = field("kind");
+        assert_eq!(kind.field_type, "Account.Kind");
+        assert_eq!(kind.type_name.as_deref(), Some("docs.v1.Account.Kind"));
+
+        let logins = field("logins");
+        assert_eq!(logins.cardinality, ProtoCardinality::Map);
+        assert_eq!(logins.field_type, "map<string, google.protobuf.Timestamp>");
+        assert_eq!(
+            logins.map,
+            Some(ProtoMapType {
+                key_type: "string".to_string(),
+                value_type: "google.protobuf.Timestamp".to_string(),
+            })
+        );
+        assert_eq!(
+            logins.type_name.as_deref(),
+            Some("google.protobuf.Timestamp")
+        );
+        assert!(!logins.repeated);
+
+        assert_eq!(field("tags").cardinality, ProtoCardinality::Repeated);
+        assert_eq!(field("nickname").cardinality, ProtoCardinality::Optional);
+        assert_eq!(field("nickname").oneof, None);
+        assert_eq!(field("phone").oneof.as_deref(), Some("contact"));
+        assert_eq!(field("phone").json_name, "phone");
+    }
+
+    #[test]
+    fn test_oneofs_leave_out_proto3_optional() {
+        let schema = documented_schema();
+
+        assert_eq!(
+            schema.messages[0].oneofs,
+            vec![ProtoOneof {
+                name: "contact".to_string(),
+                fields: vec!["phone".to_string(), "address".to_string()],
+                comments: comment("How to reach the owner"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_enums() {
+        let schema = documented_schema();
+
+        let names: Vec<&str> = schema.enums.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Account.Kind", "Region"]);
+
+        let kind = &schema.enums[0];
+        assert_eq!(kind.full_name, "docs.v1.Account.Kind");
+        assert_eq!(kind.comments, comment("Kinds of accounts"));
+        assert_eq!(kind.values[1].name, "ADMIN");
+        assert_eq!(kind.values[1].number, 1);
+        assert!(kind.values[1].deprecated);
+    }
+
+    #[test]
+    fn test_proto2_cardinality_and_defaults() {
+        let schema = ProtoParser::parse_proto_file(
+            r#"
+syntax = "proto2";
+
+message Settings {
+  required string name = 1;
+  optional int32 retries = 2 [default = 3];
+  repeated string hosts = 3;
+}
+"#,
+        )
+        .unwrap();
+        let fields = &schema.messages[0].fields;
+
+        assert_eq!(fields[0].cardinality, ProtoCardinality::Required);
+        assert_eq!(fields[1].cardinality, ProtoCardinality::Optional);
+        assert_eq!(fields[1].default_value.as_deref(), Some("3"));
+        assert_eq!(fields[2].cardinality, ProtoCardinality::Repeated);
+    }
+
+    #[test]
+    fn test_pool_schema_covers_imported_files() {
+        let pool = ProtoParser::compile(DOCUMENTED_PROTO).unwrap();
+        let schema = ProtoSchema::from_pool(&pool);
+
+        let timestamp = schema
+            .messages
+            .iter()
+            .find(|m| m.full_name == "google.protobuf.Timestamp")
+            .unwrap();
+        assert_eq!(timestamp.name, "Timestamp");
+        // Map entries are an encoding detail
+        assert!(!schema.messages.iter().any(|m| m.name.ends_with("Entry")));
+    }
+}
@@ -0,0 +1,390 @@
+use crate::client::proxy::ProxySettings;
+use crate::error::{AppError, AppResult};
+use crate::grpc::channel::connect;
+use crate::grpc::client::GrpcClient;
+use crate::grpc::tls::GrpcTlsConfig;
+use crate::grpc::transport::HttpTransportClient;
+use crate::grpc::DescriptorSource;
+use prost_reflect::DescriptorPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::OnceCell;
+use tonic::transport::Channel;
+
+type ChannelKey = (String, Option<GrpcTlsConfig>, ProxySettings);
+type PoolKey = (String, DescriptorSource);
+
+/// Connections and descriptors shared by all gRPC commands.
+///
+/// Channels are pooled per target URL, TLS and proxy settings, and kept
+/// alive between calls.
+/// Descriptor pools are cached per endpoint and descriptor source, so
+/// reflection only runs once per endpoint until it is refreshed or evicted.
+#[derive(Clone, Default)]
+pub struct GrpcState {
+    channels: Arc<Mutex<HashMap<ChannelKey, Channel>>>,
+    pools: Arc<Mutex<HashMap<PoolKey, Arc<OnceCell<DescriptorPool>>>>>,
+}
+
+impl GrpcState {
+    /// Get the pooled channel to `url`, connecting on first use
+    pub async fn channel(
+        &self,
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+    ) -> AppResult<Channel> {
+        let key = (url.to_string(), tls.cloned(), proxy.clone());
+        if let Some(channel) = self.lock_channels()?.get(&key) {
+            return Ok(channel.clone());
+        }
+
+        let channel = connect(url, tls, proxy).await?;
+        Ok(self.lock_channels()?.entry(key).or_insert(channel).clone())
+    }
+
+    /// Open a new channel to `url`, replacing the pooled one
+    pub async fn reconnect(
+        &self,
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+    ) -> AppResult<Channel> {
+        let channel = connect(url, tls, proxy).await?;
+        self.lock_channels()?.insert(
+            (url.to_string(), tls.cloned(), proxy.clone()),
+            channel.clone(),
+        );
+        Ok(channel)
+    }
+
+    /// Get the descriptors of `url` from `source`, loading them on first use.
+    /// Concurrent callers share a single reflection round trip.
+    pub async fn descriptor_pool(
+        &self,
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+        source: &DescriptorSource,
+    ) -> AppResult<DescriptorPool> {
+        let cell = self
+            .lock_pools()?
+            .entry((url.to_string(), source.clone()))
+            .or_default()
+            .clone();
+
+        let pool = cell
+            .get_or_try_init(|| async {
+                // Local sources are loaded without touching the network
+                if let Some(pool) = source.load_pool()? {
+                    return Ok(pool);
+                }
+
+                let channel = self.channel(url, tls, proxy).await?;
+                let mut client = GrpcClient::from_channel(channel, source)?;
+                client.get_descriptor_pool().await.cloned()
+            })
+            .await?;
+
+        Ok(pool.clone())
+    }
+
+    /// Create a client on the pooled channel using the cached descriptors
+    pub async fn client(
+        &self,
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+        source: &DescriptorSource,
+    ) -> AppResult<GrpcClient> {
+        let pool = self.descriptor_pool(url, tls, proxy, source).await?;
+        let channel = self.channel(url, tls, proxy).await?;
+
+        Ok(GrpcClient::with_descriptor_pool(channel, pool))
+    }
+
+    /// Create a gRPC-Web or Connect client using the cached descriptors. These
+    /// transports go through the shared HTTP client, which has no per-endpoint
+    /// TLS settings. Reflection is a bidirectional stream they cannot carry,
+    /// so their descriptors must come from local files.
+    pub async fn http_transport_client(
+        &self,
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+        source: &DescriptorSource,
+    ) -> AppResult<HttpTransportClient> {
+        if tls.is_some_and(|tls| *tls != GrpcTlsConfig::default()) {
+            return Err(AppError::validation(
+                "tls",
+                "TLS settings are only supported by the native gRPC transport",
+            ));
+        }
+        if *source == DescriptorSource::Reflection {
+            return Err(AppError::validation(
+                "descriptor_source",
+                "Reflection needs the native gRPC transport; use .proto files or a protoset",
+            ));
+        }
+
+        let pool = self.descriptor_pool(url, tls, proxy, source).await?;
+        HttpTransportClient::new(pool, proxy)
+    }
+
+    /// Drop the cached descriptors of `url` and `source` and load them again
+    pub async fn refresh(
+        &self,
+        url: &str,
+        tls: Option<&GrpcTlsConfig>,
+        proxy: &ProxySettings,
+        source: &DescriptorSource,
+    ) -> AppResult<DescriptorPool> {
+        self.lock_pools()?
+            .remove(&(url.to_string(), source.clone()));
+        self.descriptor_pool(url, tls, proxy, source).await
+    }
+
+    /// Forget the channels and every descriptor pool cached for `url`.
+    /// Returns whether anything was cached.
+    pub fn evict(&self, url: &str) -> AppResult<bool> {
+        let mut channels = self.lock_channels()?;
+        let cached_channels = channels.len();
+        channels.retain(|(channel_url, _, _), _| channel_url != url);
+        let had_channel = channels.len() != cached_channels;
+        drop(channels);
+
+        let mut pools = self.lock_pools()?;
+        let cached_pools = pools.len();
+        pools.retain(|(pool_url, _), _| pool_url != url);
+
+        Ok(had_channel || pools.len() != cached_pools)
+    }
+
+    fn lock_channels(&self) -> AppResult<MutexGuard<'_, HashMap<ChannelKey, Channel>>> {
+        self.channels
+            .lock()
+            .map_err(|_| AppError::internal("gRPC channel pool lock poisoned"))
+    }
+
+    fn lock_pools(
+        &self,
+    ) -> AppResult<MutexGuard<'_, HashMap<PoolKey, Arc<OnceCell<DescriptorPool>>>>> {
+        self.pools
+            .lock()
+            .map_err(|_| AppError::internal("gRPC descriptor cache lock poisoned"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::proto_parser::ProtoParser;
+    use crate::grpc::test_utils::fixtures;
+    use crate::grpc::test_utils::server::TestServer;
+    use crate::grpc::{GrpcCallType, GrpcRequest};
+
+    fn echo_request(url: &str) -> GrpcRequest {
+        GrpcRequest {
+            url: url.to_string(),
+            service: "test.v1.Echo".to_string(),
+            method: "Echo".to_string(),
+            message: serde_json::json!({"message": "cached"}),
+            metadata: None,
+            call_type: GrpcCallType::Unary,
+            options: Default::default(),
+            transport: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_descriptor_pool_is_cached_per_endpoint() {
+        let server = TestServer::start().await;
+        let url = server.url.clone();
+        let state = GrpcState::default();
+
+        state
+            .descriptor_pool(
+                &url,
+                None,
+                &ProxySettings::direct(),
+                &DescriptorSource::Reflection,
+            )
+            .await
+            .unwrap();
+        drop(server);
+
+        // The server is gone: only the cache can answer now
+        let pool = state
+            .descriptor_pool(
+                &url,
+                None,
+                &ProxySettings::direct(),
+                &DescriptorSource::Reflection,
+            )
+            .await
+            .unwrap();
+        assert!(pool.get_service_by_name("test.v1.Echo").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_client_calls_through_pooled_channel() {
+        let server = TestServer::start().await;
+        let state = GrpcState::default();
+
+        for _ in 0..2 {
+            let mut client = state
+                .client(
+                    &server.url,
+                    None,
+                    &ProxySettings::direct(),
+                    &DescriptorSource::Reflection,
+                )
+                .await
+                .unwrap();
+            let response = client.unary_call(echo_request(&server.url)).await.unwrap();
+            assert!(response.success);
+        }
+
+        assert_eq!(state.lock_channels().unwrap().len(), 1);
+        assert_eq!(state.lock_pools().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_channels_pooled_per_proxy() {
+        let server = TestServer::start().await;
+        let state = GrpcState::default();
+        // Bypassed for the test server, yet other settings
+        let bypassed = ProxySettings {
+            url: Some("http://127.0.0.1:1".to_string()),
+            bypass: vec!["127.0.0.1".to_string()],
+            ..ProxySettings::direct()
+        };
+
+        for proxy in [ProxySettings::direct(), bypassed] {
+            let mut client = state
+                .client(&server.url, None, &proxy, &DescriptorSource::Reflection)
+                .await
+                .unwrap();
+            let response = client.unary_call(echo_request(&server.url)).await.unwrap();
+            assert!(response.success);
+        }
+
+        assert_eq!(state.lock_channels().unwrap().len(), 2);
+        assert_eq!(state.lock_pools().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_reloads_descriptors() {
+        let dir = tempfile::tempdir().unwrap();
+        let proto_path = dir.path().join("echo.proto");
+        std::fs::write(&proto_path, fixtures::simple_echo_proto()).unwrap();
+        let source = DescriptorSource::ProtoFiles {
+            files: vec![proto_path.to_string_lossy().to_string()],
+            import_paths: vec![],
+        };
+        let state = GrpcState::default();
+        let url = "http://127.0.0.1:1";
+
+        let pool = state
+            .descriptor_pool(url, None, &ProxySettings::direct(), &source)
+            .await
+            .unwrap();
+        assert_eq!(
+            pool.get_service_by_name("test.v1.Echo")
+                .unwrap()
+                .methods()
+                .len(),
+            1
+        );
+
+        std::fs::write(&proto_path, fixtures::echo_service_proto()).unwrap();
+        let cached = state
+            .descriptor_pool(url, None, &ProxySettings::direct(), &source)
+            .await
+            .unwrap();
+        assert_eq!(
+            cached
+                .get_service_by_name("test.v1.Echo")
+                .unwrap()
+                .methods()
+                .len(),
+            1
+        );
+
+        let refreshed = state
+            .refresh(url, None, &ProxySettings::direct(), &source)
+            .await
+            .unwrap();
+        assert_eq!(
+            refreshed
+                .get_service_by_name("test.v1.Echo")
+                .unwrap()
+                .methods()
+                .len(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_evict_forgets_endpoint() {
+        let server = TestServer::start().await;
+        let state = GrpcState::default();
+
+        state
+            .client(
+                &server.url,
+                None,
+                &ProxySettings::direct(),
+                &DescriptorSource::Reflection,
+            )
+            .await
+            .unwrap();
+
+        assert!(state.evict(&server.url).unwrap());
+        assert!(state.lock_channels().unwrap().is_empty());
+        assert!(state.lock_pools().unwrap().is_empty());
+        assert!(!state.evict(&server.url).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_http_transport_needs_local_descriptors() {
+        let server = TestServer::start().await;
+        let state = GrpcState::default();
+
+        // Even with the server reachable over native gRPC
+        let result = state
+            .http_transport_client(
+                &server.url,
+                None,
+                &ProxySettings::direct(),
+                &DescriptorSource::Reflection,
+            )
+            .await;
+        assert!(
+            matches!(result, Err(AppError::ValidationError { ref field, .. }) if field == "descriptor_source")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_is_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("echo.pb");
+        let source = DescriptorSource::Protoset {
+            path: path.to_string_lossy().to_string(),
+        };
+        let state = GrpcState::default();
+        let url = "http://127.0.0.1:1";
+
+        assert!(state
+            .descriptor_pool(url, None, &ProxySettings::direct(), &source)
+            .await
+            .is_err());
+
+        let pool = ProtoParser::compile(fixtures::echo_service_proto()).unwrap();
+        std::fs::write(&path, pool.encode_to_vec()).unwrap();
+        assert!(state
+            .descriptor_pool(url, None, &ProxySettings::direct(), &source)
+            .await
+            .is_ok());
+    }
+}
@@ -1,370 +1,489 @@
-use crate::error::AppResult;
-use crate::grpc::{GrpcRequest, GrpcResponse};
+use crate::error::{AppError, AppResult};
+use crate::grpc::{GrpcResponse, JsonOptions};
+use prost_reflect::{DynamicMessage, MessageDescriptor};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio::sync::mpsc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, oneshot};
+use tonic::Status;
+
+/// Event carrying one decoded message of a stream
+pub const STREAM_MESSAGE_EVENT: &str = "grpc-stream-message";
+/// Event sent once when a stream finishes, successfully or not
+pub const STREAM_END_EVENT: &str = "grpc-stream-end";
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+/// Generate a process-unique id for a new stream
+pub fn next_stream_id() -> String {
+    format!(
+        "grpc-stream-{}",
+        NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Final status of a stream, including the metadata sent by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcStreamEnd {
+    pub success: bool,
+    pub error: Option<String>,
+    pub status_code: Option<u32>,
+    pub status_message: Option<String>,
+    pub headers: HashMap<String, Vec<String>>,
+    pub trailers: HashMap<String, Vec<String>>,
+    pub details: Vec<Value>,
+    pub message_count: u64,
+}
+
+impl GrpcStreamEnd {
+    pub fn new(response: GrpcResponse, message_count: u64) -> Self {
+        Self {
+            success: response.success,
+            error: response.error,
+            status_code: response.status_code,
+            status_message: response.status_message,
+            headers: response.headers,
+            trailers: response.trailers,
+            details: response.details,
+            message_count,
+        }
+    }
+
+    /// End of a stream that finished with `status`, using its metadata as trailers
+    pub fn from_status(status: &Status, message_count: u64, json: &JsonOptions) -> Self {
+        Self::new(GrpcResponse::from_status(status, json), message_count)
+    }
+
+    /// End of a stream that failed before or outside of the gRPC exchange
+    pub fn from_error(error: &AppError) -> Self {
+        Self::new(GrpcResponse::from_error(error), 0)
+    }
+}
+
+/// What a running call reports back while it is being consumed
+#[derive(Debug, Clone)]
+pub enum GrpcStreamEvent {
+    Message(Value),
+    End(GrpcStreamEnd),
+}
+
+/// Payload of `STREAM_MESSAGE_EVENT`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcStreamMessagePayload {
+    pub stream_id: String,
+    pub sequence: u64,
+    pub data: Value,
+}
+
+/// Payload of `STREAM_END_EVENT`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcStreamEndPayload {
+    pub stream_id: String,
+    #[serde(flatten)]
+    pub end: GrpcStreamEnd,
+}
 
 pub struct GrpcStreaming;
 
 impl GrpcStreaming {
-    pub async fn handle_server_streaming(
-        request: GrpcRequest,
-        tx: mpsc::Sender<GrpcResponse>,
-    ) -> AppResult<()> {
-        // Mock server streaming implementation
-        // In a real implementation, this would handle the actual gRPC stream
-
-        let mock_responses   = vec![
-            serde_json::json!({
-                "message": "Stream started",
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-                "data": request.message
-            }),
-            serde_json::json!({
-                "message": "Stream data 1",
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-                "data": serde_json::json!({"id": 1, "name": "User 1"})
-            }),
-            serde_json::json!({
-                "message": "Stream data 2",
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-                "data": serde_json::json!({"id": 2, "name": "User 2"})
-            }),
-        ];
-
-        for (i, data) in mock_responses.into_iter().enumerate() {
-            let response = GrpcResponse {
-                success: true,
-                data: Some(data),
-                error: None,
-                status_code: Some(0),
-                status_message: Some(format!("Stream message {}", i + 1)),
+    /// Forward stream events to the frontend, tagged with `stream_id`,
+    /// until the call reports its end or the sender is dropped
+    pub async fn emit_events(
+        app: AppHandle,
+        stream_id: String,
+        mut rx: mpsc::Receiver<GrpcStreamEvent>,
+    ) {
+        let mut sequence = 0;
+
+        while let Some(event) = rx.recv().await {
+            let emitted = match event {
+                GrpcStreamEvent::Message(data) => {
+                    sequence += 1;
+                    app.emit(
+                        STREAM_MESSAGE_EVENT,
+                        GrpcStreamMessagePayload {
+                            stream_id: stream_id.clone(),
+                            sequence,
+                            data,
+                        },
+                    )
+                }
+                GrpcStreamEvent::End(mut end) => {
+                    // Calls that end abruptly (e.g. cancelled) do not know how
+                    // many messages reached the frontend, but this loop does
+                    end.message_count = sequence;
+                    let _ = app.emit(
+                        STREAM_END_EVENT,
+                        GrpcStreamEndPayload {
+                            stream_id: stream_id.clone(),
+                            end,
+                        },
+                    );
+                    return;
+                }
             };
 
-            if tx.send(response).await.is_err() {
+            if emitted.is_err() {
                 break;
             }
-
-            // Simulate some delay between messages
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
         }
+    }
+}
 
-        // Send final message
-        let final_response = GrpcResponse {
-            success: true,
-            data: Some(serde_json::json!({
-                "message": "Stream completed",
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("Stream completed".to_string()),
-        };
+/// A running call the frontend can still interact with
+struct StreamSession {
+    /// Tells this session apart from a later one reusing its stream id
+    number: u64,
+    /// Request message type and the sender feeding the request stream.
+    /// `None` for server-streaming calls and once the client half-closed.
+    input: Option<(MessageDescriptor, mpsc::Sender<DynamicMessage>)>,
+    cancel: Option<oneshot::Sender<()>>,
+}
 
-        let _ = tx.send(final_response).await;
+/// Registry of running streams, managed as Tauri state
+#[derive(Clone, Default)]
+pub struct GrpcStreamSessions {
+    sessions: Arc<Mutex<HashMap<String, StreamSession>>>,
+}
 
-        Ok(())
-    }
+impl GrpcStreamSessions {
+    /// Run `call` in the background as stream `stream_id`.
+    ///
+    /// `input` is the request message type for client-streaming and
+    /// bidirectional calls; `call` receives the request messages pushed with
+    /// [`send`](Self::send) and the sender for its events. Returns the receiver
+    /// of those events, which ends with a single `End` event. Fails if
+    /// `stream_id` is the id of a running stream.
+    pub fn start<F, Fut>(
+        &self,
+        stream_id: &str,
+        input: Option<MessageDescriptor>,
+        call: F,
+    ) -> AppResult<mpsc::Receiver<GrpcStreamEvent>>
+    where
+        F: FnOnce(mpsc::Receiver<DynamicMessage>, mpsc::Sender<GrpcStreamEvent>) -> Fut,
+        Fut: Future<Output = AppResult<()>> + Send + 'static,
+    {
+        let (tx, rx) = create_event_channel();
+        let (message_tx, message_rx) = mpsc::channel(100);
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let number = NEXT_SESSION.fetch_add(1, Ordering::Relaxed);
+
+        {
+            let mut sessions = self.lock();
+            if sessions.contains_key(stream_id) {
+                return Err(AppError::validation(
+                    "stream_id",
+                    format!("Stream '{}' is already running", stream_id),
+                ));
+            }
+            sessions.insert(
+                stream_id.to_string(),
+                StreamSession {
+                    number,
+                    input: input.map(|desc| (desc, message_tx)),
+                    cancel: Some(cancel_tx),
+                },
+            );
+        }
 
-    pub async fn handle_client_streaming(
-        mut rx: mpsc::Receiver<Value>,
-        tx: mpsc::Sender<GrpcResponse>,
-    ) -> AppResult<()> {
-        // Mock client streaming implementation
-        let mut received_messages = Vec::new();
-
-        while let Some(message) = rx.recv().await {
-            received_messages.push(message);
-
-            // Send acknowledgment for each received message
-            let ack = GrpcResponse {
-                success: true,
-                data: Some(serde_json::json!({
-                    "message": "Message received",
-                    "count": received_messages.len(),
-                    "timestamp": chrono::Utc::now().to_rfc3339()
-                })),
-                error: None,
-                status_code: Some(0),
-                status_message: Some("Message acknowledged".to_string()),
+        let call = call(message_rx, tx.clone());
+        let sessions = self.clone();
+        let stream_id = stream_id.to_string();
+
+        tokio::spawn(async move {
+            let end = tokio::select! {
+                result = call => result.err().map(|e| GrpcStreamEnd::from_error(&e)),
+                _ = cancel_rx => Some(GrpcStreamEnd::from_status(
+                    &Status::cancelled("Stream cancelled by the client"),
+                    0,
+                    &JsonOptions::default(),
+                )),
             };
 
-            if tx.send(ack).await.is_err() {
-                break;
+            if let Some(end) = end {
+                let _ = tx.send(GrpcStreamEvent::End(end)).await;
             }
-        }
+            let mut sessions = sessions.lock();
+            if sessions
+                .get(&stream_id)
+                .is_some_and(|session| session.number == number)
+            {
+                sessions.remove(&stream_id);
+            }
+        });
 
-        // Send final response with summary
-        let final_response = GrpcResponse {
-            success: true,
-            data: Some(serde_json::json!({
-                "message": "Client stream completed",
-                "total_messages": received_messages.len(),
-                "messages": received_messages,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("Client stream completed".to_string()),
+        Ok(rx)
+    }
+
+    /// Push a JSON message into the request stream of `stream_id`
+    pub async fn send(&self, stream_id: &str, message: &Value) -> AppResult<()> {
+        let (desc, sender) = {
+            let sessions = self.lock();
+            let session = sessions
+                .get(stream_id)
+                .ok_or_else(|| unknown_stream(stream_id))?;
+            session.input.clone().ok_or_else(|| {
+                AppError::validation(
+                    "stream_id",
+                    format!("Stream '{}' does not accept messages", stream_id),
+                )
+            })?
         };
 
-        let _ = tx.send(final_response).await;
+        let message = DynamicMessage::deserialize(desc, message.clone())?;
+        sender.send(message).await.map_err(|_| {
+            AppError::validation(
+                "stream_id",
+                format!("Stream '{}' has already finished", stream_id),
+            )
+        })
+    }
 
+    /// Half-close the request stream: the server sees the end of the client
+    /// messages while its responses keep flowing
+    pub fn close_send(&self, stream_id: &str) -> AppResult<()> {
+        let mut sessions = self.lock();
+        let session = sessions
+            .get_mut(stream_id)
+            .ok_or_else(|| unknown_stream(stream_id))?;
+        session.input = None;
         Ok(())
     }
 
-    pub async fn handle_bidirectional_streaming(
-        mut rx: mpsc::Receiver<Value>,
-        tx: mpsc::Sender<GrpcResponse>,
-    ) -> AppResult<()> {
-        // Mock bidirectional streaming implementation
-        let mut message_count = 0;
-
-        while let Some(message) = rx.recv().await {
-            message_count += 1;
-
-            // Echo back the message with some processing
-            let echo_response = GrpcResponse {
-                success: true,
-                data: Some(serde_json::json!({
-                    "message": "Echo response",
-                    "original_message": message,
-                    "message_number": message_count,
-                    "timestamp": chrono::Utc::now().to_rfc3339()
-                })),
-                error: None,
-                status_code: Some(0),
-                status_message: Some("Message echoed".to_string()),
-            };
-
-            if tx.send(echo_response).await.is_err() {
-                break;
-            }
+    /// Abort the call; the stream ends with a CANCELLED status
+    pub fn cancel(&self, stream_id: &str) -> AppResult<()> {
+        let cancel = {
+            let mut sessions = self.lock();
+            let session = sessions
+                .get_mut(stream_id)
+                .ok_or_else(|| unknown_stream(stream_id))?;
+            session.cancel.take()
+        };
 
-            // Simulate processing time
-            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        if let Some(cancel) = cancel {
+            let _ = cancel.send(());
         }
+        Ok(())
+    }
 
-        // Send completion message
-        let completion_response = GrpcResponse {
-            success: true,
-            data: Some(serde_json::json!({
-                "message": "Bidirectional stream completed",
-                "total_messages_processed": message_count,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-            error: None,
-            status_code: Some(0),
-            status_message: Some("Bidirectional stream completed".to_string()),
-        };
-
-        let _ = tx.send(completion_response).await;
+    pub fn is_active(&self, stream_id: &str) -> bool {
+        self.lock().contains_key(stream_id)
+    }
 
-        Ok(())
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, StreamSession>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
     }
 }
 
-// Helper function to create streaming channels
-pub fn create_streaming_channels() -> (mpsc::Sender<Value>, mpsc::Receiver<Value>) {
-    mpsc::channel(100) // Buffer size of 100
+fn unknown_stream(stream_id: &str) -> AppError {
+    AppError::validation("stream_id", format!("Unknown stream '{}'", stream_id))
+}
+
+// Helper function to create the channel a running call reports its events on
+pub fn create_event_channel() -> (
+    mpsc::Sender<GrpcStreamEvent>,
+    mpsc::Receiver<GrpcStreamEvent>,
+) {
+    mpsc::channel(100) // Bounded so a slow frontend applies backpressure to the stream
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[tokio::test]
-    async fn test_server_streaming_sends_multiple_messages() {
-        let (tx, mut rx) = mpsc::channel(10);
-
-        let request = GrpcRequest {
-            url: "http://localhost:50051".to_string(),
-            service: "TestService".to_string(),
-            method: "StreamData".to_string(),
-            message: serde_json::json!({"test": "data"}),
-            metadata: None,
-            call_type: crate::grpc::GrpcCallType::ServerStreaming,
-        };
-
-        // Start streaming in background
-        let handle = tokio::spawn(async move {
-            GrpcStreaming::handle_server_streaming(request, tx).await
-        });
-
-        // Collect all messages
-        let mut messages = Vec::new();
-        while let Some(response) = rx.recv().await {
-            messages.push(response);
-        }
-
-        // Wait for completion
-        let result = handle.await.unwrap();
-        assert!(result.is_ok());
+    #[test]
+    fn test_next_stream_id_is_unique() {
+        let first = next_stream_id();
+        let second = next_stream_id();
 
-        // Verify we received multiple messages
-        assert!(messages.len() >= 3, "Should receive at least 3 stream messages");
+        assert!(first.starts_with("grpc-stream-"));
+        assert_ne!(first, second);
+    }
 
-        // Verify all messages are successful
-        for msg in &messages {
-            assert!(msg.success, "All stream messages should be successful");
-            assert!(msg.data.is_some(), "All messages should have data");
-        }
+    #[test]
+    fn test_stream_end_from_error() {
+        let end = GrpcStreamEnd::from_error(&AppError::network("Connection failed"));
+
+        assert!(!end.success);
+        assert_eq!(
+            end.error.as_deref(),
+            Some("Network Error: Connection failed")
+        );
+        assert!(end.status_code.is_none());
+        assert_eq!(end.message_count, 0);
     }
 
-    #[tokio::test]
-    async fn test_server_streaming_final_message() {
-        let (tx, mut rx) = mpsc::channel(10);
-
-        let request = GrpcRequest {
-            url: "http://localhost:50051".to_string(),
-            service: "TestService".to_string(),
-            method: "StreamData".to_string(),
-            message: serde_json::json!({"test": "data"}),
-            metadata: None,
-            call_type: crate::grpc::GrpcCallType::ServerStreaming,
+    #[test]
+    fn test_stream_end_payload_is_flattened() {
+        let payload = GrpcStreamEndPayload {
+            stream_id: "grpc-stream-1".to_string(),
+            end: GrpcStreamEnd::from_status(
+                &Status::not_found("missing"),
+                2,
+                &JsonOptions::default(),
+            ),
         };
 
-        tokio::spawn(async move {
-            let _ = GrpcStreaming::handle_server_streaming(request, tx).await;
-        });
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(json["stream_id"], "grpc-stream-1");
+        assert_eq!(json["status_code"], 5);
+        assert_eq!(json["status_message"], "NOT_FOUND");
+        assert_eq!(json["message_count"], 2);
+    }
 
-        let mut last_message = None;
-        while let Some(response) = rx.recv().await {
-            last_message = Some(response);
-        }
+    fn echo_input() -> MessageDescriptor {
+        crate::grpc::test_utils::server::echo_descriptor_pool()
+            .get_message_by_name("test.v1.EchoRequest")
+            .unwrap()
+    }
 
-        // Verify final message
-        assert!(last_message.is_some());
-        let last = last_message.unwrap();
-        assert!(last.success);
+    /// Start a session whose call reports every pushed message back as an event
+    fn start_echo_session(
+        sessions: &GrpcStreamSessions,
+        stream_id: &str,
+    ) -> mpsc::Receiver<GrpcStreamEvent> {
+        sessions
+            .start(
+                stream_id,
+                Some(echo_input()),
+                |mut messages, tx| async move {
+                    let mut count = 0;
+                    while let Some(message) = messages.recv().await {
+                        count += 1;
+                        let data = serde_json::to_value(&message)?;
+                        let _ = tx.send(GrpcStreamEvent::Message(data)).await;
+                    }
+                    let end =
+                        GrpcStreamEnd::from_status(&Status::ok(""), count, &JsonOptions::default());
+                    let _ = tx.send(GrpcStreamEvent::End(end)).await;
+                    Ok(())
+                },
+            )
+            .unwrap()
+    }
 
-        if let Some(data) = last.data {
-            if let Some(message) = data.get("message") {
-                let msg_str = message.as_str().unwrap_or("");
-                assert!(msg_str.contains("completed"), "Final message should indicate completion");
-            }
+    async fn collect(mut rx: mpsc::Receiver<GrpcStreamEvent>) -> Vec<GrpcStreamEvent> {
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
         }
+        events
     }
 
     #[tokio::test]
-    async fn test_client_streaming_aggregates_messages() {
-        let (msg_tx, msg_rx) = mpsc::channel(10);
-        let (resp_tx, mut resp_rx) = mpsc::channel(10);
-
-        // Start client streaming handler
-        tokio::spawn(async move {
-            let _ = GrpcStreaming::handle_client_streaming(msg_rx, resp_tx).await;
-        });
-
-        // Send multiple messages
-        msg_tx.send(serde_json::json!({"id": 1, "data": "message 1"})).await.unwrap();
-        msg_tx.send(serde_json::json!({"id": 2, "data": "message 2"})).await.unwrap();
-        msg_tx.send(serde_json::json!({"id": 3, "data": "message 3"})).await.unwrap();
-
-        // Close sender to signal end of stream
-        drop(msg_tx);
-
-        // Collect responses
-        let mut responses = Vec::new();
-        while let Some(response) = resp_rx.recv().await {
-            responses.push(response);
+    async fn test_session_send_and_close() {
+        let sessions = GrpcStreamSessions::default();
+        let rx = start_echo_session(&sessions, "s1");
+
+        sessions
+            .send("s1", &serde_json::json!({"message": "a"}))
+            .await
+            .unwrap();
+        sessions
+            .send("s1", &serde_json::json!({"message": "b"}))
+            .await
+            .unwrap();
+        sessions.close_send("s1").unwrap();
+
+        let events = collect(rx).await;
+        assert_eq!(events.len(), 3);
+        match &events[1] {
+            GrpcStreamEvent::Message(data) => assert_eq!(data["message"], "b"),
+            other => panic!("Expected message event, got {:?}", other),
         }
-
-        // Should have acknowledgments + final response
-        assert!(responses.len() >= 3, "Should have at least 3 responses");
-
-        // Check final response
-        let final_resp = responses.last().unwrap();
-        assert!(final_resp.success);
-
-        if let Some(data) = &final_resp.data {
-            if let Some(total) = data.get("total_messages") {
-                assert_eq!(total.as_u64().unwrap(), 3);
+        match &events[2] {
+            GrpcStreamEvent::End(end) => {
+                assert!(end.success);
+                assert_eq!(end.message_count, 2);
             }
+            other => panic!("Expected end event, got {:?}", other),
         }
+
+        tokio::task::yield_now().await;
+        assert!(!sessions.is_active("s1"));
     }
 
     #[tokio::test]
-    async fn test_bidirectional_streaming_echoes_messages() {
-        let (msg_tx, msg_rx) = mpsc::channel(10);
-        let (resp_tx, mut resp_rx) = mpsc::channel(10);
-
-        // Start bidirectional handler
-        tokio::spawn(async move {
-            let _ = GrpcStreaming::handle_bidirectional_streaming(msg_rx, resp_tx).await;
-        });
-
-        // Send messages and receive echoes
-        msg_tx.send(serde_json::json!({"text": "hello"})).await.unwrap();
-
-        // Wait a bit for response
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-
-        msg_tx.send(serde_json::json!({"text": "world"})).await.unwrap();
-
-        // Close to signal end
-        drop(msg_tx);
-
-        // Collect all responses
-        let mut responses = Vec::new();
-        while let Some(response) = resp_rx.recv().await {
-            responses.push(response);
-        }
+    async fn test_session_rejects_messages_after_close() {
+        let sessions = GrpcStreamSessions::default();
+        let _rx = start_echo_session(&sessions, "s2");
 
-        // Should have at least 2 echoes + completion
-        assert!(responses.len() >= 2, "Should have at least 2 responses");
+        sessions.close_send("s2").unwrap();
+        let result = sessions
+            .send("s2", &serde_json::json!({"message": "late"}))
+            .await;
 
-        // Verify all are successful
-        for resp in &responses {
-            assert!(resp.success);
-        }
+        assert!(matches!(result, Err(AppError::ValidationError { .. })));
     }
 
     #[tokio::test]
-    async fn test_create_streaming_channels() {
-        let (tx, mut rx) = create_streaming_channels();
+    async fn test_session_rejects_invalid_message() {
+        let sessions = GrpcStreamSessions::default();
+        let _rx = start_echo_session(&sessions, "s3");
 
-        // Test sending and receiving
-        let test_value = serde_json::json!({"test": "value"});
-        tx.send(test_value.clone()).await.unwrap();
+        let result = sessions
+            .send("s3", &serde_json::json!({"unknown_field": 1}))
+            .await;
 
-        let received = rx.recv().await.unwrap();
-        assert_eq!(received, test_value);
+        assert!(matches!(result, Err(AppError::ParseError { .. })));
     }
 
     #[tokio::test]
-    async fn test_streaming_channel_capacity() {
-        let (tx, _rx) = create_streaming_channels();
-
-        // Should be able to send multiple messages without blocking
-        for i in 0..10 {
-            let result = tx.send(serde_json::json!({"id": i})).await;
-            assert!(result.is_ok(), "Should send message {}", i);
+    async fn test_session_cancel_ends_with_cancelled_status() {
+        let sessions = GrpcStreamSessions::default();
+        let rx = start_echo_session(&sessions, "s4");
+
+        sessions.cancel("s4").unwrap();
+
+        let events = collect(rx).await;
+        match events.last() {
+            Some(GrpcStreamEvent::End(end)) => {
+                assert!(!end.success);
+                assert_eq!(end.status_code, Some(1));
+                assert_eq!(end.status_message.as_deref(), Some("CANCELLED"));
+            }
+            other => panic!("Expected end event, got {:?}", other),
         }
     }
 
     #[tokio::test]
-    async fn test_server_streaming_with_empty_request() {
-        let (tx, mut rx) = mpsc::channel(10);
-
-        let request = GrpcRequest {
-            url: "http://localhost:50051".to_string(),
-            service: "TestService".to_string(),
-            method: "StreamData".to_string(),
-            message: serde_json::json!({}),  // Empty message
-            metadata: None,
-            call_type: crate::grpc::GrpcCallType::ServerStreaming,
-        };
-
-        tokio::spawn(async move {
-            let _ = GrpcStreaming::handle_server_streaming(request, tx).await;
-        });
-
-        let mut count = 0;
-        while let Some(response) = rx.recv().await {
-            assert!(response.success);
-            count += 1;
-        }
+    async fn test_session_rejects_running_stream_id() {
+        let sessions = GrpcStreamSessions::default();
+        let rx = start_echo_session(&sessions, "s5");
+
+        let result = sessions.start("s5", None, |_, _| async { Ok(()) });
+        assert!(matches!(result, Err(AppError::ValidationError { .. })));
+
+        // The running session is left untouched
+        sessions
+            .send("s5", &serde_json::json!({"message": "still here"}))
+            .await
+            .unwrap();
+        sessions.close_send("s5").unwrap();
+        assert_eq!(collect(rx).await.len(), 2);
+
+        // and its id can be used again once it finished
+        tokio::task::yield_now().await;
+        let rx = start_echo_session(&sessions, "s5");
+        sessions.close_send("s5").unwrap();
+        assert_eq!(collect(rx).await.len(), 1);
+    }
 
-        assert!(count > 0, "Should still receive messages with empty request");
+    #[tokio::test]
+    async fn test_unknown_stream() {
+        let sessions = GrpcStreamSessions::default();
+
+        assert!(sessions.close_send("missing").is_err());
+        assert!(sessions.cancel("missing").is_err());
+        assert!(sessions
+            .send("missing", &serde_json::json!({}))
+            .await
+            .is_err());
     }
 }
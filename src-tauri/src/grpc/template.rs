@@ -0,0 +1,179 @@
+use prost_reflect::{FieldDescriptor, Kind, MessageDescriptor};
+use serde_json::{json, Map, Value};
+
+/// Levels of nested messages expanded when no depth is given
+pub const DEFAULT_TEMPLATE_DEPTH: usize = 5;
+
+/// Build an example JSON body for `message` in the proto3 JSON mapping.
+///
+/// Every field is set to its default value: repeated fields and maps hold one
+/// example entry, oneofs are filled with their first field and well-known
+/// types use their JSON form. Nested messages are expanded `max_depth` levels
+/// deep, deeper ones (such as recursive types) are left as `{}`.
+pub fn message_template(message: &MessageDescriptor, max_depth: usize) -> Value {
+    if let Some(value) = well_known_template(message) {
+        return value;
+    }
+    if max_depth == 0 {
+        return Value::Object(Map::new());
+    }
+
+    let mut object = Map::new();
+    for field in message.fields() {
+        if !is_first_of_oneof(&field) {
+            continue;
+        }
+        object.insert(
+            field.json_name().to_string(),
+            field_template(&field, max_depth - 1),
+        );
+    }
+    Value::Object(object)
+}
+
+fn field_template(field: &FieldDescriptor, depth: usize) -> Value {
+    match field.kind() {
+        Kind::Message(entry) if field.is_map() => {
+            let key = map_key_template(&entry.map_entry_key_field().kind());
+            let value = kind_template(&entry.map_entry_value_field().kind(), depth);
+            let mut object = Map::new();
+            object.insert(key, value);
+            Value::Object(object)
+        }
+        kind if field.is_list() => json!([kind_template(&kind, depth)]),
+        kind => kind_template(&kind, depth),
+    }
+}
+
+fn kind_template(kind: &Kind, depth: usize) -> Value {
+    match kind {
+        Kind::Double | Kind::Float => json!(0.0),
+        Kind::Int32 | Kind::Uint32 | Kind::Sint32 | Kind::Fixed32 | Kind::Sfixed32 => json!(0),
+        // 64-bit integers are strings in JSON so they keep their precision
+        Kind::Int64 | Kind::Uint64 | Kind::Sint64 | Kind::Fixed64 | Kind::Sfixed64 => json!("0"),
+        Kind::Bool => json!(false),
+        Kind::String | Kind::Bytes => json!(""),
+        Kind::Enum(enum_type) if enum_type.full_name() == "google.protobuf.NullValue" => {
+            Value::Null
+        }
+        Kind::Enum(enum_type) => json!(enum_type.default_value().name()),
+        Kind::Message(message) => message_template(message, depth),
+    }
+}
+
+/// Map keys are always strings in JSON
+fn map_key_template(kind: &Kind) -> String {
+    match kind {
+        Kind::String => "key".to_string(),
+        Kind::Bool => "false".to_string(),
+        _ => "0".to_string(),
+    }
+}
+
+/// Only one field of a oneof may be set. Fields of the synthetic oneofs
+/// behind proto3 `optional` are regular fields.
+fn is_first_of_oneof(field: &FieldDescriptor) -> bool {
+    if field.field_descriptor_proto().proto3_optional() {
+        return true;
+    }
+    match field.containing_oneof() {
+        Some(oneof) => oneof
+            .fields()
+            .next()
+            .is_none_or(|first| first.number() == field.number()),
+        None => true,
+    }
+}
+
+/// Well-known types have a JSON form of their own rather than an object of
+/// their fields
+fn well_known_template(message: &MessageDescriptor) -> Option<Value> {
+    let value = match message.full_name() {
+        "google.protobuf.Timestamp" => json!("1970-01-01T00:00:00Z"),
+        "google.protobuf.Duration" => json!("0s"),
+        "google.protobuf.FieldMask" => json!(""),
+        "google.protobuf.Struct" | "google.protobuf.Empty" => json!({}),
+        "google.protobuf.ListValue" => json!([]),
+        "google.protobuf.Value" => Value::Null,
+        "google.protobuf.Any" => json!({"@type": ""}),
+        "google.protobuf.DoubleValue"
+        | "google.protobuf.FloatValue"
+        | "google.protobuf.Int64Value"
+        | "google.protobuf.UInt64Value"
+        | "google.protobuf.Int32Value"
+        | "google.protobuf.UInt32Value"
+        | "google.protobuf.BoolValue"
+        | "google.protobuf.StringValue"
+        | "google.protobuf.BytesValue" => {
+            let value = message.get_field_by_name("value")?;
+            kind_template(&value.kind(), 0)
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::client::resolve_method;
+    use crate::grpc::proto_parser::ProtoParser;
+    use crate::grpc::test_utils::fixtures;
+    use prost_reflect::DynamicMessage;
+
+    fn create_request() -> MessageDescriptor {
+        let pool = ProtoParser::compile(fixtures::all_field_kinds_proto()).unwrap();
+        resolve_method(&pool, "Kinds", "Create").unwrap().input()
+    }
+
+    #[test]
+    fn test_template_covers_every_field_kind() {
+        let template = message_template(&create_request(), DEFAULT_TEMPLATE_DEPTH);
+
+        assert_eq!(template["name"], "");
+        assert_eq!(template["count"], 0);
+        assert_eq!(template["total"], "0");
+        assert_eq!(template["limit"], "0");
+        assert_eq!(template["ratio"], 0.0);
+        assert_eq!(template["enabled"], false);
+        assert_eq!(template["payload"], "");
+        assert_eq!(template["color"], "COLOR_UNSPECIFIED");
+        assert_eq!(template["tags"], json!([""]));
+        assert_eq!(template["items"]["key"]["id"], "");
+        assert_eq!(template["items"]["key"]["children"][0]["id"], "");
+        assert_eq!(template["colors"], json!({"0": "COLOR_UNSPECIFIED"}));
+        assert_eq!(template["note"], "");
+        assert_eq!(template["createdAt"], "1970-01-01T00:00:00Z");
+        assert_eq!(template["ttl"], "0s");
+        assert_eq!(template["updateMask"], "");
+        assert_eq!(template["attributes"], json!({}));
+        assert_eq!(template["extra"], Value::Null);
+        assert_eq!(template["version"], "0");
+        assert_eq!(template["comment"], "");
+    }
+
+    #[test]
+    fn test_template_sets_one_field_per_oneof() {
+        let template = message_template(&create_request(), DEFAULT_TEMPLATE_DEPTH);
+
+        assert_eq!(template["email"], "");
+        assert!(template.get("userId").is_none());
+    }
+
+    #[test]
+    fn test_template_stops_at_max_depth() {
+        let template = message_template(&create_request(), 2);
+
+        assert_eq!(template["item"], json!({"id": "", "children": [{}]}));
+        assert_eq!(template["tree"], json!({"next": {}}));
+        assert_eq!(message_template(&create_request(), 0), json!({}));
+    }
+
+    #[test]
+    fn test_template_is_a_valid_request() {
+        let message = create_request();
+        let template = message_template(&message, DEFAULT_TEMPLATE_DEPTH);
+
+        assert!(DynamicMessage::deserialize(message, template).is_ok());
+    }
+}
@@ -157,6 +157,140 @@ message StatusResponse {
 "#
     }
 
+    /// A message using every kind of field, for request templates
+    pub fn all_field_kinds_proto() -> &'static str {
+        r#"
+syntax = "proto3";
+
+package kinds.v1;
+
+import "google/protobuf/duration.proto";
+import "google/protobuf/empty.proto";
+import "google/protobuf/field_mask.proto";
+import "google/protobuf/struct.proto";
+import "google/protobuf/timestamp.proto";
+import "google/protobuf/wrappers.proto";
+
+service Kinds {
+  rpc Create(CreateRequest) returns (google.protobuf.Empty);
+}
+
+enum Color {
+  COLOR_UNSPECIFIED = 0;
+  RED = 1;
+}
+
+message CreateRequest {
+  string name = 1;
+  int32 count = 2;
+  int64 total = 3;
+  uint64 limit = 4;
+  double ratio = 5;
+  bool enabled = 6;
+  bytes payload = 7;
+  Color color = 8;
+  repeated string tags = 9;
+  map<string, Item> items = 10;
+  map<int32, Color> colors = 11;
+  oneof target {
+    string email = 12;
+    int64 user_id = 13;
+  }
+  optional string note = 14;
+  Item item = 15;
+  Node tree = 16;
+  google.protobuf.Timestamp created_at = 17;
+  google.protobuf.Duration ttl = 18;
+  google.protobuf.FieldMask update_mask = 19;
+  google.protobuf.Struct attributes = 20;
+  google.protobuf.Value extra = 21;
+  google.protobuf.Int64Value version = 22;
+  google.protobuf.StringValue comment = 23;
+}
+
+message Item {
+  string id = 1;
+  repeated Item children = 2;
+}
+
+message Node {
+  Node next = 1;
+}
+"#
+    }
+
+    /// Messages holding `google.protobuf.Any` at several depths
+    pub fn any_fields_proto() -> &'static str {
+        r#"
+syntax = "proto3";
+
+package wrap.v1;
+
+import "google/protobuf/any.proto";
+import "google/protobuf/timestamp.proto";
+
+enum Kind {
+  KIND_UNSPECIFIED = 0;
+  KIND_NOTE = 1;
+}
+
+message Envelope {
+  string id = 1;
+  google.protobuf.Any payload = 2;
+  repeated google.protobuf.Any items = 3;
+  map<string, Holder> holders = 4;
+  int64 size = 5;
+  Kind kind = 6;
+  string display_name = 7;
+  google.protobuf.Timestamp created_at = 8;
+}
+
+message Holder {
+  google.protobuf.Any value = 1;
+}
+
+message Note {
+  string text = 1;
+  int64 stars = 2;
+}
+"#
+    }
+
+    /// Echo service exercised by the in-process test server
+    pub fn echo_service_proto() -> &'static str {
+        r#"
+syntax = "proto3";
+
+package test.v1;
+
+import "google/protobuf/timestamp.proto";
+
+// Echoes messages back in every call style
+service Echo {
+  // Answer with the request message
+  rpc Echo(EchoRequest) returns (EchoResponse);
+  rpc Expand(ExpandRequest) returns (stream EchoResponse);
+  rpc Collect(stream EchoRequest) returns (EchoResponse);
+  rpc Chat(stream EchoRequest) returns (stream EchoResponse);
+}
+
+message EchoRequest {
+  string message = 1; // Text to echo
+}
+
+message ExpandRequest {
+  string message = 1;
+  int32 count = 2;
+}
+
+message EchoResponse {
+  string message = 1;
+  int32 index = 2;
+  google.protobuf.Timestamp sent_at = 3;
+}
+"#
+    }
+
     pub fn expected_echo_schema() -> ProtoSchema {
         ProtoSchema {
             services: vec![ProtoService {
@@ -167,7 +301,9 @@ message StatusResponse {
                     output_type: "EchoResponse".to_string(),
                     is_client_streaming: false,
                     is_server_streaming: false,
+                    ..Default::default()
                 }],
+                ..Default::default()
             }],
             messages: vec![
                 ProtoMessage {
@@ -177,7 +313,9 @@ message StatusResponse {
                         field_type: "string".to_string(),
                         number: 1,
                         repeated: false,
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
                 ProtoMessage {
                     name: "EchoResponse".to_string(),
@@ -186,9 +324,12 @@ message StatusResponse {
                         field_type: "string".to_string(),
                         number: 1,
                         repeated: false,
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
             ],
+            enums: Vec::new(),
         }
     }
 
@@ -199,6 +340,9 @@ message StatusResponse {
             error: None,
             status_code: Some(0),
             status_message: Some("OK".to_string()),
+            headers: Default::default(),
+            trailers: Default::default(),
+            details: Vec::new(),
         }
     }
 
@@ -209,7 +353,661 @@ message StatusResponse {
             error: Some(error_msg.to_string()),
             status_code: Some(5),
             status_message: Some("NOT_FOUND".to_string()),
+            headers: Default::default(),
+            trailers: Default::default(),
+            details: Vec::new(),
         }
     }
 }
 
+/// Certificates for TLS tests: a CA, plus a server certificate for
+/// `localhost` and a client certificate, both signed by the CA
+#[cfg(test)]
+pub mod pki {
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+    use rustls::server::AllowAnyAuthenticatedClient;
+    use rustls::{PrivateKey, RootCertStore, ServerConfig};
+    use std::path::Path;
+
+    pub struct TestPki {
+        pub ca_pem: String,
+        pub ca_der: Vec<u8>,
+        pub server_cert_der: Vec<u8>,
+        pub server_key_der: Vec<u8>,
+        pub client_cert_pem: String,
+        pub client_cert_der: Vec<u8>,
+        pub client_key_pem: String,
+        pub client_key_der: Vec<u8>,
+    }
+
+    impl TestPki {
+        pub fn generate() -> Self {
+            let mut ca_params = CertificateParams::new(Vec::<String>::new());
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            ca_params
+                .distinguished_name
+                .push(DnType::CommonName, "Solo Test CA");
+            let ca = Certificate::from_params(ca_params).unwrap();
+            let server =
+                Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()]))
+                    .unwrap();
+            let client =
+                Certificate::from_params(CertificateParams::new(vec!["client.test".to_string()]))
+                    .unwrap();
+
+            Self {
+                ca_pem: ca.serialize_pem().unwrap(),
+                ca_der: ca.serialize_der().unwrap(),
+                server_cert_der: server.serialize_der_with_signer(&ca).unwrap(),
+                server_key_der: server.serialize_private_key_der(),
+                client_cert_pem: client.serialize_pem_with_signer(&ca).unwrap(),
+                client_cert_der: client.serialize_der_with_signer(&ca).unwrap(),
+                client_key_pem: client.serialize_private_key_pem(),
+                client_key_der: client.serialize_private_key_der(),
+            }
+        }
+
+        /// TLS configuration of a server presenting the `localhost` certificate,
+        /// optionally requiring a client certificate signed by the CA
+        pub fn server_config(&self, require_client_cert: bool) -> ServerConfig {
+            let builder = ServerConfig::builder().with_safe_defaults();
+            let builder = if require_client_cert {
+                let mut roots = RootCertStore::empty();
+                roots
+                    .add(&rustls::Certificate(self.ca_der.clone()))
+                    .unwrap();
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            } else {
+                builder.with_no_client_auth()
+            };
+
+            let mut config = builder
+                .with_single_cert(
+                    vec![rustls::Certificate(self.server_cert_der.clone())],
+                    PrivateKey(self.server_key_der.clone()),
+                )
+                .unwrap();
+            config.alpn_protocols = vec![b"h2".to_vec()];
+            config
+        }
+    }
+
+    /// Write `contents` to `name` in `dir` and return the path
+    pub fn write_file(dir: &Path, name: &str, contents: impl AsRef<[u8]>) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+}
+
+/// In-process gRPC server implementing the `echo_service_proto` fixture with
+/// dynamic messages, plus server reflection and health checking. The fixture imports a
+/// well-known type, so reflection clients must follow imports by filename.
+#[cfg(test)]
+pub mod server {
+    use super::super::client::resolve_method;
+    use super::super::codec::DynamicCodec;
+    use super::super::error_details::{error_details_pool, RpcStatus};
+    use super::super::health::{self, HealthCheckRequest, HealthCheckResponse, ServingStatus};
+    use super::super::proto_parser::ProtoParser;
+    use super::super::reflection::ReflectionVersion;
+    use super::fixtures;
+    use super::pki::TestPki;
+    use prost::Message;
+    use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, Value};
+    use std::convert::Infallible;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+    use tokio::sync::oneshot;
+    use tokio_rustls::TlsAcceptor;
+    use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+    use tokio_stream::{Stream, StreamExt};
+    use tonic::body::BoxBody;
+    use tonic::codec::{CompressionEncoding, ProstCodec, Streaming};
+    use tonic::codegen::{BoxFuture, BoxStream};
+    use tonic::metadata::{KeyAndValueRef, MetadataMap};
+    use tonic::server::{
+        ClientStreamingService, NamedService, ServerStreamingService, StreamingService,
+        UnaryService,
+    };
+    use tonic::transport::server::Connected;
+    use tonic::transport::Body;
+    use tonic::{Request, Response, Status};
+
+    const SERVICE_NAME: &str = "test.v1.Echo";
+
+    /// Compile the echo fixture into a descriptor pool
+    pub fn echo_descriptor_pool() -> DescriptorPool {
+        ProtoParser::compile(fixtures::echo_service_proto()).expect("echo fixture should compile")
+    }
+
+    pub struct TestServer {
+        pub url: String,
+        pub pool: DescriptorPool,
+        shutdown: Option<oneshot::Sender<()>>,
+    }
+
+    impl TestServer {
+        /// Serve reflection as `grpc.reflection.v1alpha`, the only version
+        /// tonic-reflection provides
+        pub async fn start() -> Self {
+            Self::serve_tcp(Some(ReflectionVersion::V1Alpha)).await
+        }
+
+        /// Serve reflection only as `grpc.reflection.v1`, like recent servers
+        pub async fn start_with_reflection_v1() -> Self {
+            Self::serve_tcp(Some(ReflectionVersion::V1)).await
+        }
+
+        /// Serve the echo service only, like servers with reflection turned off
+        pub async fn start_without_reflection() -> Self {
+            Self::serve_tcp(None).await
+        }
+
+        /// Serve over TLS as `https://localhost:<port>`, reflection included
+        pub async fn start_tls(pki: &TestPki, require_client_cert: bool) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!(
+                "https://localhost:{}",
+                listener.local_addr().unwrap().port()
+            );
+            let acceptor = TlsAcceptor::from(Arc::new(pki.server_config(require_client_cert)));
+
+            let incoming = TcpListenerStream::new(listener)
+                .then(move |tcp| {
+                    let acceptor = acceptor.clone();
+                    async move { acceptor.accept(tcp?).await }
+                })
+                // A failed handshake would stop the server; only drop that connection
+                .filter_map(|tls| tls.ok().map(|tls| Ok::<_, std::io::Error>(TlsIo(tls))));
+
+            Self::serve(incoming, url, Some(ReflectionVersion::V1Alpha)).await
+        }
+
+        /// Serve the echo service as gRPC-Web over HTTP/1.1, like a proxy in
+        /// front of a gRPC server
+        pub async fn start_grpc_web() -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let pool = echo_descriptor_pool();
+            let (shutdown, rx) = oneshot::channel::<()>();
+            let echo = tonic_web::enable(EchoService { pool: pool.clone() });
+
+            tokio::spawn(async move {
+                let _ = tonic::transport::Server::builder()
+                    .accept_http1(true)
+                    .add_service(echo)
+                    .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
+                        let _ = rx.await;
+                    })
+                    .await;
+            });
+
+            Self {
+                url,
+                pool,
+                shutdown: Some(shutdown),
+            }
+        }
+
+        async fn serve_tcp(reflection: Option<ReflectionVersion>) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            Self::serve(TcpListenerStream::new(listener), url, reflection).await
+        }
+
+        async fn serve<I, IO>(
+            incoming: I,
+            url: String,
+            reflection: Option<ReflectionVersion>,
+        ) -> Self
+        where
+            I: Stream<Item = std::io::Result<IO>> + Send + 'static,
+            IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+        {
+            let pool = echo_descriptor_pool();
+            let (shutdown, rx) = oneshot::channel::<()>();
+
+            let reflection_service = reflection.map(|_| {
+                tonic_reflection::server::Builder::configure()
+                    .register_encoded_file_descriptor_set(&pool.encode_to_vec())
+                    .build()
+                    .unwrap()
+            });
+            let (v1alpha, v1) = match reflection {
+                Some(ReflectionVersion::V1) => (None, reflection_service.map(ReflectionV1)),
+                _ => (reflection_service, None),
+            };
+            let echo = EchoService { pool: pool.clone() };
+            // Servers without reflection serve the echo service alone
+            let health = reflection.map(|_| HealthService);
+
+            tokio::spawn(async move {
+                let _ = tonic::transport::Server::builder()
+                    .add_service(echo)
+                    .add_optional_service(health)
+                    .add_optional_service(v1alpha)
+                    .add_optional_service(v1)
+                    .serve_with_incoming_shutdown(incoming, async {
+                        let _ = rx.await;
+                    })
+                    .await;
+            });
+
+            Self {
+                url,
+                pool,
+                shutdown: Some(shutdown),
+            }
+        }
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            if let Some(shutdown) = self.shutdown.take() {
+                let _ = shutdown.send(());
+            }
+        }
+    }
+
+    /// Accepted TLS connection. tonic only knows the connection info of its
+    /// own stream types.
+    struct TlsIo(tokio_rustls::server::TlsStream<tokio::net::TcpStream>);
+
+    impl Connected for TlsIo {
+        type ConnectInfo = ();
+
+        fn connect_info(&self) -> Self::ConnectInfo {}
+    }
+
+    impl AsyncRead for TlsIo {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for TlsIo {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_shutdown(cx)
+        }
+    }
+
+    /// Exposes a v1alpha reflection service under the `grpc.reflection.v1`
+    /// name; both versions use the same messages
+    #[derive(Clone)]
+    struct ReflectionV1<S>(S);
+
+    impl<S> NamedService for ReflectionV1<S> {
+        const NAME: &'static str = "grpc.reflection.v1.ServerReflection";
+    }
+
+    impl<S> tower::Service<http::Request<Body>> for ReflectionV1<S>
+    where
+        S: tower::Service<http::Request<Body>>,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, mut req: http::Request<Body>) -> Self::Future {
+            let path =
+                req.uri()
+                    .path()
+                    .replacen("grpc.reflection.v1.", "grpc.reflection.v1alpha.", 1);
+            let mut parts = req.uri().clone().into_parts();
+            parts.path_and_query = Some(path.parse().unwrap());
+            *req.uri_mut() = http::Uri::from_parts(parts).unwrap();
+            self.0.call(req)
+        }
+    }
+
+    /// `grpc.health.v1.Health` reporting the echo service and the server as
+    /// SERVING; other services are unknown
+    #[derive(Clone)]
+    struct HealthService;
+
+    impl NamedService for HealthService {
+        const NAME: &'static str = "grpc.health.v1.Health";
+    }
+
+    impl tower::Service<http::Request<Body>> for HealthService {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+            Box::pin(async move {
+                let mut grpc = tonic::server::Grpc::new(ProstCodec::<
+                    HealthCheckResponse,
+                    HealthCheckRequest,
+                >::default());
+                let response = match req.uri().path() {
+                    health::CHECK_PATH => grpc.unary(HealthCheck, req).await,
+                    health::WATCH_PATH => grpc.server_streaming(HealthWatch, req).await,
+                    path => Status::unimplemented(path).to_http(),
+                };
+                Ok(response)
+            })
+        }
+    }
+
+    fn serving_status(service: &str) -> Option<ServingStatus> {
+        matches!(service, "" | SERVICE_NAME).then_some(ServingStatus::Serving)
+    }
+
+    struct HealthCheck;
+
+    impl UnaryService<HealthCheckRequest> for HealthCheck {
+        type Response = HealthCheckResponse;
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+
+        fn call(&mut self, request: Request<HealthCheckRequest>) -> Self::Future {
+            let service = request.into_inner().service;
+            Box::pin(async move {
+                let status = serving_status(&service)
+                    .ok_or_else(|| Status::not_found(format!("unknown service {}", service)))?;
+                Ok(Response::new(HealthCheckResponse {
+                    status: status as i32,
+                }))
+            })
+        }
+    }
+
+    /// Sends the current status, then keeps the stream open like a server
+    /// whose status never changes
+    struct HealthWatch;
+
+    impl ServerStreamingService<HealthCheckRequest> for HealthWatch {
+        type Response = HealthCheckResponse;
+        type ResponseStream = BoxStream<HealthCheckResponse>;
+        type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+        fn call(&mut self, request: Request<HealthCheckRequest>) -> Self::Future {
+            let service = request.into_inner().service;
+            Box::pin(async move {
+                let status = serving_status(&service).unwrap_or(ServingStatus::ServiceUnknown);
+                let first = tokio_stream::once(Ok(HealthCheckResponse {
+                    status: status as i32,
+                }));
+                let stream: Self::ResponseStream = Box::pin(first.chain(tokio_stream::pending()));
+                Ok(Response::new(stream))
+            })
+        }
+    }
+
+    /// Largest request message the echo service accepts
+    const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+    /// How long "slow" messages are held back
+    pub const SLOW_DELAY: Duration = Duration::from_millis(200);
+
+    #[derive(Clone)]
+    struct EchoService {
+        pool: DescriptorPool,
+    }
+
+    impl NamedService for EchoService {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+
+    impl tower::Service<http::Request<Body>> for EchoService {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+            let method_name = req
+                .uri()
+                .path()
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            let method = resolve_method(&self.pool, SERVICE_NAME, &method_name).ok();
+
+            Box::pin(async move {
+                let Some(method) = method else {
+                    return Ok(Status::unimplemented(method_name).to_http());
+                };
+                let mut grpc = tonic::server::Grpc::new(DynamicCodec::new(method.input()))
+                    .accept_compressed(CompressionEncoding::Gzip)
+                    .send_compressed(CompressionEncoding::Gzip)
+                    .max_decoding_message_size(MAX_MESSAGE_SIZE);
+                let response = match method.name() {
+                    "Echo" => grpc.unary(Echo(method.output()), req).await,
+                    "Expand" => grpc.server_streaming(Expand(method.output()), req).await,
+                    "Collect" => grpc.client_streaming(Collect(method.output()), req).await,
+                    "Chat" => grpc.streaming(Chat(method.output()), req).await,
+                    _ => Status::unimplemented(method_name).to_http(),
+                };
+                Ok(response)
+            })
+        }
+    }
+
+    /// Echoes the request `message` back, and the `x-echo-*` request metadata
+    /// as response headers. The message "error" fails with INVALID_ARGUMENT
+    /// and an `x-error-reason` trailer, "invalid" with a `google.rpc.BadRequest`,
+    /// and "slow" is answered after `SLOW_DELAY`.
+    struct Echo(MessageDescriptor);
+
+    impl UnaryService<DynamicMessage> for Echo {
+        type Response = DynamicMessage;
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+
+        fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+            let output = self.0.clone();
+            Box::pin(async move {
+                let text = message_text(request.get_ref());
+                if text == "invalid" {
+                    return Err(bad_request("message", "must not be \"invalid\""));
+                }
+                if text == "slow" {
+                    tokio::time::sleep(SLOW_DELAY).await;
+                }
+                if text == "error" {
+                    let mut trailers = MetadataMap::new();
+                    trailers.insert("x-error-reason", "refused".parse().unwrap());
+                    return Err(Status::with_metadata(
+                        tonic::Code::InvalidArgument,
+                        "echo refused",
+                        trailers,
+                    ));
+                }
+
+                let mut response = Response::new(reply(&output, &text));
+                *response.metadata_mut() = echoed_metadata(request.metadata());
+                Ok(response)
+            })
+        }
+    }
+
+    /// Streams `count` numbered copies of `message`, echoing metadata like
+    /// `Echo`; the message "error" aborts the stream after the copies were sent
+    /// and "slow" waits `SLOW_DELAY` before each copy
+    struct Expand(MessageDescriptor);
+
+    impl ServerStreamingService<DynamicMessage> for Expand {
+        type Response = DynamicMessage;
+        type ResponseStream = BoxStream<DynamicMessage>;
+        type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+        fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+            let output = self.0.clone();
+            Box::pin(async move {
+                let headers = echoed_metadata(request.metadata());
+                let text = message_text(request.get_ref());
+                let count = request
+                    .get_ref()
+                    .get_field_by_name("count")
+                    .and_then(|value| value.as_i32())
+                    .unwrap_or_default();
+
+                let (tx, rx) = tokio::sync::mpsc::channel(4);
+                tokio::spawn(async move {
+                    for index in 0..count {
+                        if text == "slow" {
+                            tokio::time::sleep(SLOW_DELAY).await;
+                        }
+                        let mut message = reply(&output, &text);
+                        message.set_field_by_name("index", Value::I32(index));
+                        let _ = tx.send(Ok(message)).await;
+                    }
+                    if text == "error" {
+                        // Let the sent messages flush before failing the stream
+                        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                        let _ = tx.send(Err(Status::aborted("stream aborted"))).await;
+                    }
+                });
+
+                let stream: Self::ResponseStream = Box::pin(ReceiverStream::new(rx));
+                let mut response = Response::new(stream);
+                *response.metadata_mut() = headers;
+                Ok(response)
+            })
+        }
+    }
+
+    /// Joins all received messages with "," once the client half-closes
+    struct Collect(MessageDescriptor);
+
+    impl ClientStreamingService<DynamicMessage> for Collect {
+        type Response = DynamicMessage;
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+
+        fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+            let output = self.0.clone();
+            Box::pin(async move {
+                let mut stream = request.into_inner();
+                let mut texts = Vec::new();
+                while let Some(message) = stream.message().await? {
+                    texts.push(message_text(&message));
+                }
+                let mut response = reply(&output, &texts.join(","));
+                response.set_field_by_name("index", Value::I32(texts.len() as i32));
+                Ok(Response::new(response))
+            })
+        }
+    }
+
+    /// Answers every received message right away; "error" fails the stream
+    struct Chat(MessageDescriptor);
+
+    impl StreamingService<DynamicMessage> for Chat {
+        type Response = DynamicMessage;
+        type ResponseStream = BoxStream<DynamicMessage>;
+        type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+        fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+            let output = self.0.clone();
+            Box::pin(async move {
+                let mut stream = request.into_inner();
+                let (tx, rx) = tokio::sync::mpsc::channel(4);
+                tokio::spawn(async move {
+                    let mut index = 0;
+                    while let Ok(Some(message)) = stream.message().await {
+                        let text = message_text(&message);
+                        if text == "error" {
+                            let _ = tx.send(Err(Status::aborted("chat aborted"))).await;
+                            return;
+                        }
+                        let mut response = reply(&output, &format!("re: {}", text));
+                        response.set_field_by_name("index", Value::I32(index));
+                        index += 1;
+                        if tx.send(Ok(response)).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+
+                let stream: Self::ResponseStream = Box::pin(ReceiverStream::new(rx));
+                Ok(Response::new(stream))
+            })
+        }
+    }
+
+    /// INVALID_ARGUMENT status carrying a `google.rpc.BadRequest` detail
+    fn bad_request(field: &str, description: &str) -> Status {
+        let desc = error_details_pool()
+            .get_message_by_name("google.rpc.BadRequest")
+            .unwrap();
+        let detail = DynamicMessage::deserialize(
+            desc,
+            serde_json::json!({"fieldViolations": [{"field": field, "description": description}]}),
+        )
+        .unwrap();
+        let rpc_status = RpcStatus {
+            code: tonic::Code::InvalidArgument as i32,
+            message: "request is invalid".to_string(),
+            details: vec![prost_types::Any {
+                type_url: "type.googleapis.com/google.rpc.BadRequest".to_string(),
+                value: detail.encode_to_vec(),
+            }],
+        };
+
+        Status::with_details(
+            tonic::Code::InvalidArgument,
+            "request is invalid",
+            rpc_status.encode_to_vec().into(),
+        )
+    }
+
+    fn echoed_metadata(metadata: &MetadataMap) -> MetadataMap {
+        let mut echoed = MetadataMap::new();
+        for entry in metadata.iter() {
+            match entry {
+                KeyAndValueRef::Ascii(key, value) if key.as_str().starts_with("x-echo-") => {
+                    echoed.append(key.clone(), value.clone());
+                }
+                KeyAndValueRef::Binary(key, value) if key.as_str().starts_with("x-echo-") => {
+                    echoed.append_bin(key.clone(), value.clone());
+                }
+                _ => {}
+            }
+        }
+        echoed
+    }
+
+    fn message_text(message: &DynamicMessage) -> String {
+        message
+            .get_field_by_name("message")
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    fn reply(output: &MessageDescriptor, text: &str) -> DynamicMessage {
+        let mut message = DynamicMessage::new(output.clone());
+        message.set_field_by_name("message", Value::String(text.to_string()));
+        message
+    }
+}
@@ -0,0 +1,538 @@
+use crate::client::proxy::ProxyConnector;
+use crate::error::{AppError, AppResult};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, ClientConnection, PrivateKey, RootCertStore, ServerName};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tonic::transport::Uri;
+use tower::ServiceExt;
+
+/// TLS settings of a gRPC channel, used for `https://` URLs.
+/// Without settings the server is verified against the system roots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GrpcTlsConfig {
+    /// PEM file with the CA certificates trusted instead of the system roots
+    pub ca_cert_path: Option<String>,
+    /// Certificate presented to servers that require mutual TLS
+    pub client_identity: Option<ClientIdentity>,
+    /// Name sent as SNI and checked against the server certificate, when it
+    /// differs from the URL host
+    pub server_name: Option<String>,
+    /// `:authority` sent with every call, when it differs from the URL host
+    pub authority: Option<String>,
+    /// Accept any server certificate. Only meant for development servers.
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+/// Client certificate and private key used for mutual TLS
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientIdentity {
+    /// PEM certificate chain and private key files
+    Pem { cert_path: String, key_path: String },
+    /// PKCS#12 archive (`.p12`/`.pfx`) holding the key and its chain
+    Pkcs12 {
+        path: String,
+        #[serde(default)]
+        password: String,
+    },
+}
+
+impl GrpcTlsConfig {
+    /// Whether any setting only makes sense on a TLS connection
+    pub fn requires_tls(&self) -> bool {
+        self.ca_cert_path.is_some()
+            || self.client_identity.is_some()
+            || self.server_name.is_some()
+            || self.insecure_skip_verify
+    }
+
+    /// Build the rustls configuration, reading the referenced files
+    pub fn client_config(&self) -> AppResult<ClientConfig> {
+        let roots = if self.insecure_skip_verify {
+            RootCertStore::empty()
+        } else {
+            self.root_store()?
+        };
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+
+        let mut config = match &self.client_identity {
+            Some(identity) => {
+                let (chain, key) = identity.load()?;
+                builder.with_client_auth_cert(chain, key).map_err(|e| {
+                    AppError::validation(
+                        "client_identity",
+                        format!("Invalid client certificate: {}", e),
+                    )
+                })?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        if self.insecure_skip_verify {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(SkipServerVerification));
+        }
+        // gRPC runs over HTTP/2 only
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(config)
+    }
+
+    fn root_store(&self) -> AppResult<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+
+        match &self.ca_cert_path {
+            Some(path) => {
+                let pem = read_file("ca_cert_path", path)?;
+                let certs = rustls_pemfile::certs(&mut pem.as_slice()).unwrap_or_default();
+                let (added, _) = roots.add_parsable_certificates(&certs);
+                if added == 0 {
+                    return Err(AppError::validation(
+                        "ca_cert_path",
+                        format!("No PEM CA certificate found in '{}'", path),
+                    ));
+                }
+            }
+            None => {
+                let certs = rustls_native_certs::load_native_certs().map_err(|e| {
+                    AppError::internal(format!("Failed to load system root certificates: {}", e))
+                })?;
+                let certs: Vec<Vec<u8>> = certs.into_iter().map(|cert| cert.0).collect();
+                roots.add_parsable_certificates(&certs);
+            }
+        }
+
+        Ok(roots)
+    }
+}
+
+impl ClientIdentity {
+    /// Read the certificate chain and private key
+    fn load(&self) -> AppResult<(Vec<Certificate>, PrivateKey)> {
+        match self {
+            ClientIdentity::Pem {
+                cert_path,
+                key_path,
+            } => {
+                let pem = read_file("client_identity", cert_path)?;
+                let chain: Vec<Certificate> = rustls_pemfile::certs(&mut pem.as_slice())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Certificate)
+                    .collect();
+                if chain.is_empty() {
+                    return Err(AppError::validation(
+                        "client_identity",
+                        format!("No PEM certificate found in '{}'", cert_path),
+                    ));
+                }
+
+                let pem = read_file("client_identity", key_path)?;
+                let key = pem_private_key(&pem).ok_or_else(|| {
+                    AppError::validation(
+                        "client_identity",
+                        format!("No PEM private key found in '{}'", key_path),
+                    )
+                })?;
+
+                Ok((chain, key))
+            }
+            ClientIdentity::Pkcs12 { path, password } => {
+                let archive = read_file("client_identity", path)?;
+                let store =
+                    p12_keystore::KeyStore::from_pkcs12(&archive, password).map_err(|e| {
+                        AppError::validation(
+                            "client_identity",
+                            format!("Failed to open PKCS#12 archive '{}': {}", path, e),
+                        )
+                    })?;
+                let (_, key_chain) = store.private_key_chain().ok_or_else(|| {
+                    AppError::validation(
+                        "client_identity",
+                        format!("No private key found in '{}'", path),
+                    )
+                })?;
+
+                let chain = key_chain
+                    .chain()
+                    .iter()
+                    .map(|cert| Certificate(cert.as_der().to_vec()))
+                    .collect();
+                Ok((chain, PrivateKey(key_chain.key().to_vec())))
+            }
+        }
+    }
+}
+
+fn read_file(field: &str, path: &str) -> AppResult<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| AppError::validation(field, format!("Failed to read '{}': {}", path, e)))
+}
+
+/// First PKCS#8, PKCS#1 (RSA) or SEC1 (EC) private key of a PEM file
+fn pem_private_key(pem: &[u8]) -> Option<PrivateKey> {
+    let mut reader = pem;
+    while let Ok(Some(item)) = rustls_pemfile::read_one(&mut reader) {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Some(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Verifier behind `insecure_skip_verify`: accepts any server certificate
+struct SkipServerVerification;
+
+impl ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Connector opening TLS connections for tonic channels
+#[derive(Clone)]
+pub struct TlsConnector {
+    tcp: ProxyConnector,
+    tls: tokio_rustls::TlsConnector,
+    server_name: Option<ServerName>,
+}
+
+impl TlsConnector {
+    /// Wrap `tcp`, which opens the TCP connections, with the TLS handshake
+    /// described by `config`
+    pub fn new(tcp: ProxyConnector, config: &GrpcTlsConfig) -> AppResult<Self> {
+        let server_name = config
+            .server_name
+            .as_deref()
+            .map(|name| {
+                ServerName::try_from(name).map_err(|_| {
+                    AppError::validation("server_name", format!("Invalid server name '{}'", name))
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            tcp,
+            tls: Arc::new(config.client_config()?).into(),
+            server_name,
+        })
+    }
+
+    /// Connect to `uri` and describe the session the handshake negotiated
+    pub async fn handshake(self, uri: Uri) -> AppResult<TlsSessionInfo> {
+        let stream = self
+            .oneshot(uri)
+            .await
+            .map_err(|e| AppError::network(format!("TLS handshake failed: {}", e)))?;
+        Ok(TlsSessionInfo::of(stream.get_ref().1))
+    }
+}
+
+/// What a TLS handshake with a server negotiated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsSessionInfo {
+    /// e.g. "TLSv1_3"
+    pub protocol_version: Option<String>,
+    /// e.g. "TLS13_AES_256_GCM_SHA384"
+    pub cipher_suite: Option<String>,
+    /// Protocol agreed on over ALPN; gRPC needs "h2"
+    pub alpn_protocol: Option<String>,
+    /// Length of the certificate chain the server presented
+    pub peer_certificates: usize,
+}
+
+impl TlsSessionInfo {
+    fn of(connection: &ClientConnection) -> Self {
+        Self {
+            protocol_version: connection
+                .protocol_version()
+                .map(|version| format!("{:?}", version)),
+            cipher_suite: connection
+                .negotiated_cipher_suite()
+                .map(|suite| format!("{:?}", suite.suite())),
+            alpn_protocol: connection
+                .alpn_protocol()
+                .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+            peer_certificates: connection
+                .peer_certificates()
+                .map_or(0, |chain| chain.len()),
+        }
+    }
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+impl tower::Service<Uri> for TlsConnector {
+    type Response = TlsStream<TcpStream>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.tcp.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        // IPv6 hosts keep their brackets in URIs
+        let host = uri.host().map(|host| {
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string()
+        });
+        let server_name = self.server_name.clone();
+        let tls = self.tls.clone();
+        let connecting = self.tcp.call(uri);
+
+        Box::pin(async move {
+            let server_name = match server_name {
+                Some(name) => name,
+                None => {
+                    let host = host.ok_or("URL has no host to verify the server against")?;
+                    ServerName::try_from(host.as_str())?
+                }
+            };
+
+            let tcp = connecting.await?;
+            Ok(tls.connect(server_name, tcp).await?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::proxy::ProxySettings;
+    use crate::error::AppError;
+    use crate::grpc::channel::connect;
+    use crate::grpc::state::GrpcState;
+    use crate::grpc::test_utils::pki::{write_file, TestPki};
+    use crate::grpc::test_utils::server::TestServer;
+    use crate::grpc::{DescriptorSource, GrpcCallType, GrpcRequest};
+
+    /// Resolve the echo method by reflection and call it, both over `tls`
+    async fn echo(url: &str, tls: &GrpcTlsConfig) -> AppResult<bool> {
+        let mut client = GrpcState::default()
+            .client(
+                url,
+                Some(tls),
+                &ProxySettings::direct(),
+                &DescriptorSource::Reflection,
+            )
+            .await?;
+        let response = client
+            .unary_call(GrpcRequest {
+                url: url.to_string(),
+                service: "test.v1.Echo".to_string(),
+                method: "Echo".to_string(),
+                message: serde_json::json!({"message": "secure"}),
+                metadata: None,
+                call_type: GrpcCallType::Unary,
+                options: Default::default(),
+                transport: Default::default(),
+            })
+            .await?;
+        Ok(response.success)
+    }
+
+    fn trusting(dir: &tempfile::TempDir, pki: &TestPki) -> GrpcTlsConfig {
+        GrpcTlsConfig {
+            ca_cert_path: Some(write_file(dir.path(), "ca.pem", &pki.ca_pem)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_ca_verifies_server() {
+        let pki = TestPki::generate();
+        let server = TestServer::start_tls(&pki, false).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(echo(&server.url, &trusting(&dir, &pki)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_server_name_overrides_url_host() {
+        let pki = TestPki::generate();
+        let server = TestServer::start_tls(&pki, false).await;
+        let dir = tempfile::tempdir().unwrap();
+        // The certificate is only valid for "localhost"
+        let url = server.url.replace("localhost", "127.0.0.1");
+
+        let tls = GrpcTlsConfig {
+            server_name: Some("localhost".to_string()),
+            ..trusting(&dir, &pki)
+        };
+        assert!(echo(&url, &tls).await.unwrap());
+        assert!(echo(&url, &trusting(&dir, &pki)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_server_is_rejected() {
+        let pki = TestPki::generate();
+        let server = TestServer::start_tls(&pki, false).await;
+
+        match echo(&server.url, &GrpcTlsConfig::default()).await {
+            Err(AppError::NetworkError { message }) => {
+                assert!(message.contains("invalid peer certificate"), "{}", message)
+            }
+            other => panic!("Expected a network error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insecure_skip_verify_accepts_any_server() {
+        let pki = TestPki::generate();
+        let server = TestServer::start_tls(&pki, false).await;
+
+        let tls = GrpcTlsConfig {
+            insecure_skip_verify: true,
+            ..Default::default()
+        };
+        assert!(echo(&server.url, &tls).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_with_pem_identity() {
+        let pki = TestPki::generate();
+        let server = TestServer::start_tls(&pki, true).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(echo(&server.url, &trusting(&dir, &pki)).await.is_err());
+
+        let tls = GrpcTlsConfig {
+            client_identity: Some(ClientIdentity::Pem {
+                cert_path: write_file(dir.path(), "client.pem", &pki.client_cert_pem),
+                key_path: write_file(dir.path(), "client.key", &pki.client_key_pem),
+            }),
+            ..trusting(&dir, &pki)
+        };
+        assert!(echo(&server.url, &tls).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_with_pkcs12_identity() {
+        let pki = TestPki::generate();
+        let server = TestServer::start_tls(&pki, true).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = p12_keystore::KeyStore::new();
+        store.add_entry(
+            "client",
+            p12_keystore::KeyStoreEntry::PrivateKeyChain(p12_keystore::PrivateKeyChain::new(
+                &pki.client_key_der,
+                b"client",
+                [p12_keystore::Certificate::from_der(&pki.client_cert_der).unwrap()],
+            )),
+        );
+        let archive = store.writer("secret").write().unwrap();
+        let path = write_file(dir.path(), "client.p12", archive);
+
+        let tls = GrpcTlsConfig {
+            client_identity: Some(ClientIdentity::Pkcs12 {
+                path: path.clone(),
+                password: "secret".to_string(),
+            }),
+            ..trusting(&dir, &pki)
+        };
+        assert!(echo(&server.url, &tls).await.unwrap());
+
+        let wrong_password = GrpcTlsConfig {
+            client_identity: Some(ClientIdentity::Pkcs12 {
+                path,
+                password: "wrong".to_string(),
+            }),
+            ..trusting(&dir, &pki)
+        };
+        assert!(matches!(
+            wrong_password.client_config(),
+            Err(AppError::ValidationError { ref field, .. }) if field == "client_identity"
+        ));
+    }
+
+    #[test]
+    fn test_unreadable_tls_files_are_validation_errors() {
+        let pki = TestPki::generate();
+        let dir = tempfile::tempdir().unwrap();
+        let not_pem = write_file(dir.path(), "empty.pem", "");
+
+        let missing_ca = GrpcTlsConfig {
+            ca_cert_path: Some(dir.path().join("missing.pem").to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let empty_ca = GrpcTlsConfig {
+            ca_cert_path: Some(not_pem.clone()),
+            ..Default::default()
+        };
+        let missing_key = GrpcTlsConfig {
+            client_identity: Some(ClientIdentity::Pem {
+                cert_path: write_file(dir.path(), "client.pem", &pki.client_cert_pem),
+                key_path: not_pem,
+            }),
+            ..trusting(&dir, &pki)
+        };
+
+        for (tls, expected_field) in [
+            (missing_ca, "ca_cert_path"),
+            (empty_ca, "ca_cert_path"),
+            (missing_key, "client_identity"),
+        ] {
+            match tls.client_config() {
+                Err(AppError::ValidationError { field, .. }) => assert_eq!(field, expected_field),
+                other => panic!("Expected a validation error, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tls_settings_need_https_url() {
+        let tls = GrpcTlsConfig {
+            insecure_skip_verify: true,
+            ..Default::default()
+        };
+
+        let result = connect("http://127.0.0.1:1", Some(&tls), &ProxySettings::direct()).await;
+        assert!(matches!(
+            result,
+            Err(AppError::ValidationError { ref field, .. }) if field == "url"
+        ));
+    }
+
+    #[test]
+    fn test_tls_config_deserializes_from_frontend_json() {
+        let tls: GrpcTlsConfig = serde_json::from_value(serde_json::json!({
+            "ca_cert_path": "/certs/ca.pem",
+            "client_identity": {"type": "pkcs12", "path": "/certs/client.p12"},
+        }))
+        .unwrap();
+
+        assert_eq!(tls.ca_cert_path.as_deref(), Some("/certs/ca.pem"));
+        assert_eq!(
+            tls.client_identity,
+            Some(ClientIdentity::Pkcs12 {
+                path: "/certs/client.p12".to_string(),
+                password: String::new(),
+            })
+        );
+        assert!(!tls.insecure_skip_verify);
+    }
+}
@@ -0,0 +1,1571 @@
+use crate::client::proxy::ProxySettings;
+use crate::client::settings::HttpClientSettings;
+use crate::client::HttpClient;
+use crate::error::{AppError, AppResult};
+use crate::grpc::client::{method_path, oversized_status, resolve_method, Deadline};
+use crate::grpc::error_details::RpcStatus;
+use crate::grpc::json::{message_to_json, JsonOptions};
+use crate::grpc::streaming::{GrpcStreamEnd, GrpcStreamEvent};
+use crate::grpc::{
+    create_metadata_map, status_code_name, GrpcCallType, GrpcRequest, GrpcResponse, GrpcTransport,
+};
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+
+/// Largest response message accepted when no limit is set, like tonic
+const DEFAULT_MAX_RESPONSE_SIZE: usize = 4 * 1024 * 1024;
+/// Request timeout of calls without a deadline. The shared HTTP client stops
+/// plain requests after 30 s, which would cut long-running streams short.
+const NO_DEADLINE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Frame flag of compressed messages, in both protocols
+const FLAG_COMPRESSED: u8 = 0x01;
+/// Frame flag of the Connect end-of-stream message
+const FLAG_END_STREAM: u8 = 0x02;
+/// Frame flag of the gRPC-Web trailers frame
+const FLAG_TRAILERS: u8 = 0x80;
+/// Length of the flags byte and length prefix in front of every frame
+const FRAME_HEADER_LEN: usize = 5;
+
+/// The only message compression supported, in both directions
+const GZIP: &str = "gzip";
+
+/// Prefix of the headers carrying the trailers of unary Connect calls
+const CONNECT_TRAILER_PREFIX: &str = "trailer-";
+
+/// Standard base64 with optional padding, as used by gRPC-Web text bodies
+/// and Connect error details
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Client for the gRPC-Web and Connect protocols, sending dynamic messages
+/// through the shared `HttpClient` instead of a tonic channel.
+///
+/// Both protocols work over HTTP/1.1, where a request body is sent in full
+/// before the response is read: client-streaming calls buffer their messages
+/// until the request stream is closed and bidirectional calls are not
+/// supported.
+pub struct HttpTransportClient {
+    http: HttpClient,
+    pool: DescriptorPool,
+}
+
+impl HttpTransportClient {
+    pub fn new(pool: DescriptorPool, proxy: &ProxySettings) -> AppResult<Self> {
+        // Messages carry their own compression, so no HTTP content coding is
+        // negotiated for the body
+        let settings = HttpClientSettings {
+            proxy: Some(proxy.clone()),
+            gzip: Some(false),
+            brotli: Some(false),
+            zstd: Some(false),
+            ..Default::default()
+        };
+        Ok(Self {
+            http: HttpClient::with_settings(&settings)?,
+            pool,
+        })
+    }
+
+    pub fn find_method(&self, service: &str, method: &str) -> AppResult<MethodDescriptor> {
+        resolve_method(&self.pool, service, method)
+    }
+
+    pub async fn unary_call(&self, request: GrpcRequest) -> AppResult<GrpcResponse> {
+        let method = self.find_method(&request.service, &request.method)?;
+        let message = DynamicMessage::deserialize(method.input(), request.message.clone())?;
+        let deadline = Deadline::after(&request.options);
+
+        self.single_response(&request, &method, &[message], deadline)
+            .await
+    }
+
+    /// Run a server-streaming call, sending each decoded message to `tx` as it
+    /// arrives and finishing with the final status and trailers
+    pub async fn server_streaming_call(
+        &self,
+        request: GrpcRequest,
+        tx: mpsc::Sender<GrpcStreamEvent>,
+    ) -> AppResult<()> {
+        let method = self.find_method(&request.service, &request.method)?;
+        let message = DynamicMessage::deserialize(method.input(), request.message.clone())?;
+        let deadline = Deadline::after(&request.options);
+
+        let end = match self.start(&request, &method, &[message], deadline).await? {
+            Ok(call) => call.forward(&request.options.json, &tx).await?,
+            Err(status) => GrpcStreamEnd::from_status(&status, 0, &request.options.json),
+        };
+
+        let _ = tx.send(GrpcStreamEvent::End(end)).await;
+        Ok(())
+    }
+
+    /// Run a client-streaming call fed by `messages`. They are sent together
+    /// once the sender of `messages` is dropped (half-close), then the single
+    /// response is sent to `tx`.
+    pub async fn client_streaming_call(
+        &self,
+        request: GrpcRequest,
+        mut messages: mpsc::Receiver<DynamicMessage>,
+        tx: mpsc::Sender<GrpcStreamEvent>,
+    ) -> AppResult<()> {
+        let method = self.find_method(&request.service, &request.method)?;
+        let deadline = Deadline::after(&request.options);
+
+        let mut buffered = Vec::new();
+        let collected = deadline
+            .run(async {
+                while let Some(message) = messages.recv().await {
+                    buffered.push(message);
+                }
+                Ok(())
+            })
+            .await;
+        let mut response = match collected {
+            Ok(()) => {
+                self.single_response(&request, &method, &buffered, deadline)
+                    .await?
+            }
+            Err(status) => GrpcResponse::from_status(&status, &request.options.json),
+        };
+
+        let mut message_count = 0;
+        if let Some(data) = response.data.take() {
+            message_count = 1;
+            let _ = tx.send(GrpcStreamEvent::Message(data)).await;
+        }
+        let end = GrpcStreamEnd::new(response, message_count);
+        let _ = tx.send(GrpcStreamEvent::End(end)).await;
+        Ok(())
+    }
+
+    /// Send `messages` and read the single response message along with the
+    /// headers and trailers
+    async fn single_response(
+        &self,
+        request: &GrpcRequest,
+        method: &MethodDescriptor,
+        messages: &[DynamicMessage],
+        deadline: Deadline,
+    ) -> AppResult<GrpcResponse> {
+        let mut call = match self.start(request, method, messages, deadline).await? {
+            Ok(call) => call,
+            Err(status) => return Ok(GrpcResponse::from_status(&status, &request.options.json)),
+        };
+
+        let received = async {
+            let message = call
+                .next_message()
+                .await?
+                .ok_or_else(|| Status::internal("Server sent no response message"))?;
+            if call.next_message().await?.is_some() {
+                return Err(Status::internal(
+                    "Server sent more than one response message",
+                ));
+            }
+            Ok(message)
+        }
+        .await;
+
+        let response = match received {
+            Ok(message) => {
+                let data = message_to_json(&message, &request.options.json)?;
+                GrpcResponse::ok(data).with_trailers(&call.trailers)
+            }
+            Err(status) => GrpcResponse::from_status(&status, &request.options.json),
+        };
+        Ok(response.with_headers(&call.headers))
+    }
+
+    /// Send the request of a call. Fails with the status of the call when the
+    /// server could not be reached or answered with an error right away.
+    async fn start(
+        &self,
+        request: &GrpcRequest,
+        method: &MethodDescriptor,
+        messages: &[DynamicMessage],
+        deadline: Deadline,
+    ) -> AppResult<Result<HttpCall, Status>> {
+        let protocol = Protocol::of(request.transport, &request.call_type)?;
+        for message in messages {
+            if let Some(status) = oversized_status(request.options.max_request_size, message) {
+                return Ok(Err(status));
+            }
+        }
+
+        let url = format!(
+            "{}{}",
+            request.url.trim_end_matches('/'),
+            method_path(method)?
+        );
+        let url = reqwest::Url::parse(&url)
+            .map_err(|e| AppError::validation("url", format!("Invalid URL '{}': {}", url, e)))?;
+
+        let gzip = request.options.gzip;
+        let mut headers = protocol.headers(request.options.timeout(), gzip);
+        if let Some(metadata) = &request.metadata {
+            headers.extend(http_headers(&create_metadata_map(metadata)?.into_headers()));
+        }
+
+        let sent = self
+            .http
+            .client()
+            .post(url)
+            .headers(headers)
+            .body(protocol.body(messages, gzip)?)
+            .timeout(request.options.timeout().unwrap_or(NO_DEADLINE_TIMEOUT))
+            .send();
+        let response = match deadline
+            .run(async { sent.await.map_err(request_failed) })
+            .await
+        {
+            Ok(response) => response,
+            Err(status) => return Ok(Err(status)),
+        };
+
+        let max_response_size = request
+            .options
+            .max_response_size
+            .unwrap_or(DEFAULT_MAX_RESPONSE_SIZE);
+        Ok(HttpCall::open(
+            protocol,
+            response,
+            method.output(),
+            deadline,
+            max_response_size,
+        )
+        .await)
+    }
+}
+
+/// Wire format of one call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    GrpcWeb {
+        text: bool,
+    },
+    /// Unary Connect calls send bare messages, streaming ones framed messages
+    Connect {
+        json: bool,
+        streaming: bool,
+    },
+}
+
+impl Protocol {
+    fn of(transport: GrpcTransport, call_type: &GrpcCallType) -> AppResult<Self> {
+        let streaming = !matches!(call_type, GrpcCallType::Unary);
+        if matches!(call_type, GrpcCallType::Bidirectional) {
+            return Err(AppError::validation(
+                "transport",
+                "Bidirectional calls need the native gRPC transport",
+            ));
+        }
+
+        match transport {
+            GrpcTransport::Grpc => Err(AppError::validation(
+                "transport",
+                "Native gRPC calls go through a tonic channel",
+            )),
+            GrpcTransport::GrpcWeb => Ok(Protocol::GrpcWeb { text: false }),
+            GrpcTransport::GrpcWebText => Ok(Protocol::GrpcWeb { text: true }),
+            GrpcTransport::ConnectJson => Ok(Protocol::Connect {
+                json: true,
+                streaming,
+            }),
+            GrpcTransport::ConnectProto => Ok(Protocol::Connect {
+                json: false,
+                streaming,
+            }),
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Protocol::GrpcWeb { text: false } => "application/grpc-web+proto",
+            Protocol::GrpcWeb { text: true } => "application/grpc-web-text+proto",
+            Protocol::Connect {
+                json: true,
+                streaming: false,
+            } => "application/json",
+            Protocol::Connect {
+                json: false,
+                streaming: false,
+            } => "application/proto",
+            Protocol::Connect {
+                json: true,
+                streaming: true,
+            } => "application/connect+json",
+            Protocol::Connect {
+                json: false,
+                streaming: true,
+            } => "application/connect+proto",
+        }
+    }
+
+    fn is_json(self) -> bool {
+        matches!(self, Protocol::Connect { json: true, .. })
+    }
+
+    fn is_framed(self) -> bool {
+        !matches!(
+            self,
+            Protocol::Connect {
+                streaming: false,
+                ..
+            }
+        )
+    }
+
+    /// Protocol headers of a request, announcing the deadline to the server
+    /// and, with `gzip`, the compression of messages in both directions
+    fn headers(self, timeout: Option<Duration>, gzip: bool) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let content_type = HeaderValue::from_static(self.content_type());
+        headers.insert(CONTENT_TYPE, content_type.clone());
+
+        let timeout_ms = timeout.map(|timeout| timeout.as_millis() as u64);
+        match self {
+            Protocol::GrpcWeb { .. } => {
+                headers.insert(ACCEPT, content_type);
+                headers.insert("x-grpc-web", HeaderValue::from_static("1"));
+                if let Some(ms) = timeout_ms {
+                    // At most 8 digits are allowed
+                    let value = format!("{}m", ms.min(99_999_999));
+                    if let Ok(value) = HeaderValue::from_str(&value) {
+                        headers.insert("grpc-timeout", value);
+                    }
+                }
+                if gzip {
+                    headers.insert("grpc-encoding", HeaderValue::from_static(GZIP));
+                    headers.insert("grpc-accept-encoding", HeaderValue::from_static(GZIP));
+                }
+            }
+            Protocol::Connect { streaming, .. } => {
+                headers.insert("connect-protocol-version", HeaderValue::from_static("1"));
+                if let Some(ms) = timeout_ms {
+                    // At most 10 digits are allowed
+                    headers.insert("connect-timeout-ms", ms.min(9_999_999_999).into());
+                }
+                match (gzip, streaming) {
+                    (true, true) => {
+                        let gzip = HeaderValue::from_static(GZIP);
+                        headers.insert("connect-content-encoding", gzip.clone());
+                        headers.insert("connect-accept-encoding", gzip);
+                    }
+                    // Unary bodies are compressed as a whole
+                    (true, false) => {
+                        let gzip = HeaderValue::from_static(GZIP);
+                        headers.insert(CONTENT_ENCODING, gzip.clone());
+                        headers.insert(ACCEPT_ENCODING, gzip);
+                    }
+                    (false, _) => {}
+                }
+            }
+        }
+        headers
+    }
+
+    fn encode(self, message: &DynamicMessage) -> AppResult<Vec<u8>> {
+        match self.is_json() {
+            true => Ok(serde_json::to_vec(message)?),
+            false => Ok(message.encode_to_vec()),
+        }
+    }
+
+    fn decode(self, desc: &MessageDescriptor, payload: &[u8]) -> Result<DynamicMessage, String> {
+        let decoded = match self.is_json() {
+            true => {
+                let mut deserializer = serde_json::Deserializer::from_slice(payload);
+                DynamicMessage::deserialize(desc.clone(), &mut deserializer)
+                    .and_then(|message| deserializer.end().map(|_| message))
+                    .map_err(|e| e.to_string())
+            }
+            false => DynamicMessage::decode(desc.clone(), payload).map_err(|e| e.to_string()),
+        };
+        decoded.map_err(|e| format!("Failed to decode '{}': {}", desc.full_name(), e))
+    }
+
+    /// Request body carrying `messages`, each compressed with `gzip`
+    fn body(self, messages: &[DynamicMessage], gzip: bool) -> AppResult<Vec<u8>> {
+        let encode = |message| match gzip {
+            true => compress(&self.encode(message)?),
+            false => self.encode(message),
+        };
+        if !self.is_framed() {
+            return match messages {
+                [message] => encode(message),
+                _ => Err(AppError::internal("Unary calls send exactly one message")),
+            };
+        }
+
+        let mut body = Vec::new();
+        for message in messages {
+            let payload = encode(message)?;
+            body.push(if gzip { FLAG_COMPRESSED } else { 0 });
+            body.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+            body.extend_from_slice(&payload);
+        }
+        match self {
+            Protocol::GrpcWeb { text: true } => Ok(BASE64.encode(body).into_bytes()),
+            _ => Ok(body),
+        }
+    }
+}
+
+/// A call whose request was sent and whose response is being read
+struct HttpCall {
+    protocol: Protocol,
+    body: ResponseBody,
+    output: MessageDescriptor,
+    deadline: Deadline,
+    /// Compression of the response messages announced by the server
+    encoding: Option<String>,
+    headers: MetadataMap,
+    /// Trailing metadata of a call that finished successfully
+    trailers: MetadataMap,
+    /// Status the call ends with once the remaining body is read, known
+    /// upfront for responses without messages
+    end: Option<Status>,
+    finished: bool,
+}
+
+impl HttpCall {
+    /// Check the HTTP response of a call, failing with the status of the call
+    /// when the server answered without a response body to read
+    async fn open(
+        protocol: Protocol,
+        response: reqwest::Response,
+        output: MessageDescriptor,
+        deadline: Deadline,
+        max_response_size: usize,
+    ) -> Result<Self, Status> {
+        let http_status = response.status();
+        let mut headers = response.headers().clone();
+        let mut trailers = HeaderMap::new();
+        let mut end = None;
+
+        match protocol {
+            Protocol::GrpcWeb { .. } => {
+                // Trailers-only responses carry the status among the headers
+                if headers.contains_key("grpc-status") {
+                    let status = trailers_status(&headers);
+                    if status.code() != Code::Ok {
+                        return Err(status);
+                    }
+                    end = Some(status);
+                } else if http_status != StatusCode::OK {
+                    return Err(http_error(http_status));
+                }
+            }
+            Protocol::Connect { streaming, .. } => {
+                if !streaming {
+                    trailers = split_connect_trailers(&mut headers);
+                }
+                if http_status != StatusCode::OK {
+                    let body = deadline
+                        .run(async { response.bytes().await.map_err(request_failed) })
+                        .await?;
+                    return Err(connect_error(http_status, &body, &trailers));
+                }
+            }
+        }
+
+        let encoding = match protocol {
+            Protocol::GrpcWeb { .. } => headers.get("grpc-encoding"),
+            Protocol::Connect {
+                streaming: true, ..
+            } => headers.get("connect-content-encoding"),
+            Protocol::Connect {
+                streaming: false, ..
+            } => headers.get(CONTENT_ENCODING),
+        };
+        let encoding = encoding
+            .and_then(|value| value.to_str().ok())
+            .filter(|encoding| *encoding != "identity")
+            .map(str::to_string);
+
+        Ok(Self {
+            protocol,
+            body: ResponseBody::new(
+                response,
+                protocol == Protocol::GrpcWeb { text: true },
+                max_response_size,
+            ),
+            output,
+            deadline,
+            encoding,
+            headers: metadata(&headers),
+            trailers: metadata(&trailers),
+            end,
+            finished: false,
+        })
+    }
+
+    /// Read the next response message. `None` once the call finished
+    /// successfully, with its trailers in `trailers`; calls ending with an
+    /// error status fail with that status.
+    async fn next_message(&mut self) -> Result<Option<DynamicMessage>, Status> {
+        if self.finished {
+            return Ok(None);
+        }
+        if let Some(status) = self.end.take() {
+            self.finished = true;
+            self.trailers = status.metadata().clone();
+            return Ok(None);
+        }
+
+        let (flags, mut payload) = if self.protocol.is_framed() {
+            let Some(frame) = self.body.frame(self.deadline).await? else {
+                self.finished = true;
+                return Err(Status::internal(
+                    "Server closed the response without a status",
+                ));
+            };
+            frame
+        } else {
+            let body = self.body.read_to_end(self.deadline).await?;
+            // The call ends after this message, with the trailers taken from
+            // the headers
+            let trailers = std::mem::take(&mut self.trailers);
+            self.end = Some(Status::with_metadata(Code::Ok, "", trailers));
+            // A single message, compressed as a whole by its content encoding
+            match self.encoding {
+                Some(_) => (FLAG_COMPRESSED, body),
+                None => (0, body),
+            }
+        };
+        if flags & FLAG_COMPRESSED != 0 {
+            payload = self.decompress(&payload).map_err(Status::internal)?;
+            if payload.len() > self.body.max_size {
+                return Err(Status::resource_exhausted(format!(
+                    "Decompressed response message is larger than the limit of {} bytes",
+                    self.body.max_size
+                )));
+            }
+        }
+
+        match self.protocol {
+            Protocol::GrpcWeb { .. } if flags & FLAG_TRAILERS != 0 => {
+                self.finished = true;
+                let status = trailers_status(&parse_trailers_frame(&payload));
+                match status.code() {
+                    Code::Ok => {
+                        self.trailers = status.metadata().clone();
+                        Ok(None)
+                    }
+                    _ => Err(status),
+                }
+            }
+            Protocol::Connect { .. } if flags & FLAG_END_STREAM != 0 => {
+                self.finished = true;
+                let end_stream: EndStream = serde_json::from_slice(&payload).map_err(|e| {
+                    Status::internal(format!("Invalid end of stream message: {}", e))
+                })?;
+                let metadata = end_stream.metadata();
+                match end_stream.error {
+                    Some(error) => Err(error.into_status(metadata)),
+                    None => {
+                        self.trailers = metadata;
+                        Ok(None)
+                    }
+                }
+            }
+            _ => self
+                .protocol
+                .decode(&self.output, &payload)
+                .map(Some)
+                .map_err(Status::internal),
+        }
+    }
+
+    /// Inflate a compressed frame, reading at most one byte past the response
+    /// size limit
+    fn decompress(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        if self.encoding.as_deref() != Some(GZIP) {
+            return Err(format!(
+                "Server sent a message compressed with unsupported encoding '{}'",
+                self.encoding.as_deref().unwrap_or("identity")
+            ));
+        }
+
+        let mut message = Vec::new();
+        GzDecoder::new(payload)
+            .take(self.body.max_size as u64 + 1)
+            .read_to_end(&mut message)
+            .map_err(|e| format!("Failed to decompress a message: {}", e))?;
+        Ok(message)
+    }
+
+    /// Send every response message to `tx` and return how the stream ended.
+    /// Stops reading early when the receiving side has gone away.
+    async fn forward(
+        mut self,
+        json: &JsonOptions,
+        tx: &mpsc::Sender<GrpcStreamEvent>,
+    ) -> AppResult<GrpcStreamEnd> {
+        let mut message_count = 0;
+
+        let end = loop {
+            match self.next_message().await {
+                Ok(Some(message)) => {
+                    message_count += 1;
+                    let data = message_to_json(&message, json)?;
+                    if tx.send(GrpcStreamEvent::Message(data)).await.is_err() {
+                        break GrpcResponse::from_status(
+                            &Status::cancelled("Stream receiver closed"),
+                            json,
+                        );
+                    }
+                }
+                Ok(None) => {
+                    break GrpcResponse::from_status(&Status::ok(""), json)
+                        .with_trailers(&self.trailers)
+                }
+                Err(status) => break GrpcResponse::from_status(&status, json),
+            }
+        };
+
+        Ok(GrpcStreamEnd::new(
+            end.with_headers(&self.headers),
+            message_count,
+        ))
+    }
+}
+
+/// Response body read chunk by chunk, decoding gRPC-Web text on the way
+struct ResponseBody {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    /// Base64 text not decoded yet, for `grpc-web-text` responses
+    text: Option<Vec<u8>>,
+    /// Largest message accepted, checked before the message is buffered
+    max_size: usize,
+}
+
+impl ResponseBody {
+    fn new(response: reqwest::Response, text: bool, max_size: usize) -> Self {
+        Self {
+            response,
+            buffer: Vec::new(),
+            text: text.then(Vec::new),
+            max_size,
+        }
+    }
+
+    /// Read the next chunk into the buffer; `false` at the end of the body
+    async fn read_chunk(&mut self, deadline: Deadline) -> Result<bool, Status> {
+        let response = &mut self.response;
+        let chunk = deadline
+            .run(async { response.chunk().await.map_err(request_failed) })
+            .await?;
+
+        match (chunk, &mut self.text) {
+            (Some(chunk), None) => self.buffer.extend_from_slice(&chunk),
+            (Some(chunk), Some(text)) => {
+                text.extend_from_slice(&chunk);
+                // Decode whole base64 quanta only, the rest may be split
+                let complete = text.len() / 4 * 4;
+                let quanta: Vec<u8> = text.drain(..complete).collect();
+                decode_text(&quanta, &mut self.buffer).map_err(invalid_text)?;
+            }
+            (None, Some(text)) if !text.is_empty() => {
+                let rest = std::mem::take(text);
+                decode_text(&rest, &mut self.buffer).map_err(invalid_text)?;
+            }
+            (None, _) => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Next frame of the body as its flags and payload, `None` at the end
+    async fn frame(&mut self, deadline: Deadline) -> Result<Option<(u8, Vec<u8>)>, Status> {
+        loop {
+            if self.buffer.len() >= FRAME_HEADER_LEN {
+                let mut length = [0; 4];
+                length.copy_from_slice(&self.buffer[1..FRAME_HEADER_LEN]);
+                let length = u32::from_be_bytes(length) as usize;
+                // Trust the announced length only up to the limit
+                if length > self.max_size {
+                    return Err(oversized(length, self.max_size));
+                }
+                let end = FRAME_HEADER_LEN + length;
+                if self.buffer.len() >= end {
+                    let flags = self.buffer[0];
+                    let payload = self.buffer[FRAME_HEADER_LEN..end].to_vec();
+                    self.buffer.drain(..end);
+                    return Ok(Some((flags, payload)));
+                }
+            }
+
+            if !self.read_chunk(deadline).await? {
+                return match self.buffer.is_empty() {
+                    true => Ok(None),
+                    false => Err(Status::internal(
+                        "Response body ended in the middle of a message",
+                    )),
+                };
+            }
+        }
+    }
+
+    /// The whole body, for unframed responses holding a single message
+    async fn read_to_end(&mut self, deadline: Deadline) -> Result<Vec<u8>, Status> {
+        while self.read_chunk(deadline).await? {
+            if self.buffer.len() > self.max_size {
+                return Err(oversized(self.buffer.len(), self.max_size));
+            }
+        }
+        Ok(std::mem::take(&mut self.buffer))
+    }
+}
+
+/// RESOURCE_EXHAUSTED for a message of `size` bytes above the response size
+/// limit. Bodies read whole report the size read when they went over.
+fn oversized(size: usize, limit: usize) -> Status {
+    Status::resource_exhausted(format!(
+        "Response message of {} bytes is larger than the limit of {} bytes",
+        size, limit
+    ))
+}
+
+fn compress(data: &[u8]) -> AppResult<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| AppError::internal(format!("Failed to compress a message: {}", e)))
+}
+
+/// Decode gRPC-Web text, which may join separately padded base64 chunks
+fn decode_text(text: &[u8], out: &mut Vec<u8>) -> Result<(), base64::DecodeError> {
+    let mut start = 0;
+    for (index, quantum) in text.chunks(4).enumerate() {
+        if quantum.contains(&b'=') {
+            let end = index * 4 + quantum.len();
+            out.extend(BASE64.decode(&text[start..end])?);
+            start = end;
+        }
+    }
+    out.extend(BASE64.decode(&text[start..])?);
+    Ok(())
+}
+
+fn invalid_text(error: base64::DecodeError) -> Status {
+    Status::internal(format!("Response is not valid gRPC-Web text: {}", error))
+}
+
+/// Parse the HTTP/1 style header block of a gRPC-Web trailers frame
+fn parse_trailers_frame(block: &[u8]) -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+    for line in String::from_utf8_lossy(block).split("\r\n") {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = HeaderName::from_bytes(name.trim().to_ascii_lowercase().as_bytes());
+        let value = HeaderValue::from_str(value.trim());
+        if let (Ok(name), Ok(value)) = (name, value) {
+            trailers.append(name, value);
+        }
+    }
+    trailers
+}
+
+/// Status of gRPC trailers; the rest of the trailers becomes its metadata
+fn trailers_status(trailers: &HeaderMap) -> Status {
+    let mut trailers = trailers.clone();
+    // tonic panics on details it cannot decode
+    let valid_details = trailers
+        .get("grpc-status-details-bin")
+        .map(|details| BASE64.decode(details.as_bytes()).is_ok());
+    if valid_details == Some(false) {
+        trailers.remove("grpc-status-details-bin");
+    }
+
+    Status::from_header_map(&tonic_headers(&trailers))
+        .unwrap_or_else(|| Status::internal("Server sent trailers without a grpc-status"))
+}
+
+/// Move the `trailer-` prefixed headers of a unary Connect response out of
+/// `headers`, without their prefix
+fn split_connect_trailers(headers: &mut HeaderMap) -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+    for (name, value) in headers.iter() {
+        if let Some(trailer) = name.as_str().strip_prefix(CONNECT_TRAILER_PREFIX) {
+            if let Ok(trailer) = HeaderName::from_bytes(trailer.as_bytes()) {
+                trailers.append(trailer, value.clone());
+            }
+        }
+    }
+
+    let names: Vec<HeaderName> = headers
+        .keys()
+        .filter(|name| name.as_str().starts_with(CONNECT_TRAILER_PREFIX))
+        .cloned()
+        .collect();
+    for name in names {
+        headers.remove(name);
+    }
+    trailers
+}
+
+/// Status of a failed Connect call: the JSON error of the body, or one
+/// derived from the HTTP status when the body is not a Connect error
+fn connect_error(http_status: StatusCode, body: &[u8], trailers: &HeaderMap) -> Status {
+    let metadata = metadata(trailers);
+    match serde_json::from_slice::<ConnectError>(body) {
+        Ok(error) if !error.code.is_empty() => error.into_status(metadata),
+        _ => {
+            let status = http_error(http_status);
+            Status::with_metadata(status.code(), status.message(), metadata)
+        }
+    }
+}
+
+/// Status of an HTTP response that carries none, per the gRPC mapping of
+/// HTTP status codes
+fn http_error(http_status: StatusCode) -> Status {
+    let code = match http_status.as_u16() {
+        400 => Code::Internal,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::Unimplemented,
+        429 | 502 | 503 | 504 => Code::Unavailable,
+        _ => Code::Unknown,
+    };
+    Status::new(code, format!("Server responded with HTTP {}", http_status))
+}
+
+fn request_failed(error: reqwest::Error) -> Status {
+    match error.is_timeout() {
+        true => Status::deadline_exceeded("Deadline exceeded"),
+        false => Status::unavailable(format!("Request failed: {}", error)),
+    }
+}
+
+/// Error of the Connect protocol, e.g.
+/// `{"code": "not_found", "message": "...", "details": [...]}`
+#[derive(Debug, Deserialize)]
+struct ConnectError {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    details: Vec<ConnectErrorDetail>,
+}
+
+/// Detail message of a Connect error, carried as base64 protobuf
+#[derive(Debug, Deserialize)]
+struct ConnectErrorDetail {
+    #[serde(rename = "type")]
+    type_name: String,
+    #[serde(default)]
+    value: String,
+}
+
+impl ConnectError {
+    /// The details are re-encoded as a `google.rpc.Status`, like gRPC servers
+    /// send them, so they are decoded the same way
+    fn into_status(self, metadata: MetadataMap) -> Status {
+        let code = connect_code(&self.code);
+        let details: Vec<prost_types::Any> = self
+            .details
+            .iter()
+            .filter_map(|detail| {
+                Some(prost_types::Any {
+                    type_url: format!("type.googleapis.com/{}", detail.type_name),
+                    value: BASE64.decode(&detail.value).ok()?,
+                })
+            })
+            .collect();
+        let details = match details.is_empty() {
+            true => Vec::new(),
+            false => RpcStatus {
+                code: code as i32,
+                message: self.message.clone(),
+                details,
+            }
+            .encode_to_vec(),
+        };
+
+        Status::with_details_and_metadata(code, self.message, details.into(), metadata)
+    }
+}
+
+/// gRPC code of a Connect error code, which is the lower-case name of the
+/// code except for "canceled"
+fn connect_code(name: &str) -> Code {
+    let name = match name {
+        "canceled" => "cancelled",
+        name => name,
+    };
+    (0..=16)
+        .map(Code::from_i32)
+        .find(|code| status_code_name(*code).eq_ignore_ascii_case(name))
+        .unwrap_or(Code::Unknown)
+}
+
+/// Last message of a Connect stream
+#[derive(Debug, Deserialize)]
+struct EndStream {
+    error: Option<ConnectError>,
+    #[serde(default)]
+    metadata: HashMap<String, Vec<String>>,
+}
+
+impl EndStream {
+    fn metadata(&self) -> MetadataMap {
+        let mut headers = HeaderMap::new();
+        for (name, values) in &self.metadata {
+            let Ok(name) = HeaderName::from_bytes(name.to_ascii_lowercase().as_bytes()) else {
+                continue;
+            };
+            for value in values {
+                if let Ok(value) = HeaderValue::from_str(value) {
+                    headers.append(name.clone(), value);
+                }
+            }
+        }
+        metadata(&headers)
+    }
+}
+
+/// Metadata holding the HTTP `headers` of a call
+fn metadata(headers: &HeaderMap) -> MetadataMap {
+    MetadataMap::from_headers(tonic_headers(headers))
+}
+
+/// `headers` of tonic, which uses `http` 0.2, for the HTTP client on `http` 1
+fn http_headers(headers: &http::HeaderMap) -> HeaderMap {
+    let mut converted = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_str().as_bytes());
+        let value = HeaderValue::from_bytes(value.as_bytes());
+        if let (Ok(name), Ok(value)) = (name, value) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
+
+/// `headers` as the `http` 0.2 map tonic takes
+fn tonic_headers(headers: &HeaderMap) -> http::HeaderMap {
+    let mut converted = http::HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = http::HeaderName::from_bytes(name.as_str().as_bytes());
+        let value = http::HeaderValue::from_bytes(value.as_bytes());
+        if let (Ok(name), Ok(value)) = (name, value) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::streaming::create_event_channel;
+    use crate::grpc::test_utils::server::{echo_descriptor_pool, TestServer};
+    use crate::grpc::GrpcCallOptions;
+    use base64::engine::general_purpose::STANDARD_NO_PAD;
+    use httpmock::Method::POST;
+    use httpmock::MockServer;
+    use serde_json::json;
+
+    fn request(
+        url: &str,
+        transport: GrpcTransport,
+        method: &str,
+        message: serde_json::Value,
+    ) -> GrpcRequest {
+        let call_type =
+            GrpcCallType::of(&resolve_method(&echo_descriptor_pool(), "Echo", method).unwrap());
+        GrpcRequest {
+            url: url.to_string(),
+            service: "test.v1.Echo".to_string(),
+            method: method.to_string(),
+            message,
+            metadata: None,
+            call_type,
+            options: Default::default(),
+            transport,
+        }
+    }
+
+    fn client() -> HttpTransportClient {
+        HttpTransportClient::new(echo_descriptor_pool(), &ProxySettings::direct()).unwrap()
+    }
+
+    /// Run a server-streaming call and collect its events
+    async fn stream(request: GrpcRequest) -> (Vec<serde_json::Value>, GrpcStreamEnd) {
+        let (tx, mut rx) = create_event_channel();
+        client().server_streaming_call(request, tx).await.unwrap();
+
+        let mut messages = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                GrpcStreamEvent::Message(data) => messages.push(data),
+                GrpcStreamEvent::End(end) => return (messages, end),
+            }
+        }
+        panic!("stream ended without an end event");
+    }
+
+    fn echo_response(message: &str, index: i32) -> Vec<u8> {
+        let desc = echo_descriptor_pool()
+            .get_message_by_name("test.v1.EchoResponse")
+            .unwrap();
+        DynamicMessage::deserialize(desc, json!({"message": message, "index": index}))
+            .unwrap()
+            .encode_to_vec()
+    }
+
+    fn frame(flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![flags];
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_unary_call() {
+        let server = TestServer::start_grpc_web().await;
+
+        for transport in [GrpcTransport::GrpcWeb, GrpcTransport::GrpcWebText] {
+            let mut request = request(&server.url, transport, "Echo", json!({"message": "hi"}));
+            request.metadata = Some(HashMap::from([("x-echo-id".to_string(), "42".to_string())]));
+
+            let response = client().unary_call(request).await.unwrap();
+
+            assert!(response.success, "{:?}: {:?}", transport, response.error);
+            assert_eq!(response.data, Some(json!({"message": "hi"})));
+            assert_eq!(response.headers.get("x-echo-id").unwrap(), &["42"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_error_status_and_details() {
+        let server = TestServer::start_grpc_web().await;
+
+        let response = client()
+            .unary_call(request(
+                &server.url,
+                GrpcTransport::GrpcWeb,
+                "Echo",
+                json!({"message": "error"}),
+            ))
+            .await
+            .unwrap();
+        assert!(!response.success);
+        assert_eq!(response.status_message.as_deref(), Some("INVALID_ARGUMENT"));
+        assert_eq!(response.error.as_deref(), Some("echo refused"));
+        assert_eq!(
+            response.trailers.get("x-error-reason").unwrap(),
+            &["refused"]
+        );
+
+        let response = client()
+            .unary_call(request(
+                &server.url,
+                GrpcTransport::GrpcWebText,
+                "Echo",
+                json!({"message": "invalid"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.details[0]["@type"],
+            "type.googleapis.com/google.rpc.BadRequest"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_server_streaming() {
+        let server = TestServer::start_grpc_web().await;
+
+        for transport in [GrpcTransport::GrpcWeb, GrpcTransport::GrpcWebText] {
+            let (messages, end) = stream(request(
+                &server.url,
+                transport,
+                "Expand",
+                json!({"message": "hi", "count": 3}),
+            ))
+            .await;
+
+            assert_eq!(messages.len(), 3);
+            assert_eq!(messages[2], json!({"message": "hi", "index": 2}));
+            assert!(end.success, "{:?}: {:?}", transport, end.error);
+        }
+
+        let (messages, end) = stream(request(
+            &server.url,
+            GrpcTransport::GrpcWeb,
+            "Expand",
+            json!({"message": "error", "count": 2}),
+        ))
+        .await;
+        assert_eq!(messages.len(), 2);
+        assert_eq!(end.status_message.as_deref(), Some("ABORTED"));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_client_streaming_buffers_messages() {
+        let server = TestServer::start_grpc_web().await;
+        let (message_tx, message_rx) = mpsc::channel(4);
+        let desc = echo_descriptor_pool()
+            .get_message_by_name("test.v1.EchoRequest")
+            .unwrap();
+        for text in ["a", "b"] {
+            let message = DynamicMessage::deserialize(desc.clone(), json!({"message": text}));
+            message_tx.send(message.unwrap()).await.unwrap();
+        }
+        drop(message_tx);
+
+        let (tx, mut rx) = create_event_channel();
+        client()
+            .client_streaming_call(
+                request(&server.url, GrpcTransport::GrpcWeb, "Collect", json!(null)),
+                message_rx,
+                tx,
+            )
+            .await
+            .unwrap();
+
+        let Some(GrpcStreamEvent::Message(data)) = rx.recv().await else {
+            panic!("expected the response message");
+        };
+        assert_eq!(data, json!({"message": "a,b", "index": 2}));
+        let Some(GrpcStreamEvent::End(end)) = rx.recv().await else {
+            panic!("expected the end of the call");
+        };
+        assert!(end.success);
+        assert_eq!(end.message_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_deadline_and_size_limit() {
+        let server = TestServer::start_grpc_web().await;
+
+        let mut slow = request(
+            &server.url,
+            GrpcTransport::GrpcWeb,
+            "Echo",
+            json!({"message": "slow"}),
+        );
+        slow.options = GrpcCallOptions {
+            timeout_ms: Some(50),
+            ..Default::default()
+        };
+        let response = client().unary_call(slow).await.unwrap();
+        assert_eq!(
+            response.status_message.as_deref(),
+            Some("DEADLINE_EXCEEDED")
+        );
+
+        let mut large = request(
+            &server.url,
+            GrpcTransport::GrpcWeb,
+            "Echo",
+            json!({"message": "x".repeat(100)}),
+        );
+        large.options = GrpcCallOptions {
+            max_response_size: Some(10),
+            ..Default::default()
+        };
+        let response = client().unary_call(large).await.unwrap();
+        assert_eq!(
+            response.status_message.as_deref(),
+            Some("RESOURCE_EXHAUSTED")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_gzip() {
+        let server = TestServer::start_grpc_web().await;
+        let gzip = GrpcCallOptions {
+            gzip: true,
+            ..Default::default()
+        };
+
+        for transport in [GrpcTransport::GrpcWeb, GrpcTransport::GrpcWebText] {
+            let mut echo = request(&server.url, transport, "Echo", json!({"message": "hi"}));
+            echo.options = gzip.clone();
+            let response = client().unary_call(echo).await.unwrap();
+
+            assert!(response.success, "{:?}: {:?}", transport, response.error);
+            assert_eq!(response.data, Some(json!({"message": "hi"})));
+            assert_eq!(response.headers.get("grpc-encoding").unwrap(), &["gzip"]);
+        }
+
+        let mut expand = request(
+            &server.url,
+            GrpcTransport::GrpcWeb,
+            "Expand",
+            json!({"message": "hi", "count": 2}),
+        );
+        expand.options = gzip.clone();
+        let (messages, end) = stream(expand).await;
+        assert_eq!(messages.len(), 2);
+        assert!(end.success, "{:?}", end.error);
+
+        // Small once compressed, over the limit once decompressed
+        let mut large = request(
+            &server.url,
+            GrpcTransport::GrpcWeb,
+            "Echo",
+            json!({"message": "x".repeat(1000)}),
+        );
+        large.options = GrpcCallOptions {
+            max_response_size: Some(100),
+            ..gzip
+        };
+        let response = client().unary_call(large).await.unwrap();
+        assert_eq!(
+            response.status_message.as_deref(),
+            Some("RESOURCE_EXHAUSTED")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_json_unary_call() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/test.v1.Echo/Echo")
+                .header("content-type", "application/json")
+                .header("connect-protocol-version", "1")
+                .header("connect-timeout-ms", "5000")
+                .json_body(json!({"message": "hi"}));
+            then.status(200)
+                .header("content-type", "application/json")
+                .header("x-request-id", "7")
+                .header("trailer-x-cost", "3")
+                .json_body(json!({"message": "hi", "index": 1}));
+        });
+        let mut request = request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Echo",
+            json!({"message": "hi"}),
+        );
+        request.options.timeout_ms = Some(5000);
+
+        let response = client().unary_call(request).await.unwrap();
+
+        mock.assert();
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.data, Some(json!({"message": "hi", "index": 1})));
+        assert_eq!(response.headers.get("x-request-id").unwrap(), &["7"]);
+        assert!(!response.headers.contains_key("trailer-x-cost"));
+        assert_eq!(response.trailers.get("x-cost").unwrap(), &["3"]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_error_maps_code_and_details() {
+        let server = MockServer::start();
+        let detail = crate::grpc::error_details::error_details_pool()
+            .get_message_by_name("google.rpc.BadRequest")
+            .unwrap();
+        let detail =
+            DynamicMessage::deserialize(detail, json!({"fieldViolations": [{"field": "message"}]}))
+                .unwrap();
+        server.mock(|when, then| {
+            when.method(POST).path("/test.v1.Echo/Echo");
+            then.status(400)
+                .header("content-type", "application/json")
+                .json_body(json!({
+                    "code": "invalid_argument",
+                    "message": "bad message",
+                    "details": [{
+                        "type": "google.rpc.BadRequest",
+                        "value": STANDARD_NO_PAD.encode(detail.encode_to_vec()),
+                    }],
+                }));
+        });
+
+        let response = client()
+            .unary_call(request(
+                &server.base_url(),
+                GrpcTransport::ConnectProto,
+                "Echo",
+                json!({"message": "hi"}),
+            ))
+            .await
+            .unwrap();
+
+        assert!(!response.success);
+        assert_eq!(response.status_code, Some(Code::InvalidArgument as u32));
+        assert_eq!(response.error.as_deref(), Some("bad message"));
+        assert_eq!(
+            response.details[0]["fieldViolations"][0]["field"],
+            "message"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_http_error_without_body() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST);
+            then.status(503);
+        });
+
+        let response = client()
+            .unary_call(request(
+                &server.base_url(),
+                GrpcTransport::ConnectJson,
+                "Echo",
+                json!({}),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_message.as_deref(), Some("UNAVAILABLE"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_proto_server_streaming() {
+        let server = MockServer::start();
+        let mut body = frame(0, &echo_response("hi", 0));
+        body.extend(frame(0, &echo_response("hi", 1)));
+        body.extend(frame(
+            FLAG_END_STREAM,
+            br#"{"metadata": {"x-cost": ["3"]}}"#,
+        ));
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/test.v1.Echo/Expand")
+                .header("content-type", "application/connect+proto");
+            then.status(200)
+                .header("content-type", "application/connect+proto")
+                .body(body);
+        });
+
+        let (messages, end) = stream(request(
+            &server.base_url(),
+            GrpcTransport::ConnectProto,
+            "Expand",
+            json!({"message": "hi", "count": 2}),
+        ))
+        .await;
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1], json!({"message": "hi", "index": 1}));
+        assert!(end.success, "{:?}", end.error);
+        assert_eq!(end.trailers.get("x-cost").unwrap(), &["3"]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_stream_ending_with_error() {
+        let server = MockServer::start();
+        let mut body = frame(0, br#"{"message": "hi"}"#);
+        body.extend(frame(
+            FLAG_END_STREAM,
+            br#"{"error": {"code": "resource_exhausted", "message": "quota"}}"#,
+        ));
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/test.v1.Echo/Expand")
+                .header("content-type", "application/connect+json");
+            then.status(200).body(body);
+        });
+
+        let (messages, end) = stream(request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Expand",
+            json!({"message": "hi", "count": 2}),
+        ))
+        .await;
+
+        assert_eq!(messages, vec![json!({"message": "hi"})]);
+        assert!(!end.success);
+        assert_eq!(end.status_message.as_deref(), Some("RESOURCE_EXHAUSTED"));
+        assert_eq!(end.error.as_deref(), Some("quota"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_size_limit_checked_before_reading() {
+        let server = MockServer::start();
+        // The header announces 1 GiB, the body never holds it
+        let mut body = vec![0];
+        body.extend_from_slice(&(1u32 << 30).to_be_bytes());
+        body.extend_from_slice(b"{}");
+        server.mock(|when, then| {
+            when.method(POST).path("/test.v1.Echo/Expand");
+            then.status(200).body(body);
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/test.v1.Echo/Echo");
+            then.status(200)
+                .header("content-type", "application/json")
+                .json_body(json!({"message": "x".repeat(100)}));
+        });
+
+        let mut expand = request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Expand",
+            json!({"message": "hi", "count": 1}),
+        );
+        expand.options.max_response_size = Some(1024);
+        let (messages, end) = stream(expand).await;
+        assert!(messages.is_empty());
+        assert_eq!(end.status_message.as_deref(), Some("RESOURCE_EXHAUSTED"));
+        assert!(end.error.unwrap().contains("1073741824 bytes"));
+
+        let mut echo = request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Echo",
+            json!({"message": "hi"}),
+        );
+        echo.options.max_response_size = Some(10);
+        let response = client().unary_call(echo).await.unwrap();
+        assert_eq!(
+            response.status_message.as_deref(),
+            Some("RESOURCE_EXHAUSTED")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_gzip() {
+        let server = MockServer::start();
+        let mut body = frame(FLAG_COMPRESSED, &compress(br#"{"message": "hi"}"#).unwrap());
+        body.extend(frame(FLAG_END_STREAM, b"{}"));
+        let streaming = server.mock(|when, then| {
+            when.method(POST)
+                .path("/test.v1.Echo/Expand")
+                .header("connect-content-encoding", "gzip")
+                .header("connect-accept-encoding", "gzip");
+            then.status(200)
+                .header("connect-content-encoding", "gzip")
+                .body(body);
+        });
+        let unary = server.mock(|when, then| {
+            when.method(POST)
+                .path("/test.v1.Echo/Echo")
+                .header("content-encoding", "gzip")
+                .header("accept-encoding", "gzip");
+            then.status(200)
+                .header("content-type", "application/json")
+                .header("content-encoding", "gzip")
+                .body(compress(br#"{"message": "hi"}"#).unwrap());
+        });
+
+        let mut expand = request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Expand",
+            json!({"message": "hi", "count": 1}),
+        );
+        expand.options.gzip = true;
+        let (messages, end) = stream(expand).await;
+        streaming.assert();
+        assert_eq!(messages, vec![json!({"message": "hi"})]);
+        assert!(end.success, "{:?}", end.error);
+
+        let mut echo = request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Echo",
+            json!({"message": "hi"}),
+        );
+        echo.options.gzip = true;
+        let response = client().unary_call(echo).await.unwrap();
+        unary.assert();
+        assert_eq!(response.data, Some(json!({"message": "hi"})));
+    }
+
+    #[tokio::test]
+    async fn test_no_http_content_coding_for_framed_bodies() {
+        let server = MockServer::start();
+        let mut body = frame(0, br#"{"message": "hi"}"#);
+        body.extend(frame(FLAG_END_STREAM, b"{}"));
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/test.v1.Echo/Expand")
+                .matches(|request| {
+                    let headers = request.headers.as_deref().unwrap_or_default();
+                    !headers
+                        .iter()
+                        .any(|(name, _)| name.eq_ignore_ascii_case("accept-encoding"))
+                });
+            then.status(200).body(body);
+        });
+
+        let mut expand = request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Expand",
+            json!({"message": "hi", "count": 1}),
+        );
+        expand.options.gzip = true;
+        let (messages, end) = stream(expand).await;
+
+        mock.assert();
+        assert_eq!(messages, vec![json!({"message": "hi"})]);
+        assert!(end.success, "{:?}", end.error);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_message_needs_known_encoding() {
+        let server = MockServer::start();
+        let body = frame(FLAG_COMPRESSED, br#"{"message": "hi"}"#);
+        server.mock(|when, then| {
+            when.method(POST).path("/test.v1.Echo/Expand");
+            then.status(200).body(body);
+        });
+
+        let (messages, end) = stream(request(
+            &server.base_url(),
+            GrpcTransport::ConnectJson,
+            "Expand",
+            json!({"message": "hi", "count": 1}),
+        ))
+        .await;
+
+        assert!(messages.is_empty());
+        assert_eq!(
+            end.error.as_deref(),
+            Some("Server sent a message compressed with unsupported encoding 'identity'")
+        );
+    }
+
+    #[test]
+    fn test_decode_text_joins_padded_chunks() {
+        let text = format!("{}{}", BASE64.encode("ab"), BASE64.encode("cde"));
+        let mut decoded = Vec::new();
+
+        decode_text(text.as_bytes(), &mut decoded).unwrap();
+
+        assert_eq!(decoded, b"abcde");
+    }
+
+    #[test]
+    fn test_connect_code_names() {
+        assert_eq!(connect_code("canceled"), Code::Cancelled);
+        assert_eq!(connect_code("not_found"), Code::NotFound);
+        assert_eq!(connect_code("data_loss"), Code::DataLoss);
+        assert_eq!(connect_code("teapot"), Code::Unknown);
+    }
+}
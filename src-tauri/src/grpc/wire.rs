@@ -0,0 +1,504 @@
+use crate::error::{AppError, AppResult};
+use crate::grpc::json::{message_to_json, JsonOptions};
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor};
+use serde::{Deserialize, Serialize};
+
+/// Nesting depth of messages and groups decoded before giving up, like the
+/// recursion limit of protobuf parsers
+const MAX_DEPTH: usize = 64;
+
+/// Base64 as found in logs and queues: padding optional
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+const BASE64_URL_SAFE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Text encoding of binary data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BytesEncoding {
+    #[default]
+    Base64,
+    Hex,
+}
+
+impl BytesEncoding {
+    /// Decode `text`, ignoring whitespace. Hex may start with `0x`; base64 may
+    /// use the URL-safe alphabet.
+    pub fn decode(&self, text: &str) -> AppResult<Vec<u8>> {
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        match self {
+            BytesEncoding::Hex => {
+                let digits = text
+                    .strip_prefix("0x")
+                    .or_else(|| text.strip_prefix("0X"))
+                    .unwrap_or(&text);
+                hex::decode(digits)
+                    .map_err(|e| AppError::validation("data", format!("Invalid hex: {}", e)))
+            }
+            BytesEncoding::Base64 => BASE64
+                .decode(&text)
+                .or_else(|_| BASE64_URL_SAFE.decode(&text))
+                .map_err(|e| AppError::validation("data", format!("Invalid base64: {}", e))),
+        }
+    }
+
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BytesEncoding::Hex => hex::encode(bytes),
+            BytesEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+/// Protobuf encoding of bytes, read with or without their message type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireMessage {
+    pub fields: Vec<WireField>,
+    /// The message in the proto3 JSON mapping, when decoded against its type
+    pub json: Option<serde_json::Value>,
+    /// Why the bytes are not a valid message of the given type
+    pub schema_error: Option<String>,
+}
+
+/// One field of the wire format. Fields repeated on the wire are listed once
+/// per occurrence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireField {
+    pub number: u32,
+    pub wire_type: WireType,
+    /// Position of the field's tag in the decoded bytes
+    pub offset: usize,
+    /// Full name of the field, when its message type is known
+    pub name: Option<String>,
+    pub value: WireValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireType {
+    Varint,
+    I64,
+    Len,
+    StartGroup,
+    EndGroup,
+    I32,
+}
+
+/// The possible readings of a value. 64-bit integers are strings so they keep
+/// their precision in JavaScript.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WireValue {
+    Varint {
+        uint: String,
+        int: String,
+        /// ZigZag decoded, as used by `sint32`/`sint64`
+        sint: String,
+        bool: Option<bool>,
+    },
+    I64 {
+        uint: String,
+        int: String,
+        double: f64,
+    },
+    Len {
+        hex: String,
+        /// The bytes as text, when they are printable UTF-8
+        string: Option<String>,
+        /// The bytes as an embedded message, when they parse as one
+        message: Option<Vec<WireField>>,
+    },
+    Group {
+        fields: Vec<WireField>,
+    },
+    I32 {
+        uint: u32,
+        int: i32,
+        float: f32,
+    },
+}
+
+/// Decode the protobuf wire format of `bytes`. Without a message type every
+/// value gets all plausible readings; with one, fields are named and embedded
+/// messages are read as their declared type.
+pub fn decode_wire(bytes: &[u8], message: Option<&MessageDescriptor>) -> AppResult<WireMessage> {
+    let fields = WireReader::new(bytes, 0)
+        .fields(message, MAX_DEPTH, None)
+        .map_err(AppError::parse)?;
+
+    let (json, schema_error) = match message {
+        Some(desc) => match DynamicMessage::decode(desc.clone(), bytes) {
+            Ok(message) => (
+                Some(message_to_json(&message, &JsonOptions::default())?),
+                None,
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        },
+        None => (None, None),
+    };
+
+    Ok(WireMessage {
+        fields,
+        json,
+        schema_error,
+    })
+}
+
+struct WireReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Offset of `bytes` in the outermost message
+    base: usize,
+}
+
+impl<'a> WireReader<'a> {
+    fn new(bytes: &'a [u8], base: usize) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            base,
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.base + self.pos)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..70).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.pos)
+                .ok_or_else(|| self.error("Truncated varint"))?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("Varint longer than 10 bytes"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| self.error(&format!("Truncated value of {} bytes", len)))?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    /// Read fields up to the end of the bytes, or up to the end of the group
+    /// numbered `group`
+    fn fields(
+        &mut self,
+        message: Option<&MessageDescriptor>,
+        depth: usize,
+        group: Option<u32>,
+    ) -> Result<Vec<WireField>, String> {
+        let mut fields = Vec::new();
+
+        while self.pos < self.bytes.len() {
+            let offset = self.base + self.pos;
+            let tag = self.varint()?;
+            let number = u32::try_from(tag >> 3)
+                .ok()
+                .filter(|number| (1..=536_870_911).contains(number))
+                .ok_or_else(|| format!("Invalid field number {} at byte {}", tag >> 3, offset))?;
+            let field = message.and_then(|message| message.get_field(number));
+
+            let (wire_type, value) = match tag & 7 {
+                0 => (WireType::Varint, varint_value(self.varint()?)),
+                1 => {
+                    let bytes: [u8; 8] = self.take(8)?.try_into().unwrap_or_default();
+                    let value = u64::from_le_bytes(bytes);
+                    let value = WireValue::I64 {
+                        uint: value.to_string(),
+                        int: (value as i64).to_string(),
+                        double: f64::from_bits(value),
+                    };
+                    (WireType::I64, value)
+                }
+                2 => {
+                    let len = self.varint()?;
+                    let len = usize::try_from(len).map_err(|_| self.error("Invalid length"))?;
+                    let payload_offset = self.base + self.pos;
+                    let payload = self.take(len)?;
+                    let value = len_value(payload, payload_offset, field.as_ref(), depth);
+                    (WireType::Len, value)
+                }
+                3 => {
+                    if depth == 0 {
+                        return Err(self.error("Groups nested too deeply"));
+                    }
+                    let group_type = field.as_ref().and_then(message_kind);
+                    let fields = self.fields(group_type.as_ref(), depth - 1, Some(number))?;
+                    (WireType::StartGroup, WireValue::Group { fields })
+                }
+                4 if group == Some(number) => return Ok(fields),
+                4 => {
+                    return Err(format!(
+                        "Unexpected end of group {} at byte {}",
+                        number, offset
+                    ))
+                }
+                5 => {
+                    let bytes: [u8; 4] = self.take(4)?.try_into().unwrap_or_default();
+                    let value = u32::from_le_bytes(bytes);
+                    let value = WireValue::I32 {
+                        uint: value,
+                        int: value as i32,
+                        float: f32::from_bits(value),
+                    };
+                    (WireType::I32, value)
+                }
+                wire_type => {
+                    return Err(format!(
+                        "Invalid wire type {} at byte {}",
+                        wire_type, offset
+                    ))
+                }
+            };
+
+            fields.push(WireField {
+                number,
+                wire_type,
+                offset,
+                name: field.map(|field| field.full_name().to_string()),
+                value,
+            });
+        }
+
+        match group {
+            Some(number) => Err(format!("Group {} is not terminated", number)),
+            None => Ok(fields),
+        }
+    }
+}
+
+fn varint_value(value: u64) -> WireValue {
+    let sint = (value >> 1) as i64 ^ -((value & 1) as i64);
+    WireValue::Varint {
+        uint: value.to_string(),
+        int: (value as i64).to_string(),
+        sint: sint.to_string(),
+        bool: (value <= 1).then_some(value == 1),
+    }
+}
+
+/// Read a length-delimited value as text and as an embedded message, or only
+/// as what `field` declares it to be
+fn len_value(
+    payload: &[u8],
+    offset: usize,
+    field: Option<&FieldDescriptor>,
+    depth: usize,
+) -> WireValue {
+    let kind = field.map(|field| field.kind());
+    let as_string = matches!(kind, None | Some(Kind::String));
+    let as_message = matches!(kind, None | Some(Kind::Message(_)));
+
+    let string = std::str::from_utf8(payload)
+        .ok()
+        .filter(|text| as_string && is_printable(text))
+        .map(str::to_string);
+    let message = match as_message && depth > 0 && !payload.is_empty() {
+        true => WireReader::new(payload, offset)
+            .fields(field.and_then(message_kind).as_ref(), depth - 1, None)
+            .ok(),
+        false => None,
+    };
+
+    WireValue::Len {
+        hex: hex::encode(payload),
+        string,
+        message,
+    }
+}
+
+fn message_kind(field: &FieldDescriptor) -> Option<MessageDescriptor> {
+    match field.kind() {
+        Kind::Message(message) => Some(message),
+        _ => None,
+    }
+}
+
+fn is_printable(text: &str) -> bool {
+    text.chars()
+        .all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::test_utils::server::echo_descriptor_pool;
+    use prost::Message;
+    use serde_json::json;
+
+    #[test]
+    fn test_bytes_encodings() {
+        assert_eq!(
+            BytesEncoding::Hex.decode("0x08 96 01").unwrap(),
+            vec![0x08, 0x96, 0x01]
+        );
+        assert_eq!(
+            BytesEncoding::Base64.decode("CJYB").unwrap(),
+            vec![0x08, 0x96, 0x01]
+        );
+        assert_eq!(
+            BytesEncoding::Base64.decode("-_8").unwrap(),
+            vec![0xfb, 0xff]
+        );
+        assert!(BytesEncoding::Hex.decode("0g").is_err());
+        assert_eq!(BytesEncoding::Hex.encode(&[0xca, 0xfe]), "cafe");
+    }
+
+    #[test]
+    fn test_decode_scalars_without_schema() {
+        // 1: varint 150, 2: fixed64 -2, 3: fixed32 1.5f, 4: varint 1
+        let mut bytes = vec![0x08, 0x96, 0x01, 0x11];
+        bytes.extend((-2i64).to_le_bytes());
+        bytes.push(0x1d);
+        bytes.extend(1.5f32.to_le_bytes());
+        bytes.extend([0x20, 0x01]);
+
+        let decoded = decode_wire(&bytes, None).unwrap();
+
+        let json = serde_json::to_value(&decoded.fields).unwrap();
+        assert_eq!(
+            json[0],
+            json!({
+                "number": 1,
+                "wire_type": "varint",
+                "offset": 0,
+                "name": null,
+                "value": {"type": "varint", "uint": "150", "int": "150", "sint": "75", "bool": null},
+            })
+        );
+        assert_eq!(json[1]["value"]["int"], "-2");
+        assert_eq!(
+            json[1]["value"]["uint"],
+            u64::MAX.wrapping_sub(1).to_string()
+        );
+        assert_eq!(json[2]["value"]["float"], 1.5);
+        assert_eq!(json[2]["offset"], 12);
+        assert_eq!(json[3]["value"]["bool"], true);
+        assert!(decoded.json.is_none());
+    }
+
+    #[test]
+    fn test_decode_nested_message_and_string_without_schema() {
+        // 1: "hello", 2: {1: 3, 2: "a"}
+        let mut bytes = vec![0x0a, 0x05];
+        bytes.extend(b"hello");
+        bytes.extend([0x12, 0x05, 0x08, 0x03, 0x12, 0x01, b'a']);
+
+        let decoded = decode_wire(&bytes, None).unwrap();
+
+        let json = serde_json::to_value(&decoded.fields).unwrap();
+        assert_eq!(json[0]["value"]["string"], "hello");
+        // "hello" is not a valid message: 'l' ends a group that never started
+        assert!(json[0]["value"]["message"].is_null());
+        let nested = &json[1]["value"]["message"];
+        assert_eq!(nested[0]["value"]["uint"], "3");
+        assert_eq!(nested[1]["value"]["string"], "a");
+        assert_eq!(nested[1]["offset"], 11);
+        assert_eq!(json[1]["value"]["hex"], "0803120161");
+    }
+
+    #[test]
+    fn test_decode_groups() {
+        // 1: group {2: 5}, 3: varint 1
+        let bytes = [0x0b, 0x10, 0x05, 0x0c, 0x18, 0x01];
+
+        let decoded = decode_wire(&bytes, None).unwrap();
+
+        assert_eq!(decoded.fields.len(), 2);
+        assert_eq!(decoded.fields[0].wire_type, WireType::StartGroup);
+        match &decoded.fields[0].value {
+            WireValue::Group { fields } => assert_eq!(fields[0].number, 2),
+            other => panic!("Expected a group, got {:?}", other),
+        }
+        assert_eq!(decoded.fields[1].number, 3);
+    }
+
+    #[test]
+    fn test_decode_invalid_wire_format() {
+        for bytes in [
+            &[0x08][..],
+            &[0x0a, 0x05, 0x01],
+            &[0x0f],
+            &[0x00],
+            &[0x0b, 0x10, 0x05],
+            &[0x0c],
+        ] {
+            assert!(decode_wire(bytes, None).is_err(), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_decode_with_message_type() {
+        let pool = echo_descriptor_pool();
+        let desc = pool.get_message_by_name("test.v1.EchoResponse").unwrap();
+        let timestamp = prost_types::Timestamp {
+            seconds: 1,
+            nanos: 0,
+        };
+        let mut bytes = vec![0x0a, 0x02, b'h', b'i', 0x10, 0x07, 0x1a];
+        bytes.push(timestamp.encoded_len() as u8);
+        bytes.extend(timestamp.encode_to_vec());
+
+        let decoded = decode_wire(&bytes, Some(&desc)).unwrap();
+
+        let names: Vec<_> = decoded.fields.iter().map(|f| f.name.as_deref()).collect();
+        assert_eq!(
+            names,
+            [
+                Some("test.v1.EchoResponse.message"),
+                Some("test.v1.EchoResponse.index"),
+                Some("test.v1.EchoResponse.sent_at"),
+            ]
+        );
+        match &decoded.fields[2].value {
+            WireValue::Len {
+                string, message, ..
+            } => {
+                assert!(string.is_none());
+                let nested = message.as_ref().unwrap();
+                assert_eq!(
+                    nested[0].name.as_deref(),
+                    Some("google.protobuf.Timestamp.seconds")
+                );
+            }
+            other => panic!("Expected a length-delimited value, got {:?}", other),
+        }
+        assert_eq!(
+            decoded.json,
+            Some(json!({"message": "hi", "index": 7, "sentAt": "1970-01-01T00:00:01Z"}))
+        );
+        assert!(decoded.schema_error.is_none());
+    }
+
+    #[test]
+    fn test_decode_with_mismatched_message_type() {
+        let pool = echo_descriptor_pool();
+        let desc = pool.get_message_by_name("test.v1.EchoResponse").unwrap();
+        // Field 1 (a string) sent as a varint
+        let decoded = decode_wire(&[0x08, 0x01], Some(&desc)).unwrap();
+
+        assert_eq!(decoded.fields.len(), 1);
+        assert!(decoded.json.is_none());
+        assert!(decoded.schema_error.is_some());
+    }
+}
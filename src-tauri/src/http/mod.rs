@@ -1,10 +1,16 @@
+pub mod request;
+pub mod response;
+
 use serde::{Deserialize, Serialize};
 use tauri::command;
 
 use crate::auth::AuthType;
+use crate::client::settings::HttpClientSettings;
 use crate::client::HttpClient;
 use crate::error::AppResult;
 use crate::graphql::{GraphQLClient, GraphQLRequest};
+use crate::http::request::HttpRequestSpec;
+use crate::http::response::HttpResponse;
 
 #[derive(Serialize, Deserialize)]
 pub struct ApiResponse {
@@ -24,56 +30,17 @@ fn app_result_to_result(result: AppResult<ApiResponse>) -> Result<ApiResponse, S
     }
 }
 
-// Regular HTTP request commands
-#[command]
-pub async fn plain_request(
-    method: String,
-    url: String,
-    body: Option<serde_json::Value>,
-) -> Result<ApiResponse, String> {
-    let client = HttpClient::new();
-    app_result_to_result(client.execute_request(&method, &url, body).await)
-}
-
-#[command]
-pub async fn basic_auth_request(
-    method: String,
-    url: String,
-    body: Option<serde_json::Value>,
-    username: String,
-    password: String,
-) -> Result<ApiResponse, String> {
-    let client = HttpClient::new();
-    let auth = AuthType::Basic { username, password };
-
-    match client.build_request(&method, &url, body) {
-        Ok(request) => {
-            let request_with_auth = auth.apply_to_request(request);
-            app_result_to_result(client.send_and_parse(request_with_auth).await)
-        }
-        Err(error) => Err(error.to_string()),
-    }
-}
-
+/// Send an HTTP request with any headers, query parameters, body and auth.
+/// `settings` are those of the collection, which the request can override.
 #[command]
-pub async fn bearer_auth_request(
-    method: String,
-    url: String,
-    body: Option<serde_json::Value>,
-    bearer_token: String,
-) -> Result<ApiResponse, String> {
-    let client = HttpClient::new();
-    let auth = AuthType::Bearer {
-        token: bearer_token,
-    };
-
-    match client.build_request(&method, &url, body) {
-        Ok(request) => {
-            let request_with_auth = auth.apply_to_request(request);
-            app_result_to_result(client.send_and_parse(request_with_auth).await)
-        }
-        Err(error) => Err(error.to_string()),
-    }
+pub async fn http_request(
+    request: HttpRequestSpec,
+    settings: Option<HttpClientSettings>,
+) -> Result<HttpResponse, String> {
+    let settings = settings.unwrap_or_default().with_overrides(&request.settings);
+    let client = HttpClient::with_settings(&settings)?;
+    let built = client.build_from_spec(&request).await?;
+    Ok(client.send(built).await?)
 }
 
 // GraphQL request commands
@@ -82,8 +49,9 @@ pub async fn graphql_request(
     url: String,
     query: String,
     variables: Option<serde_json::Value>,
+    settings: Option<HttpClientSettings>,
 ) -> Result<ApiResponse, String> {
-    let client = GraphQLClient::new();
+    let client = GraphQLClient::with_settings(&settings.unwrap_or_default())?;
     let request = GraphQLRequest::new(query, variables);
     app_result_to_result(client.execute(&url, request, None).await)
 }
@@ -95,8 +63,9 @@ pub async fn graphql_basic_auth_request(
     variables: Option<serde_json::Value>,
     username: String,
     password: String,
+    settings: Option<HttpClientSettings>,
 ) -> Result<ApiResponse, String> {
-    let client = GraphQLClient::new();
+    let client = GraphQLClient::with_settings(&settings.unwrap_or_default())?;
     let request = GraphQLRequest::new(query, variables);
     let auth = AuthType::Basic { username, password };
     app_result_to_result(client.execute(&url, request, Some(auth)).await)
@@ -108,8 +77,9 @@ pub async fn graphql_bearer_auth_request(
     query: String,
     variables: Option<serde_json::Value>,
     bearer_token: String,
+    settings: Option<HttpClientSettings>,
 ) -> Result<ApiResponse, String> {
-    let client = GraphQLClient::new();
+    let client = GraphQLClient::with_settings(&settings.unwrap_or_default())?;
     let request = GraphQLRequest::new(query, variables);
     let auth = AuthType::Bearer {
         token: bearer_token,
@@ -118,8 +88,11 @@ pub async fn graphql_bearer_auth_request(
 }
 
 #[command]
-pub async fn graphql_introspection(url: String) -> Result<ApiResponse, String> {
-    let client = GraphQLClient::new();
+pub async fn graphql_introspection(
+    url: String,
+    settings: Option<HttpClientSettings>,
+) -> Result<ApiResponse, String> {
+    let client = GraphQLClient::with_settings(&settings.unwrap_or_default())?;
     app_result_to_result(client.introspect(&url, None).await)
 }
 
@@ -130,8 +103,9 @@ pub async fn graphql_introspection_with_auth(
     token: Option<String>,
     username: Option<String>,
     password: Option<String>,
+    settings: Option<HttpClientSettings>,
 ) -> Result<ApiResponse, String> {
-    let client = GraphQLClient::new();
+    let client = GraphQLClient::with_settings(&settings.unwrap_or_default())?;
     let auth = AuthType::from_params(&auth_type, token, username, password);
 
     let auth_option = match auth {
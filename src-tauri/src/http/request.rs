@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthType;
+use crate::client::settings::HttpClientSettings;
+
+/// Everything needed to send one HTTP request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpRequestSpec {
+    pub method: String,
+    pub url: String,
+    /// Sent in this order; a name may appear several times. A `Content-Type`
+    /// given here replaces the one implied by the body.
+    #[serde(default)]
+    pub headers: Vec<KeyValue>,
+    /// Appended to the query string of `url`
+    #[serde(default)]
+    pub query: Vec<KeyValue>,
+    #[serde(default)]
+    pub body: RequestBody,
+    #[serde(default)]
+    pub auth: AuthType,
+    /// Client settings overriding those of the collection
+    #[serde(default)]
+    pub settings: HttpClientSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyValue {
+    pub name: String,
+    pub value: String,
+}
+
+impl KeyValue {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RequestBody {
+    #[default]
+    None,
+    Json {
+        value: serde_json::Value,
+    },
+    /// Text sent as is, `text/plain` unless another content type is given
+    Text {
+        content: String,
+        content_type: Option<String>,
+    },
+    /// `application/x-www-form-urlencoded` fields
+    Form {
+        fields: Vec<KeyValue>,
+    },
+    /// `multipart/form-data` parts
+    Multipart {
+        parts: Vec<MultipartPart>,
+    },
+    /// Contents of a local file, `application/octet-stream` unless another
+    /// content type is given
+    File {
+        path: String,
+        content_type: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultipartPart {
+    pub name: String,
+    #[serde(flatten)]
+    pub value: MultipartValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MultipartValue {
+    Text {
+        value: String,
+    },
+    /// A local file, uploaded under its own name unless `file_name` is given
+    File {
+        path: String,
+        file_name: Option<String>,
+        content_type: Option<String>,
+    },
+}
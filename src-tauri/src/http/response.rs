@@ -0,0 +1,326 @@
+use base64::{engine::general_purpose, Engine};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use mime::Mime;
+use serde::{Deserialize, Serialize};
+
+use crate::http::request::KeyValue;
+
+/// Everything received for one HTTP request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpResponse {
+    /// Whether the status is 2xx
+    pub success: bool,
+    pub status: u16,
+    /// Canonical reason of the status, e.g. "Not Found"
+    pub status_text: Option<String>,
+    /// e.g. "HTTP/1.1"
+    pub http_version: String,
+    /// URL the response came from, after following redirects
+    pub url: String,
+    /// In the order received; the values of a repeated name come together
+    pub headers: Vec<KeyValue>,
+    /// Body decoded according to its content type
+    pub body: ResponseBody,
+    /// Body exactly as received, base64 encoded; `None` when empty. Keeps
+    /// what decoding loses, like the key order and number precision of JSON
+    /// or the bytes of a text in another charset.
+    pub raw_body: Option<String>,
+    pub size_bytes: u64,
+    pub timings: ResponseTimings,
+}
+
+/// Elapsed time of each phase of a request, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseTimings {
+    /// Host name lookup of the server, or of the proxy; `None` for IP
+    /// addresses and reused connections
+    pub dns_ms: Option<f64>,
+    /// Opening the TCP connection, to the proxy if any; `None` for reused
+    /// connections
+    pub connect_ms: Option<f64>,
+    /// TLS handshake; `None` for plain HTTP and reused connections
+    pub tls_ms: Option<f64>,
+    /// From sending the request to receiving the response headers, opening
+    /// the connection excluded
+    pub ttfb_ms: f64,
+    /// Reading the body
+    pub download_ms: f64,
+    /// Sum of the phases above
+    pub total_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseBody {
+    Empty,
+    Json {
+        value: serde_json::Value,
+    },
+    /// Text decoded from `charset`, including JSON that does not parse
+    Text {
+        text: String,
+        format: TextFormat,
+        charset: String,
+    },
+    /// Content only available as the raw body
+    Binary {
+        mime_type: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextFormat {
+    Plain,
+    Html,
+    Xml,
+}
+
+impl ResponseBody {
+    /// Decode `bytes` as described by the `Content-Type` header. Without one,
+    /// bytes that are valid UTF-8 are taken for text, or JSON if they parse.
+    pub fn decode(content_type: Option<&str>, bytes: &[u8]) -> Self {
+        if bytes.is_empty() {
+            return ResponseBody::Empty;
+        }
+
+        let mime = content_type.and_then(|value| value.parse::<Mime>().ok());
+        let kind = match &mime {
+            Some(mime) => BodyKind::of(mime),
+            None if std::str::from_utf8(bytes).is_ok_and(|text| !text.contains('\0')) => {
+                BodyKind::Json
+            }
+            None => BodyKind::Binary,
+        };
+
+        let format = match kind {
+            BodyKind::Binary => {
+                return ResponseBody::Binary {
+                    mime_type: mime.map_or_else(
+                        || mime::APPLICATION_OCTET_STREAM.to_string(),
+                        |mime| mime.essence_str().to_string(),
+                    ),
+                }
+            }
+            BodyKind::Json => TextFormat::Plain,
+            BodyKind::Text(format) => format,
+        };
+
+        let declared = mime
+            .as_ref()
+            .and_then(|mime| mime.get_param(mime::CHARSET))
+            .map(|charset| charset.as_str().to_string());
+        let encoding = detect_charset(bytes, declared.as_deref(), format);
+        let (text, encoding, _) = encoding.decode(bytes);
+
+        if kind == BodyKind::Json {
+            if let Ok(value) = serde_json::from_str(&text) {
+                return ResponseBody::Json { value };
+            }
+        }
+        ResponseBody::Text {
+            text: text.into_owned(),
+            format,
+            charset: encoding.name().to_string(),
+        }
+    }
+}
+
+/// Encode a body exactly as received
+pub fn raw_body(bytes: &[u8]) -> String {
+    general_purpose::STANDARD.encode(bytes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyKind {
+    Json,
+    Text(TextFormat),
+    Binary,
+}
+
+impl BodyKind {
+    fn of(mime: &Mime) -> Self {
+        let subtype = mime.subtype().as_str();
+        let suffix = mime.suffix().map(|suffix| suffix.as_str());
+
+        if subtype == "json" || suffix == Some("json") {
+            BodyKind::Json
+        } else if subtype == "html" || subtype == "xhtml" || suffix == Some("html") {
+            BodyKind::Text(TextFormat::Html)
+        } else if subtype == "xml" || suffix == Some("xml") {
+            BodyKind::Text(TextFormat::Xml)
+        } else if mime.type_() == mime::TEXT
+            || matches!(
+                mime.essence_str(),
+                "application/javascript"
+                    | "application/ecmascript"
+                    | "application/x-www-form-urlencoded"
+                    | "application/graphql"
+                    | "application/yaml"
+                    | "application/x-yaml"
+                    | "application/sql"
+            )
+        {
+            BodyKind::Text(TextFormat::Plain)
+        } else {
+            BodyKind::Binary
+        }
+    }
+}
+
+/// Pick the encoding of a text body: a byte order mark first, then the
+/// charset of the content type, then a declaration in the document itself.
+/// Undeclared bytes are UTF-8 when valid, Windows-1252 otherwise.
+fn detect_charset(bytes: &[u8], declared: Option<&str>, format: TextFormat) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    let in_document = match format {
+        TextFormat::Html => declared_in_document(bytes, "charset="),
+        TextFormat::Xml => declared_in_document(bytes, "encoding="),
+        TextFormat::Plain => None,
+    };
+    if let Some(encoding) = declared
+        .or(in_document.as_deref())
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+    {
+        return encoding;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+/// Value following `attribute` near the start of a document, as in
+/// `<meta charset="...">` or `<?xml version="1.0" encoding="..."?>`
+fn declared_in_document(bytes: &[u8], attribute: &str) -> Option<String> {
+    let head = &bytes[..bytes.len().min(1024)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let value = &head[head.find(attribute)? + attribute.len()..];
+    let value = value.trim_start_matches(['"', '\'']);
+    let end = value
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')))
+        .unwrap_or(value.len());
+    (end > 0).then(|| value[..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn as_text(body: ResponseBody) -> (String, TextFormat, String) {
+        match body {
+            ResponseBody::Text {
+                text,
+                format,
+                charset,
+            } => (text, format, charset),
+            other => panic!("Expected a text body, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_json() {
+        let body = ResponseBody::decode(Some("application/problem+json"), br#"{"ok":true}"#);
+        assert_eq!(
+            body,
+            ResponseBody::Json {
+                value: json!({"ok": true})
+            }
+        );
+
+        // JSON that does not parse is still text
+        let (text, format, _) = as_text(ResponseBody::decode(Some("application/json"), b"{oops"));
+        assert_eq!(text, "{oops");
+        assert_eq!(format, TextFormat::Plain);
+
+        // Plain text is not parsed even when it would be valid JSON
+        let (text, ..) = as_text(ResponseBody::decode(Some("text/plain"), b"42"));
+        assert_eq!(text, "42");
+    }
+
+    #[test]
+    fn test_decode_markup() {
+        let (_, format, charset) = as_text(ResponseBody::decode(
+            Some("application/soap+xml; charset=utf-8"),
+            b"<Envelope/>",
+        ));
+        assert_eq!(format, TextFormat::Xml);
+        assert_eq!(charset, "UTF-8");
+
+        let (_, format, _) = as_text(ResponseBody::decode(Some("text/html"), b"<p>hi</p>"));
+        assert_eq!(format, TextFormat::Html);
+    }
+
+    #[test]
+    fn test_decode_charsets() {
+        // "café" in Latin-1
+        let latin1 = b"caf\xe9";
+
+        let (text, _, charset) = as_text(ResponseBody::decode(
+            Some("text/plain; charset=ISO-8859-1"),
+            latin1,
+        ));
+        assert_eq!(text, "café");
+        assert_eq!(charset, "windows-1252");
+
+        let xml = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><name>caf\xe9</name>";
+        let (text, ..) = as_text(ResponseBody::decode(Some("application/xml"), xml));
+        assert!(text.contains("café"));
+
+        let html = b"<html><head><meta charset=\"shift_jis\"></head><body>\x93\xfa</body></html>";
+        let (text, _, charset) = as_text(ResponseBody::decode(Some("text/html"), html));
+        assert!(text.contains('日'));
+        assert_eq!(charset, "Shift_JIS");
+
+        // A byte order mark wins over the declared charset
+        let (text, _, charset) = as_text(ResponseBody::decode(
+            Some("text/plain; charset=iso-8859-1"),
+            "\u{feff}café".as_bytes(),
+        ));
+        assert_eq!(text, "café");
+        assert_eq!(charset, "UTF-8");
+
+        // Undeclared and not UTF-8
+        let (text, ..) = as_text(ResponseBody::decode(Some("text/plain"), latin1));
+        assert_eq!(text, "café");
+    }
+
+    #[test]
+    fn test_decode_binary() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        assert_eq!(
+            ResponseBody::decode(Some("image/png"), png),
+            ResponseBody::Binary {
+                mime_type: "image/png".to_string()
+            }
+        );
+        assert_eq!(
+            ResponseBody::decode(None, png),
+            ResponseBody::Binary {
+                mime_type: "application/octet-stream".to_string()
+            }
+        );
+        assert_eq!(raw_body(b"\x00\xff"), "AP8=");
+    }
+
+    #[test]
+    fn test_decode_without_content_type() {
+        assert_eq!(ResponseBody::decode(None, b""), ResponseBody::Empty);
+        assert_eq!(
+            ResponseBody::decode(None, b"[1, 2]"),
+            ResponseBody::Json {
+                value: json!([1, 2])
+            }
+        );
+
+        let (text, format, _) = as_text(ResponseBody::decode(None, b"OK"));
+        assert_eq!(text, "OK");
+        assert_eq!(format, TextFormat::Plain);
+    }
+}
@@ -1,10 +1,42 @@
 use super::*;
+use base64::{engine::general_purpose, Engine};
 use httpmock::Method::{DELETE, GET, PATCH, POST, PUT};
 use httpmock::MockServer;
-use reqwest::Client;
 use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-/// Tests a GET request using plain_request against a mock server.
+use crate::auth::AuthType;
+use crate::client::proxy::ProxySettings;
+use crate::client::settings::RedirectPolicy;
+use crate::http::request::{HttpRequestSpec, KeyValue, MultipartPart, MultipartValue, RequestBody};
+use crate::http::response::{ResponseBody, TextFormat};
+
+/// Settings reaching the mock servers directly, whatever the proxy
+/// environment variables
+fn direct() -> Option<HttpClientSettings> {
+    Some(HttpClientSettings {
+        proxy: Some(ProxySettings::direct()),
+        ..Default::default()
+    })
+}
+
+/// Request with an optional JSON body and nothing else, sent without proxy
+fn json_request(method: &str, url: String, body: Option<serde_json::Value>) -> HttpRequestSpec {
+    HttpRequestSpec {
+        method: method.to_string(),
+        url,
+        headers: Vec::new(),
+        query: Vec::new(),
+        body: match body {
+            Some(value) => RequestBody::Json { value },
+            None => RequestBody::None,
+        },
+        auth: AuthType::None,
+        settings: direct().unwrap(),
+    }
+}
+
+/// Tests a GET request using http_request against a mock server.
 /// The expected result is a successful response (HTTP 200).
 #[tokio::test]
 async fn test_plain_get_request_success() {
@@ -15,13 +47,13 @@ async fn test_plain_get_request_success() {
         then.status(200).body("OK");
     });
 
-    let result = plain_request("GET".into(), server.url("/").into(), None).await;
+    let result = http_request(json_request("GET", server.url("/"), None), None).await;
 
     assert!(result.is_ok());
     mock.assert();
 }
 
-/// Tests a POST request with a JSON body using plain_request against a mock server.
+/// Tests a POST request with a JSON body using http_request against a mock server.
 /// The expected result is a successful response (HTTP 201).
 #[tokio::test]
 async fn test_plain_post_request_success() {
@@ -44,13 +76,13 @@ async fn test_plain_post_request_success() {
         "userId": 1
     });
 
-    let result = plain_request("POST".into(), server.url("/posts").into(), Some(body)).await;
+    let result = http_request(json_request("POST", server.url("/posts"), Some(body)), None).await;
 
     assert!(result.is_ok());
     mock.assert();
 }
 
-/// Tests a PUT request with a JSON body using plain_request against a mock server.
+/// Tests a PUT request with a JSON body using http_request against a mock server.
 /// The expected result is a successful response (HTTP 200).
 #[tokio::test]
 async fn test_plain_put_request_success() {
@@ -75,13 +107,17 @@ async fn test_plain_put_request_success() {
         "userId": 1
     });
 
-    let result = plain_request("PUT".into(), server.url("/posts/1").into(), Some(body)).await;
+    let result = http_request(
+        json_request("PUT", server.url("/posts/1"), Some(body)),
+        None,
+    )
+    .await;
 
     assert!(result.is_ok());
     mock.assert();
 }
 
-/// Tests a DELETE request using plain_request against a mock server.
+/// Tests a DELETE request using http_request against a mock server.
 /// The expected result is a successful response (HTTP 204).
 #[tokio::test]
 async fn test_plain_delete_request_success() {
@@ -92,13 +128,13 @@ async fn test_plain_delete_request_success() {
         then.status(204);
     });
 
-    let result = plain_request("DELETE".into(), server.url("/posts/1").into(), None).await;
+    let result = http_request(json_request("DELETE", server.url("/posts/1"), None), None).await;
 
     assert!(result.is_ok());
     mock.assert();
 }
 
-/// Tests a PATCH request with a JSON body using plain_request against a mock server.
+/// Tests a PATCH request with a JSON body using http_request against a mock server.
 /// The expected result is a successful response (HTTP 200).
 #[tokio::test]
 async fn test_plain_patch_request_success() {
@@ -123,62 +159,650 @@ async fn test_plain_patch_request_success() {
         "userId": 1
     });
 
-    let result = plain_request("PATCH".into(), server.url("/posts/1").into(), Some(body)).await;
+    let result = http_request(
+        json_request("PATCH", server.url("/posts/1"), Some(body)),
+        None,
+    )
+    .await;
 
     assert!(result.is_ok());
     mock.assert();
 }
 
-/// Tests if a GET request using Basic Authentication hits a mock server
-/// with the correct Authorization header. The expected response is HTTP 200
-/// with body "Authorized".
+/// Tests that tagged Basic auth from the frontend reaches the server as the
+/// Authorization header.
 #[tokio::test]
 async fn test_basic_auth_request_hits_server() {
     let server = MockServer::start();
 
     let mock = server.mock(|when, then| {
         when.method(GET)
+            .path("/auth")
             .header("Authorization", "Basic dXNlcjpwYXNz");
         then.status(200).body("Authorized");
     });
 
-    let client = Client::new();
-    let url = &format!("{}/auth", server.url(""));
+    let mut request = json_request("GET", server.url("/auth"), None);
+    request.auth = serde_json::from_value(json!({
+        "type": "basic",
+        "username": "user",
+        "password": "pass",
+    }))
+    .unwrap();
+
+    let response = http_request(request, None).await.unwrap();
+
+    assert_eq!(response.status, 200);
+    mock.assert();
+}
+
+/// Tests that tagged Bearer auth from the frontend reaches the server as the
+/// Authorization header.
+#[tokio::test]
+async fn test_bearer_auth_request_hits_server() {
+    let server = MockServer::start();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/auth")
+            .header("Authorization", "Bearer abc123");
+        then.status(200).body("Bearer Authorized");
+    });
+
+    let mut request = json_request("GET", server.url("/auth"), None);
+    request.auth = serde_json::from_value(json!({"type": "bearer", "token": "abc123"})).unwrap();
+
+    let response = http_request(request, None).await.unwrap();
+
+    assert_eq!(response.status, 200);
+    mock.assert();
+}
+
+/// Tests that headers are sent in order with repeated names kept, and that
+/// query parameters are appended to the URL.
+#[tokio::test]
+async fn test_http_request_sends_headers_and_query() {
+    let server = MockServer::start();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/search")
+            .query_param("lang", "en")
+            .query_param("q", "a b")
+            .header("x-tag", "one")
+            .header("x-tag", "two");
+        then.status(200).json_body(json!({"ok": true}));
+    });
+
+    let mut request = json_request("GET", server.url("/search?lang=en"), None);
+    request.headers = vec![KeyValue::new("X-Tag", "one"), KeyValue::new("X-Tag", "two")];
+    request.query = vec![KeyValue::new("q", "a b")];
+
+    let response = http_request(request, None).await.unwrap();
+
+    assert!(response.success);
+    assert_eq!(
+        response.body,
+        ResponseBody::Json {
+            value: json!({"ok": true})
+        }
+    );
+    mock.assert();
+}
+
+/// Tests a spec as sent by the frontend: tagged body and auth.
+#[tokio::test]
+async fn test_http_request_from_frontend_json() {
+    let server = MockServer::start();
+
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/posts")
+            .header("Authorization", "Basic dXNlcjpwYXNz")
+            .header("Content-Type", "application/json")
+            .json_body(json!({"title": "foo"}));
+        then.status(201).json_body(json!({"id": 1}));
+    });
+
+    let request: HttpRequestSpec = serde_json::from_value(json!({
+        "method": "POST",
+        "url": server.url("/posts"),
+        "body": {"type": "json", "value": {"title": "foo"}},
+        "auth": {"type": "basic", "username": "user", "password": "pass"},
+    }))
+    .unwrap();
+
+    let response = http_request(request, None).await.unwrap();
+
+    assert!(response.success);
+    mock.assert();
+}
+
+/// Tests a raw text body with its own content type, and a Content-Type
+/// header replacing the one of a JSON body.
+#[tokio::test]
+async fn test_http_request_text_body_and_content_type_override() {
+    let server = MockServer::start();
+
+    let xml = server.mock(|when, then| {
+        when.method(POST)
+            .path("/xml")
+            .header("Content-Type", "application/xml")
+            .body("<order id=\"1\"/>");
+        then.status(200).json_body(json!({}));
+    });
+    let json_api = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/json-api")
+            .header("Content-Type", "application/vnd.api+json")
+            .json_body(json!({"data": null}));
+        then.status(200).json_body(json!({}));
+    });
+
+    let mut request = json_request("POST", server.url("/xml"), None);
+    request.body = RequestBody::Text {
+        content: "<order id=\"1\"/>".to_string(),
+        content_type: Some("application/xml".to_string()),
+    };
+    assert!(http_request(request, None).await.unwrap().success);
+
+    let mut request = json_request(
+        "PATCH",
+        server.url("/json-api"),
+        Some(json!({"data": null})),
+    );
+    request.headers = vec![KeyValue::new("Content-Type", "application/vnd.api+json")];
+    assert!(http_request(request, None).await.unwrap().success);
+
+    xml.assert();
+    json_api.assert();
+}
+
+/// Tests an x-www-form-urlencoded body.
+#[tokio::test]
+async fn test_http_request_form_body() {
+    let server = MockServer::start();
+
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/login")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body("user=jane+doe&remember=1");
+        then.status(200).json_body(json!({}));
+    });
+
+    let mut request = json_request("POST", server.url("/login"), None);
+    request.body = RequestBody::Form {
+        fields: vec![
+            KeyValue::new("user", "jane doe"),
+            KeyValue::new("remember", "1"),
+        ],
+    };
+
+    assert!(http_request(request, None).await.unwrap().success);
+    mock.assert();
+}
+
+/// Tests multipart and binary bodies read from files.
+#[tokio::test]
+async fn test_http_request_multipart_and_file_bodies() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("report.csv");
+    std::fs::write(&path, "a,b\n1,2\n").unwrap();
+    let path = path.to_string_lossy().to_string();
+
+    let multipart = server.mock(|when, then| {
+        when.method(POST)
+            .path("/upload")
+            .header_exists("Content-Type")
+            .body_contains("name=\"note\"")
+            .body_contains("quarterly")
+            .body_contains("name=\"file\"; filename=\"report.csv\"")
+            .body_contains("Content-Type: text/csv")
+            .body_contains("a,b\n1,2\n");
+        then.status(200).json_body(json!({}));
+    });
+    let binary = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/blob")
+            .header("Content-Type", "application/octet-stream")
+            .body("a,b\n1,2\n");
+        then.status(200).json_body(json!({}));
+    });
+
+    let mut request = json_request("POST", server.url("/upload"), None);
+    request.body = RequestBody::Multipart {
+        parts: vec![
+            MultipartPart {
+                name: "note".to_string(),
+                value: MultipartValue::Text {
+                    value: "quarterly".to_string(),
+                },
+            },
+            MultipartPart {
+                name: "file".to_string(),
+                value: MultipartValue::File {
+                    path: path.clone(),
+                    file_name: None,
+                    content_type: Some("text/csv".to_string()),
+                },
+            },
+        ],
+    };
+    assert!(http_request(request, None).await.unwrap().success);
+
+    let mut request = json_request("PUT", server.url("/blob"), None);
+    request.body = RequestBody::File {
+        path,
+        content_type: None,
+    };
+    assert!(http_request(request, None).await.unwrap().success);
+
+    multipart.assert();
+    binary.assert();
+}
+
+/// Tests that invalid headers and unreadable files are reported without
+/// sending anything.
+#[tokio::test]
+async fn test_http_request_reports_invalid_spec() {
+    let mut request = json_request("GET", "http://127.0.0.1:9/".to_string(), None);
+    request.headers = vec![KeyValue::new("bad header", "x")];
+    let error = http_request(request, None).await.unwrap_err();
+    assert!(error.contains("Invalid header 'bad header'"));
+
+    let mut request = json_request("POST", "http://127.0.0.1:9/".to_string(), None);
+    request.body = RequestBody::File {
+        path: "/nonexistent/body.bin".to_string(),
+        content_type: None,
+    };
+    let error = http_request(request, None).await.unwrap_err();
+    assert!(error.contains("Failed to read"));
+}
+
+/// Tests that an error status comes back with its JSON body, headers and size.
+#[tokio::test]
+async fn test_http_request_reports_status_and_headers() {
+    let server = MockServer::start();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/missing");
+        then.status(404)
+            .header("content-type", "application/json")
+            .header("set-cookie", "a=1")
+            .header("set-cookie", "b=2")
+            .body(r#"{"error":"not found"}"#);
+    });
+
+    let response = http_request(json_request("GET", server.url("/missing"), None), None)
+        .await
+        .unwrap();
+
+    assert!(!response.success);
+    assert_eq!(response.status, 404);
+    assert_eq!(response.status_text.as_deref(), Some("Not Found"));
+    assert_eq!(response.http_version, "HTTP/1.1");
+    assert_eq!(
+        response.body,
+        ResponseBody::Json {
+            value: json!({"error": "not found"})
+        }
+    );
+    assert_eq!(response.size_bytes, 21);
+
+    let cookies: Vec<&str> = response
+        .headers
+        .iter()
+        .filter(|header| header.name == "set-cookie")
+        .map(|header| header.value.as_str())
+        .collect();
+    assert_eq!(cookies, ["a=1", "b=2"]);
+}
+
+/// Tests that the raw body of a JSON response is the text as received, with
+/// the formatting, key order and number precision that parsing loses.
+#[tokio::test]
+async fn test_http_request_keeps_raw_json_body() {
+    let server = MockServer::start();
+    let text = "{\n  \"zeta\": 1,\n  \"alpha\": 12345678901234567890123\n}";
+
+    server.mock(|when, then| {
+        when.method(GET).path("/big");
+        then.status(200)
+            .header("content-type", "application/json")
+            .body(text);
+    });
+
+    let response = http_request(json_request("GET", server.url("/big"), None), None)
+        .await
+        .unwrap();
+
+    assert!(matches!(response.body, ResponseBody::Json { .. }));
+    let raw_body = general_purpose::STANDARD
+        .decode(response.raw_body.unwrap())
+        .unwrap();
+    assert_eq!(String::from_utf8(raw_body).unwrap(), text);
+
+    server.mock(|when, then| {
+        when.method(GET).path("/empty");
+        then.status(204);
+    });
+    let response = http_request(json_request("GET", server.url("/empty"), None), None)
+        .await
+        .unwrap();
+    assert_eq!(response.body, ResponseBody::Empty);
+    assert_eq!(response.raw_body, None);
+}
+
+/// Tests that bodies other than JSON are decoded as text or kept as binary,
+/// whatever the status.
+#[tokio::test]
+async fn test_http_request_decodes_non_json_bodies() {
+    let server = MockServer::start();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/fault");
+        then.status(500)
+            .header("content-type", "text/xml; charset=utf-8")
+            .body("<fault>down</fault>");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/logo");
+        then.status(200)
+            .header("content-type", "image/png")
+            .body(b"\x89PNG\r\n\x1a\n");
+    });
+
+    let response = http_request(json_request("GET", server.url("/fault"), None), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 500);
+    assert_eq!(
+        response.body,
+        ResponseBody::Text {
+            text: "<fault>down</fault>".to_string(),
+            format: TextFormat::Xml,
+            charset: "UTF-8".to_string(),
+        }
+    );
+    assert_eq!(
+        response.raw_body.as_deref(),
+        Some("PGZhdWx0PmRvd248L2ZhdWx0Pg==")
+    );
+
+    let response = http_request(json_request("GET", server.url("/logo"), None), None)
+        .await
+        .unwrap();
+    assert!(response.success);
+    assert_eq!(
+        response.body,
+        ResponseBody::Binary {
+            mime_type: "image/png".to_string()
+        }
+    );
+    assert_eq!(response.raw_body.as_deref(), Some("iVBORw0KGgo="));
+}
+
+/// Tests that the URL reported is the one reached after redirects, and that
+/// the lookup of the host and the connect are timed apart.
+#[tokio::test]
+async fn test_http_request_reports_final_url_and_timings() {
+    let server = MockServer::start();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/old");
+        then.status(302).header("location", "/new");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/new");
+        then.status(200).body("moved");
+    });
+
+    let base_url = format!("http://localhost:{}", server.port());
+    let response = http_request(json_request("GET", format!("{}/old", base_url), None), None)
+        .await
+        .unwrap();
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.url, format!("{}/new", base_url));
+    assert_eq!(response.size_bytes, 5);
+
+    let timings = response.timings;
+    assert!(timings.dns_ms.is_some());
+    assert!(timings.connect_ms.is_some());
+    assert!(timings.tls_ms.is_none());
+    let phases = timings.dns_ms.unwrap()
+        + timings.connect_ms.unwrap()
+        + timings.ttfb_ms
+        + timings.download_ms;
+    assert!((timings.total_ms - phases).abs() < 1e-6);
+}
+
+/// Tests that redirects are returned as they are, or followed up to a limit.
+#[tokio::test]
+async fn test_http_request_redirect_settings() {
+    let server = MockServer::start();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/a");
+        then.status(301).header("location", "/b");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/b");
+        then.status(302).header("location", "/c");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/c");
+        then.status(200).body("end");
+    });
+
+    let settings = |redirects| HttpClientSettings {
+        redirects: Some(redirects),
+        ..Default::default()
+    };
+
+    let response = http_request(
+        json_request("GET", server.url("/a"), None),
+        Some(settings(RedirectPolicy::Never)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status, 301);
+    assert_eq!(response.url, server.url("/a"));
 
-    let response = client
-        .get(url)
-        .basic_auth("user", Some("pass"))
-        .send()
+    let error = http_request(
+        json_request("GET", server.url("/a"), None),
+        Some(settings(RedirectPolicy::Follow { max: 1 })),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("redirect"), "{}", error);
+
+    // The request overrides the collection
+    let mut request = json_request("GET", server.url("/a"), None);
+    request.settings.redirects = Some(RedirectPolicy::Follow { max: 2 });
+    let response = http_request(request, Some(settings(RedirectPolicy::Never)))
         .await
         .unwrap();
+    assert_eq!(response.status, 200);
+}
+
+/// Tests the user agent and default headers, which headers of the request
+/// replace.
+#[tokio::test]
+async fn test_http_request_default_headers() {
+    let server = MockServer::start();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .header("user-agent", "team-agent/2")
+            .header("x-team", "core")
+            .header("x-env", "staging");
+        then.status(200);
+    });
+
+    let collection = HttpClientSettings {
+        user_agent: Some("team-agent/2".to_string()),
+        default_headers: vec![
+            KeyValue::new("X-Team", "core"),
+            KeyValue::new("X-Env", "production"),
+        ],
+        ..Default::default()
+    };
+    let mut request = json_request("GET", server.url("/"), None);
+    request.headers = vec![KeyValue::new("X-Env", "staging")];
 
-    assert_eq!(response.status(), 200);
-    assert_eq!(response.text().await.unwrap(), "Authorized");
+    let response = http_request(request, Some(collection)).await.unwrap();
 
+    assert!(response.success);
     mock.assert();
 }
 
-/// Tests if a GET request using Bearer Authentication hits a mock server
-/// with the correct Authorization header. The expected response is HTTP 200
-/// with body "Bearer Authorized".
+/// Tests the read timeout and the limit on the response size.
 #[tokio::test]
-async fn test_bearer_auth_request_hits_server() {
+async fn test_http_request_read_timeout_and_max_size() {
+    let server = MockServer::start();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200)
+            .delay(std::time::Duration::from_millis(200))
+            .body("late");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/large");
+        then.status(200).body("x".repeat(2048));
+    });
+
+    let settings = HttpClientSettings {
+        read_timeout_ms: Some(50),
+        max_response_bytes: Some(1024),
+        ..Default::default()
+    };
+
+    // Headers that never come in time
+    let error = http_request(
+        json_request("GET", server.url("/slow"), None),
+        Some(settings.clone()),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        error.contains("Timed out waiting for the response"),
+        "{}",
+        error
+    );
+
+    // Headers right away, then a body that stalls
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let stalled = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 1024];
+        let _ = socket.read(&mut request).await;
+        let head = "HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\nla";
+        socket.write_all(head.as_bytes()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        let _ = socket.write_all(b"te").await;
+    });
+
+    let error = http_request(json_request("GET", stalled, None), Some(settings.clone()))
+        .await
+        .unwrap_err();
+    assert!(error.contains("Timed out"), "{}", error);
+
+    let error = http_request(
+        json_request("GET", server.url("/large"), None),
+        Some(settings),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("1024 bytes"), "{}", error);
+}
+
+/// Tests that zstd is advertised and decompressed unless disabled.
+#[tokio::test]
+async fn test_http_request_zstd_decompression() {
     let server = MockServer::start();
+    let compressed = zstd::encode_all(&br#"{"compressed":true}"#[..], 0).unwrap();
 
     let mock = server.mock(|when, then| {
-        when.method(GET).header("Authorization", "Bearer abc123");
-        then.status(200).body("Bearer Authorized");
+        when.method(GET).header("accept-encoding", "gzip, br, zstd");
+        then.status(200)
+            .header("content-type", "application/json")
+            .header("content-encoding", "zstd")
+            .body(compressed.clone());
     });
 
-    let client = Client::new();
-    let url = &format!("{}/auth", server.url(""));
+    let response = http_request(json_request("GET", server.url("/"), None), None)
+        .await
+        .unwrap();
 
-    let response = client.get(url).bearer_auth("abc123").send().await.unwrap();
+    assert_eq!(
+        response.body,
+        ResponseBody::Json {
+            value: json!({"compressed": true})
+        }
+    );
+    assert!(response
+        .headers
+        .iter()
+        .all(|header| header.name != "content-encoding"));
+    mock.assert();
 
-    assert_eq!(response.status(), 200);
-    assert_eq!(response.text().await.unwrap(), "Bearer Authorized");
+    let mut request = json_request("GET", server.url("/"), None);
+    request.settings.zstd = Some(false);
+    let response = http_request(request, None).await.unwrap();
+    // Not advertised any more, so the mock does not match
+    assert_eq!(response.status, 404);
+}
 
+/// Tests that requests go through the proxy unless its bypass rules match.
+#[tokio::test]
+async fn test_http_request_through_proxy() {
+    let proxy = MockServer::start();
+
+    // The proxy receives the request for the target host with credentials
+    let mock = proxy.mock(|when, then| {
+        when.method(GET)
+            .path("/users")
+            .header("host", "api.example.test")
+            .header("proxy-authorization", "Basic amFuZTpzZWNyZXQ=");
+        then.status(200).body("proxied");
+    });
+
+    let mut request = json_request("GET", "http://api.example.test/users".to_string(), None);
+    request.settings.proxy = Some(ProxySettings {
+        url: Some(proxy.base_url()),
+        username: Some("jane".to_string()),
+        password: Some("secret".to_string()),
+        use_env: false,
+        ..Default::default()
+    });
+    let response = http_request(request, None).await.unwrap();
+
+    assert_eq!(response.status, 200);
     mock.assert();
+
+    // Bypassed hosts are reached directly, even with a proxy that is down
+    let server = MockServer::start();
+    let bypassed = server.mock(|when, then| {
+        when.method(GET).path("/");
+        then.status(204);
+    });
+    let mut request = json_request("GET", server.url("/"), None);
+    request.settings.proxy = Some(ProxySettings {
+        url: Some("http://127.0.0.1:9".to_string()),
+        bypass: vec!["127.0.0.0/8".to_string()],
+        use_env: false,
+        ..Default::default()
+    });
+
+    let response = http_request(request, None).await.unwrap();
+
+    assert_eq!(response.status, 204);
+    bypassed.assert();
 }
 
 // Graphql tests
@@ -208,6 +832,7 @@ async fn test_graphql_request_success() {
         server.url("/graphql").into(),
         "query { users { id name } }".into(),
         None,
+        direct(),
     )
     .await;
 
@@ -253,6 +878,7 @@ async fn test_graphql_request_with_variables() {
         server.url("/graphql").into(),
         "query GetUser($userId: ID!, $limit: Int) { user(id: $userId) { posts(limit: $limit) { title } } }".into(),
         Some(variables),
+        direct(),
     ).await;
 
     assert!(result.is_ok());
@@ -290,6 +916,7 @@ async fn test_graphql_basic_auth_request() {
         None,
         "user".into(),
         "pass".into(),
+        direct(),
     )
     .await;
 
@@ -327,6 +954,7 @@ async fn test_graphql_bearer_auth_request() {
         "query { me { id name } }".into(),
         None,
         "token123".into(),
+        direct(),
     )
     .await;
 
@@ -362,6 +990,7 @@ async fn test_graphql_error_response() {
         server.url("/graphql").into(),
         "query { invalidField }".into(),
         None,
+        direct(),
     )
     .await;
 
@@ -412,6 +1041,7 @@ async fn test_graphql_mutation() {
         "mutation CreateUser($input: UserInput!) { createUser(input: $input) { id name email } }"
             .into(),
         Some(variables),
+        direct(),
     )
     .await;
 
@@ -443,7 +1073,7 @@ async fn test_graphql_introspection_success() {
             }));
     });
 
-    let result = super::graphql_introspection(server.url("/graphql")).await;
+    let result = super::graphql_introspection(server.url("/graphql"), direct()).await;
 
     assert!(result.is_ok());
     let response = result.unwrap();
@@ -479,6 +1109,7 @@ async fn test_graphql_introspection_with_bearer_auth() {
         Some("test-token".to_string()),
         None,
         None,
+        direct(),
     )
     .await;
 
@@ -12,21 +12,37 @@ fn main() {
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(grpc::state::GrpcState::default())
+        .manage(grpc::streaming::GrpcStreamSessions::default())
         .invoke_handler(tauri::generate_handler![
-            http::plain_request,
-            http::basic_auth_request,
-            http::bearer_auth_request,
+            http::http_request,
             http::graphql_request,
             http::graphql_basic_auth_request,
             http::graphql_introspection,
             http::graphql_introspection_with_auth,
             grpc::commands::grpc_unary_request,
             grpc::commands::grpc_server_streaming_request,
+            grpc::commands::grpc_open_stream,
+            grpc::commands::grpc_stream_send,
+            grpc::commands::grpc_stream_close,
+            grpc::commands::grpc_stream_cancel,
+            grpc::commands::grpc_stream_active,
             grpc::commands::grpc_discover_services,
             grpc::commands::grpc_parse_proto_file,
             grpc::commands::grpc_get_service_info,
             grpc::commands::grpc_get_method_info,
+            grpc::commands::grpc_request_template,
+            grpc::commands::grpc_proto_request_template,
             grpc::commands::grpc_test_connection,
+            grpc::commands::grpc_probe_server,
+            grpc::commands::grpc_refresh_descriptors,
+            grpc::commands::grpc_evict_endpoint,
+            grpc::commands::grpc_export_grpcurl,
+            grpc::commands::grpc_import_grpcurl,
+            grpc::commands::grpc_decode_wire,
+            grpc::commands::grpc_json_to_binary,
+            grpc::commands::grpc_binary_to_json,
+            grpc::commands::grpc_compare_schemas,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
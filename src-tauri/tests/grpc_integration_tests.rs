@@ -1,6 +1,7 @@
 // Integration tests for gRPC functionality
 // These tests require the grpc-test-server to be running on localhost:50051
 
+use solo_lib::client::proxy::ProxySettings;
 use solo_lib::grpc::proto_parser::ProtoParser;
 use solo_lib::grpc::reflection::GrpcReflection;
 use solo_lib::grpc::{GrpcCallType, GrpcRequest};
@@ -51,7 +52,7 @@ message EchoResponse {
 /// Note: This requires the test server to be running
 #[tokio::test]
 async fn test_scenario_2_discover_services() {
-    let reflection = GrpcReflection::new(TEST_SERVER_URL).await;
+    let reflection = GrpcReflection::new(TEST_SERVER_URL, None, &ProxySettings::direct()).await;
 
     if reflection.is_err() {
         println!("⚠️  Skipping: Server not available at {}", TEST_SERVER_URL);
@@ -162,8 +163,8 @@ message User {
     let list_users = &schema.services[0].methods[0];
 
     assert_eq!(list_users.name, "ListUsers");
-    // Note: Our parser needs to detect streaming
-    // This is a known limitation we should fix
+    assert!(!list_users.is_client_streaming);
+    assert!(list_users.is_server_streaming);
 }
 
 /// Test Scenario 5: Error Handling (Invalid Proto)
@@ -173,11 +174,13 @@ async fn test_scenario_5_parse_invalid_proto() {
 
     let result = ProtoParser::parse_proto_file(invalid_proto);
 
-    // Even with invalid syntax, our basic parser doesn't fail
-    // It just returns empty services/messages
-    assert!(result.is_ok());
-    let schema = result.unwrap();
-    assert_eq!(schema.services.len(), 0, "Should have no services");
+    let err = result.expect_err("Invalid syntax should be rejected");
+    assert_eq!(err.error_type(), "ParseError");
+    assert!(
+        err.to_string().contains("input.proto:1:1"),
+        "Error should point at the offending token: {}",
+        err
+    );
 }
 
 /// Test Scenario 6: Multiple Services
@@ -228,6 +231,8 @@ async fn test_scenario_7_grpc_request_structure() {
             message: serde_json::json!({"test": "data"}),
             metadata: Some(HashMap::new()),
             call_type: call_type.clone(),
+            options: Default::default(),
+            transport: Default::default(),
         };
 
         // Validate structure
@@ -270,6 +275,8 @@ message TestResponse { string result = 1; }
         message: serde_json::json!({"id": "123"}),
         metadata: None,
         call_type: GrpcCallType::Unary,
+        options: Default::default(),
+        transport: Default::default(),
     };
 
     // Step 4: Validate request structure
@@ -291,6 +298,8 @@ async fn test_metadata_creation() {
         message: serde_json::json!({"id": "user1"}),
         metadata: Some(metadata.clone()),
         call_type: GrpcCallType::Unary,
+        options: Default::default(),
+        transport: Default::default(),
     };
 
     assert!(request.metadata.is_some());
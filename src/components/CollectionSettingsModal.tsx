@@ -0,0 +1,340 @@
+import { useState } from "react";
+import { useTheme } from "../context/ThemeContext";
+import { X, Settings } from "lucide-react";
+import clsx from "clsx";
+import {
+  HttpClientSettings,
+  HttpVersion,
+  ProxySettings,
+  loadCollectionSettings,
+  saveCollectionSettings,
+} from "../utils/collectionSettings";
+
+interface CollectionSettingsModalProps {
+  folder: string;
+  onClose: () => void;
+}
+
+type NumberSetting =
+  | "timeout_ms"
+  | "connect_timeout_ms"
+  | "read_timeout_ms"
+  | "max_response_bytes";
+
+type DecompressionSetting = "gzip" | "brotli" | "zstd";
+
+export const CollectionSettingsModal = ({
+  folder,
+  onClose,
+}: CollectionSettingsModalProps) => {
+  const { theme } = useTheme();
+  const [tempSettings, setTempSettings] = useState<HttpClientSettings>(
+    () => loadCollectionSettings(folder) ?? {}
+  );
+
+  const inputClassName = clsx(
+    "w-full p-2 border rounded text-sm",
+    theme === "dark"
+      ? "bg-gray-700 border-gray-600 text-white"
+      : "bg-white border-gray-300 text-gray-800"
+  );
+
+  const handleSave = () => {
+    saveCollectionSettings(folder, tempSettings);
+    onClose();
+  };
+
+  const handleNumberChange = (setting: NumberSetting, value: string) => {
+    setTempSettings({
+      ...tempSettings,
+      [setting]: value === "" ? undefined : Number(value),
+    });
+  };
+
+  const proxy = tempSettings.proxy ?? {};
+  // Kept as typed, as splitting on every key press would eat the commas
+  const [bypass, setBypass] = useState(() => proxy.bypass?.join(", ") ?? "");
+
+  const handleProxyChange = (change: ProxySettings) => {
+    // Like the other settings, unset fields fall back to the backend defaults
+    const set = Object.fromEntries(
+      Object.entries({ ...proxy, ...change }).filter(
+        ([, value]) => value !== undefined && value !== ""
+      )
+    );
+    setTempSettings({
+      ...tempSettings,
+      proxy: Object.keys(set).length > 0 ? set : undefined,
+    });
+  };
+
+  const handleBypassChange = (value: string) => {
+    setBypass(value);
+    const hosts = value
+      .split(",")
+      .map((host) => host.trim())
+      .filter((host) => host !== "");
+    handleProxyChange({ bypass: hosts.length > 0 ? hosts : undefined });
+  };
+
+  const followRedirects = tempSettings.redirects?.type !== "never";
+  const maxRedirects =
+    tempSettings.redirects?.type === "follow" ? tempSettings.redirects.max : "";
+
+  const handleRedirectsChange = (follow: boolean, max: string) => {
+    let redirects: HttpClientSettings["redirects"];
+    if (!follow) {
+      redirects = { type: "never" };
+    } else if (max !== "") {
+      redirects = { type: "follow", max: Number(max) };
+    }
+    setTempSettings({ ...tempSettings, redirects });
+  };
+
+  const numberFields: { setting: NumberSetting; label: string; hint: string }[] =
+    [
+      {
+        setting: "timeout_ms",
+        label: "Timeout (ms)",
+        hint: "Whole request, body included. 30000 by default.",
+      },
+      {
+        setting: "connect_timeout_ms",
+        label: "Connect timeout (ms)",
+        hint: "Opening the connection. Unlimited by default.",
+      },
+      {
+        setting: "read_timeout_ms",
+        label: "Read timeout (ms)",
+        hint: "Waiting for the headers, then each read of the body. Unlimited by default.",
+      },
+      {
+        setting: "max_response_bytes",
+        label: "Max response size (bytes)",
+        hint: "Larger responses fail. Unlimited by default.",
+      },
+    ];
+
+  const decompressionFields: { setting: DecompressionSetting; label: string }[] =
+    [
+      { setting: "gzip", label: "gzip" },
+      { setting: "brotli", label: "brotli" },
+      { setting: "zstd", label: "zstd" },
+    ];
+
+  return (
+    <div
+      className="fixed inset-0 bg-black/50 flex items-center justify-center z-50"
+      onClick={(e) => e.stopPropagation()}
+    >
+      <div
+        className={clsx(
+          "p-6 rounded-lg shadow-lg max-w-md w-full mx-4 max-h-[90vh] overflow-y-auto",
+          theme === "dark" ? "bg-gray-800 text-white" : "bg-white text-gray-900"
+        )}
+      >
+        <div className="flex justify-between items-center mb-6">
+          <div className="flex items-center gap-2">
+            <Settings className="w-5 h-5" />
+            <h3 className="text-lg font-medium">Client Settings: {folder}</h3>
+          </div>
+          <button
+            onClick={onClose}
+            className={clsx(
+              "p-1 rounded hover:bg-opacity-10 cursor-pointer transition-colors",
+              theme === "dark"
+                ? "text-gray-300 hover:bg-gray-700"
+                : "text-gray-600 hover:bg-gray-200"
+            )}
+          >
+            <X className="w-5 h-5" />
+          </button>
+        </div>
+
+        <div className="space-y-4">
+          {numberFields.map(({ setting, label, hint }) => (
+            <div key={setting}>
+              <label className="block text-sm font-medium mb-1">{label}</label>
+              <input
+                type="number"
+                min={0}
+                value={tempSettings[setting] ?? ""}
+                onChange={(e) => handleNumberChange(setting, e.target.value)}
+                className={inputClassName}
+              />
+              <div className="text-xs text-gray-500 mt-1">{hint}</div>
+            </div>
+          ))}
+
+          {/* Redirects */}
+          <div>
+            <label className="flex items-center gap-3 cursor-pointer mb-2">
+              <input
+                type="checkbox"
+                checked={followRedirects}
+                onChange={(e) =>
+                  handleRedirectsChange(e.target.checked, String(maxRedirects))
+                }
+                className="text-purple-600"
+              />
+              <div className="text-sm font-medium">Follow redirects</div>
+            </label>
+            {followRedirects && (
+              <input
+                type="number"
+                min={0}
+                placeholder="Up to 10 redirects"
+                value={maxRedirects}
+                onChange={(e) => handleRedirectsChange(true, e.target.value)}
+                className={inputClassName}
+              />
+            )}
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium mb-1">User agent</label>
+            <input
+              type="text"
+              placeholder="Solo-Client/1.0"
+              value={tempSettings.user_agent ?? ""}
+              onChange={(e) =>
+                setTempSettings({
+                  ...tempSettings,
+                  user_agent: e.target.value || undefined,
+                })
+              }
+              className={inputClassName}
+            />
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium mb-1">
+              HTTP version
+            </label>
+            <select
+              value={tempSettings.http_version ?? "auto"}
+              onChange={(e) =>
+                setTempSettings({
+                  ...tempSettings,
+                  http_version: e.target.value as HttpVersion,
+                })
+              }
+              className={inputClassName}
+            >
+              <option value="auto">HTTP/1.1, or HTTP/2 when offered</option>
+              <option value="http1_only">HTTP/1.1 only</option>
+              <option value="http2_prior_knowledge">
+                HTTP/2 (prior knowledge)
+              </option>
+            </select>
+          </div>
+
+          {/* Proxy, for HTTP, GraphQL and gRPC requests alike */}
+          <div>
+            <label className="block text-sm font-medium mb-1">Proxy URL</label>
+            <input
+              type="text"
+              placeholder="http://, https://, socks5:// or socks5h://"
+              value={proxy.url ?? ""}
+              onChange={(e) => handleProxyChange({ url: e.target.value })}
+              className={inputClassName}
+            />
+            <div className="flex gap-2 mt-2">
+              <input
+                type="text"
+                placeholder="Username"
+                value={proxy.username ?? ""}
+                onChange={(e) =>
+                  handleProxyChange({ username: e.target.value })
+                }
+                className={inputClassName}
+              />
+              <input
+                type="password"
+                placeholder="Password"
+                value={proxy.password ?? ""}
+                onChange={(e) =>
+                  handleProxyChange({ password: e.target.value })
+                }
+                className={inputClassName}
+              />
+            </div>
+            <input
+              type="text"
+              placeholder="Bypass: localhost, example.com, 10.0.0.0/8"
+              value={bypass}
+              onChange={(e) => handleBypassChange(e.target.value)}
+              className={clsx(inputClassName, "mt-2")}
+            />
+            <label className="flex items-center gap-3 cursor-pointer mt-2">
+              <input
+                type="checkbox"
+                checked={proxy.use_env !== false}
+                onChange={(e) =>
+                  handleProxyChange({
+                    use_env: e.target.checked ? undefined : false,
+                  })
+                }
+                className="text-purple-600"
+              />
+              <div className="text-sm">
+                Without a URL, use the proxy environment variables
+              </div>
+            </label>
+          </div>
+
+          {/* Decompression, each enabled by default */}
+          <div>
+            <div className="text-sm font-medium mb-2">Decompress responses</div>
+            <div className="flex gap-4">
+              {decompressionFields.map(({ setting, label }) => (
+                <label
+                  key={setting}
+                  className="flex items-center gap-2 cursor-pointer text-sm"
+                >
+                  <input
+                    type="checkbox"
+                    checked={tempSettings[setting] !== false}
+                    onChange={(e) =>
+                      setTempSettings({
+                        ...tempSettings,
+                        [setting]: e.target.checked ? undefined : false,
+                      })
+                    }
+                    className="text-purple-600"
+                  />
+                  {label}
+                </label>
+              ))}
+            </div>
+          </div>
+        </div>
+
+        <div className="flex gap-2 mt-6 pt-4 border-t border-gray-300 dark:border-gray-600">
+          <button
+            onClick={onClose}
+            className={clsx(
+              "px-4 py-2 rounded text-sm cursor-pointer",
+              theme === "dark"
+                ? "bg-gray-700 text-gray-300 hover:bg-gray-600"
+                : "bg-gray-200 text-gray-700 hover:bg-gray-300"
+            )}
+          >
+            Cancel
+          </button>
+          <button
+            onClick={handleSave}
+            className={clsx(
+              "px-4 py-2 rounded text-sm text-white flex-1 cursor-pointer",
+              theme === "dark"
+                ? "bg-purple-700 hover:bg-purple-800"
+                : "bg-purple-600 hover:bg-purple-700"
+            )}
+          >
+            Save Settings
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+};
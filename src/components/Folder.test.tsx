@@ -195,6 +195,38 @@ describe("FolderComponent", () => {
     });
   });
 
+  describe("Client settings", () => {
+    it("should save the client settings of the folder", () => {
+      renderFolder({ isDropdownOpen: true });
+
+      fireEvent.click(screen.getByText("Client Settings"));
+      expect(screen.getByText("Client Settings: Test Folder")).toBeInTheDocument();
+
+      // The first number field is the timeout
+      const [timeout] = screen.getAllByRole("spinbutton");
+      fireEvent.change(timeout, { target: { value: "5000" } });
+      fireEvent.click(screen.getByText("Save Settings"));
+
+      expect(
+        JSON.parse(localStorage.getItem("solo-settings-Test Folder") || "{}")
+      ).toEqual({ timeout_ms: 5000 });
+      expect(screen.queryByText("Client Settings: Test Folder")).toBeNull();
+    });
+
+    it("should load the saved settings", () => {
+      localStorage.setItem(
+        "solo-settings-Test Folder",
+        JSON.stringify({ user_agent: "Tests/1.0", redirects: { type: "never" } })
+      );
+      renderFolder({ isDropdownOpen: true });
+
+      fireEvent.click(screen.getByText("Client Settings"));
+
+      expect(screen.getByDisplayValue("Tests/1.0")).toBeInTheDocument();
+      expect(screen.getByRole("checkbox", { name: /follow redirects/i })).not.toBeChecked();
+    });
+  });
+
   describe("Folder renaming", () => {
     it("should show rename input when rename is clicked", () => {
       renderFolder({ isDropdownOpen: true });
@@ -11,11 +11,13 @@ import {
   Check,
   X,
   CopyPlus,
+  Settings,
 } from "lucide-react";
 import { useTheme } from "../context/ThemeContext";
 import clsx from "clsx";
 import { useState, useEffect, useRef } from "react";
 import { generateCurl } from "../utils/curlGenerator";
+import { CollectionSettingsModal } from "./CollectionSettingsModal";
 
 type FolderProps = {
   folder: string;
@@ -58,6 +60,7 @@ export const FolderComponent = ({
   const [newFileName, setNewFileName] = useState("");
   const [editingFolderName, setEditingFolderName] = useState(false);
   const [newFolderName, setNewFolderName] = useState("");
+  const [showClientSettings, setShowClientSettings] = useState(false);
   const folderInputRef = useRef<HTMLInputElement>(null);
 
   const handleFileDropdownToggle = (fileName: string, e: React.MouseEvent) => {
@@ -325,6 +328,18 @@ export const FolderComponent = ({
               theme === "dark" ? "border-gray-700" : "border-gray-200"
             )}
           ></div>
+          <button
+            onClick={() => setShowClientSettings(true)}
+            className={clsx(
+              "px-4 py-2 text-sm w-full text-left flex items-center cursor-pointer",
+              theme === "dark"
+                ? "text-gray-300 hover:bg-gray-700"
+                : "text-gray-700 hover:bg-gray-100"
+            )}
+          >
+            <Settings className="w-4 h-4 mr-2" />
+            Client Settings
+          </button>
           <button
             onClick={handleFolderRenameClick}
             className={clsx(
@@ -540,6 +555,13 @@ export const FolderComponent = ({
           ))}
         </div>
       )}
+
+      {showClientSettings && (
+        <CollectionSettingsModal
+          folder={folder}
+          onClose={() => setShowClientSettings(false)}
+        />
+      )}
     </div>
   );
 };
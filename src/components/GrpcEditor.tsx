@@ -4,6 +4,7 @@ import clsx from "clsx";
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useToast } from "../hooks/useToast";
+import { currentCollectionSettings } from "../utils/collectionSettings";
 
 interface ProtoSchema {
   services: ProtoService[];
@@ -158,6 +159,7 @@ export const GrpcEditor = () => {
     try {
       const result = (await invoke("grpc_discover_services", {
         url: url,
+        proxy: currentCollectionSettings()?.proxy,
       })) as any;
 
       const schema: ProtoSchema | undefined = result?.services
@@ -201,6 +203,7 @@ export const GrpcEditor = () => {
     try {
       const result = (await invoke("grpc_test_connection", {
         url: url,
+        proxy: currentCollectionSettings()?.proxy,
       })) as { connected: boolean; message: string; latency_ms?: number };
 
       if (result.connected) {
@@ -13,15 +13,21 @@ export const InputMethod = () => {
     url,
     loading,
     requestType,
+    grpcCallType,
     setMethod,
     setUrl,
     handleRequest,
+    cancelRequest,
     resetFields,
   } = useRequest();
   const { replaceVariablesInUrl } = useVariables();
   const { theme } = useTheme();
   const toast = useToast();
 
+  // A running server stream can be stopped with the send button
+  const streaming =
+    loading && requestType === "grpc" && grpcCallType === "server_streaming";
+
   const handleRequestWithVariables = async () => {
     const processedUrl = replaceVariablesInUrl(url);
 
@@ -113,20 +119,26 @@ export const InputMethod = () => {
           <Trash2 className="w-4 h-4" />
         </button>
         <button
-          onClick={handleRequestWithVariables}
-          disabled={loading}
-          title={loading ? "Sending request..." : "Send request"}
+          onClick={streaming ? cancelRequest : handleRequestWithVariables}
+          disabled={loading && !streaming}
+          title={
+            streaming
+              ? "Stop the stream"
+              : loading
+              ? "Sending request..."
+              : "Send request"
+          }
           className={clsx(
             "p-2 h-10 text-white rounded cursor-pointer flex items-center justify-center gap-2",
             loading ? "w-32" : "w-28",
             theme === "dark"
               ? "bg-purple-700 hover:bg-purple-800"
               : "bg-purple-600 hover:bg-purple-700",
-            loading && "opacity-50 cursor-not-allowed"
+            loading && !streaming && "opacity-50 cursor-not-allowed"
           )}
         >
           {loading && <Loader2 className="w-4 h-4 animate-spin" />}
-          {loading ? "Sending..." : "Send"}
+          {streaming ? "Stop" : loading ? "Sending..." : "Send"}
         </button>
       </div>
     </div>
@@ -5,6 +5,7 @@ import clsx from "clsx";
 import { ShortcutsDisplay } from "./ShortcutsDisplay";
 import { useCurlGenerator } from "../hooks/useCurlGenerator";
 import { JsonViewer } from "./JsonViewer";
+import { isHttpResponse, responseContent } from "../utils/httpResponse";
 import { Maximize2, Minimize2, Copy, ChevronDown, Check } from "lucide-react";
 
 type TabType = "response" | "headers" | "timeline";
@@ -88,9 +89,12 @@ export const ResponseView = ({ isRequestCollapsed, onToggleCollapse }: ResponseV
     let textToCopy = "";
 
     if (activeTab === "response" && response) {
-      textToCopy = viewMode === "pretty"
-        ? JSON.stringify(response, null, 2)
-        : JSON.stringify(response);
+      const content = responseContent(response);
+      textToCopy = typeof content === "string"
+        ? content
+        : viewMode === "pretty"
+        ? JSON.stringify(content, null, 2)
+        : JSON.stringify(content);
     } else if (url) {
       textToCopy = generateCurl();
     }
@@ -117,7 +121,9 @@ export const ResponseView = ({ isRequestCollapsed, onToggleCollapse }: ResponseV
     return theme === "dark" ? "text-gray-400" : "text-gray-600";
   };
 
-  const headers = response
+  const headers = isHttpResponse(response)
+    ? response.headers.map(({ name, value }) => ({ key: name, value }))
+    : response
     ? [
         { key: "Content-Type", value: "application/json" },
         { key: "Server", value: "Tauri/1.0" },
@@ -168,7 +174,10 @@ export const ResponseView = ({ isRequestCollapsed, onToggleCollapse }: ResponseV
           return <ShortcutsDisplay />;
         }
 
-        if (viewMode === "raw") {
+        const content = responseContent(response);
+
+        // Text bodies look the same in both modes
+        if (viewMode === "raw" || typeof content === "string") {
           return (
             <div
               className={clsx(
@@ -176,12 +185,12 @@ export const ResponseView = ({ isRequestCollapsed, onToggleCollapse }: ResponseV
                 theme === "dark" ? "text-gray-300" : "text-gray-800"
               )}
             >
-              {JSON.stringify(response)}
+              {typeof content === "string" ? content : JSON.stringify(content)}
             </div>
           );
         }
 
-        return <JsonViewer data={response} />;
+        return <JsonViewer data={content} />;
 
       case "headers":
         return (
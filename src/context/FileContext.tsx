@@ -8,6 +8,10 @@ import {
 import { useRequest, QueryParam, RequestType } from "./RequestContext";
 import { useVariables } from "./VariablesContext";
 import { useToast } from "../hooks/useToast";
+import {
+  COLLECTION_SETTINGS_PREFIX,
+  collectionSettingsKey,
+} from "../utils/collectionSettings";
 
 type RequestData = {
   method: "GET" | "POST" | "PUT" | "DELETE" | "PATCH";
@@ -170,7 +174,11 @@ export const FileProvider = ({ children }: { children: ReactNode }) => {
     const loadedFolders: FolderStructure = {};
     for (let i = 0; i < localStorage.length; i++) {
       const key = localStorage.key(i);
-      if (key && !key.startsWith("solo-variables-")) {
+      if (
+        key &&
+        !key.startsWith("solo-variables-") &&
+        !key.startsWith(COLLECTION_SETTINGS_PREFIX)
+      ) {
         try {
           const files = JSON.parse(
             localStorage.getItem(key) || "[]"
@@ -199,6 +207,7 @@ export const FileProvider = ({ children }: { children: ReactNode }) => {
       delete newFolders[folder];
       localStorage.removeItem(folder);
       localStorage.removeItem(`solo-variables-${folder}`);
+      localStorage.removeItem(collectionSettingsKey(folder));
       return newFolders;
     });
     if (folder === currentFolder) {
@@ -229,6 +238,9 @@ export const FileProvider = ({ children }: { children: ReactNode }) => {
       const oldVariablesData = localStorage.getItem(
         `solo-variables-${oldName}`
       );
+      const oldSettingsData = localStorage.getItem(
+        collectionSettingsKey(oldName)
+      );
 
       if (!oldFolderData) {
         console.error(`Folder '${oldName}' not found`);
@@ -246,6 +258,14 @@ export const FileProvider = ({ children }: { children: ReactNode }) => {
         );
       }
 
+      // Migrate client settings if they exist
+      if (oldSettingsData) {
+        localStorage.setItem(
+          collectionSettingsKey(trimmedNewName),
+          oldSettingsData
+        );
+      }
+
       // Update folders state
       setFolders((prev) => {
         const newFolders = { ...prev };
@@ -273,6 +293,7 @@ export const FileProvider = ({ children }: { children: ReactNode }) => {
       // Clean up old data
       localStorage.removeItem(oldName);
       localStorage.removeItem(`solo-variables-${oldName}`);
+      localStorage.removeItem(collectionSettingsKey(oldName));
 
       console.log(
         `Folder renamed from '${oldName}' to '${trimmedNewName}' successfully`
@@ -526,7 +547,11 @@ export const FileProvider = ({ children }: { children: ReactNode }) => {
   ): { folder: string; data: RequestData; displayName?: string } | null => {
     for (let i = 0; i < localStorage.length; i++) {
       const folderName = localStorage.key(i);
-      if (folderName && !folderName.startsWith("solo-variables-")) {
+      if (
+        folderName &&
+        !folderName.startsWith("solo-variables-") &&
+        !folderName.startsWith(COLLECTION_SETTINGS_PREFIX)
+      ) {
         try {
           const files = JSON.parse(
             localStorage.getItem(folderName) || "[]"
@@ -1,7 +1,17 @@
-import { createContext, useContext, useState, ReactNode } from "react";
+import {
+  createContext,
+  useContext,
+  useEffect,
+  useRef,
+  useState,
+  ReactNode,
+} from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useVariables } from "./VariablesContext";
 import { useToast } from "../hooks/useToast";
+import { currentCollectionSettings } from "../utils/collectionSettings";
+import { responseContent } from "../utils/httpResponse";
 
 export type Tab =
   | "body"
@@ -80,6 +90,7 @@ type RequestContextType = {
   setDescription: (description: string) => void;
   setGrpcSchema: (schema: { services: any[]; messages: any[] }) => void;
   handleRequest: (processedUrl?: string) => Promise<void>;
+  cancelRequest: () => void;
   resetFields: () => void;
   formatJson: () => void;
   formatGraphqlVariables: () => void;
@@ -88,6 +99,101 @@ type RequestContextType = {
 
 const RequestContext = createContext<RequestContextType | undefined>(undefined);
 
+type GrpcStreamMessage = {
+  stream_id: string;
+  sequence: number;
+  data: unknown;
+};
+
+type GrpcStreamEnd = {
+  stream_id: string;
+  [field: string]: unknown;
+};
+
+let nextStreamId = 1;
+
+// Messages kept from a server stream; older ones are dropped past this
+const MAX_STREAM_MESSAGES = 1000;
+// How often a quiet stream is checked for having closed without an end event
+const STREAM_CHECK_MS = 2000;
+
+// Run a server-streaming call, collecting the messages emitted for it until
+// its end event. The stream id is chosen here so that the listeners are in
+// place before the first message. Aborting `signal` cancels the call.
+const collectServerStream = async (
+  args: Record<string, unknown>,
+  signal: AbortSignal
+) => {
+  const streamId = `grpc-stream-ui-${Date.now()}-${nextStreamId++}`;
+  const messages: unknown[] = [];
+  let droppedMessages = 0;
+  let onEnd: (end: GrpcStreamEnd) => void = () => {};
+  let onFail: (error: Error) => void = () => {};
+  const ended = new Promise<GrpcStreamEnd>((resolve, reject) => {
+    onEnd = resolve;
+    onFail = reject;
+  });
+  // Only awaited once the call started, which may fail first
+  ended.catch(() => {});
+
+  const unlistenMessage = await listen<GrpcStreamMessage>(
+    "grpc-stream-message",
+    (event) => {
+      if (event.payload.stream_id === streamId) {
+        messages.push(event.payload.data);
+        if (messages.length > MAX_STREAM_MESSAGES) {
+          messages.shift();
+          droppedMessages++;
+        }
+      }
+    }
+  );
+  const unlistenEnd = await listen<GrpcStreamEnd>("grpc-stream-end", (event) => {
+    if (event.payload.stream_id === streamId) {
+      onEnd(event.payload);
+    }
+  });
+
+  const cancel = () => {
+    invoke("grpc_stream_cancel", { streamId }).catch(() => {});
+    onFail(new Error("Stream cancelled"));
+  };
+  signal.addEventListener("abort", cancel);
+
+  // The end event may still be on its way when the stream is first seen
+  // inactive, so it only counts as missing on the next check
+  let seenInactive = false;
+  const check = setInterval(async () => {
+    const active = await invoke<boolean>("grpc_stream_active", {
+      streamId,
+    }).catch(() => false);
+    if (active) {
+      seenInactive = false;
+    } else if (seenInactive) {
+      onFail(new Error("Stream closed without a status"));
+    } else {
+      seenInactive = true;
+    }
+  }, STREAM_CHECK_MS);
+
+  try {
+    if (signal.aborted) throw new Error("Stream cancelled");
+    await invoke("grpc_server_streaming_request", { ...args, streamId });
+    const end = await ended;
+    // Same shape as a unary response, with the messages kept as data
+    return {
+      ...end,
+      data: messages,
+      ...(droppedMessages > 0 ? { dropped_messages: droppedMessages } : {}),
+    };
+  } finally {
+    clearInterval(check);
+    signal.removeEventListener("abort", cancel);
+    unlistenMessage();
+    unlistenEnd();
+  }
+};
+
 export const RequestProvider = ({ children }: { children: ReactNode }) => {
   const [method, setMethod] = useState<HttpMethod>("POST");
   const [url, setUrl] = useState("");
@@ -126,6 +232,12 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
   const { clearVariables } = useVariables();
   const toast = useToast();
 
+  // Cancels the running server stream, if any
+  const streamAbort = useRef<AbortController | null>(null);
+  useEffect(() => () => streamAbort.current?.abort(), []);
+
+  const cancelRequest = () => streamAbort.current?.abort();
+
   const resetFields = () => {
     setMethod(requestType === "graphql" ? "POST" : "GET");
     setUrl("");
@@ -198,6 +310,7 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
         const variables = graphqlVariables.trim()
           ? JSON.parse(graphqlVariables)
           : {};
+        const settings = currentCollectionSettings();
 
         if (useBasicAuth) {
           result = await invoke("graphql_basic_auth_request", {
@@ -206,6 +319,7 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
             variables,
             username,
             password,
+            settings,
           });
         } else if (bearerToken.trim()) {
           result = await invoke("graphql_bearer_auth_request", {
@@ -213,12 +327,14 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
             query: graphqlQuery,
             variables,
             bearerToken,
+            settings,
           });
         } else {
           result = await invoke("graphql_request", {
             url: finalUrl,
             query: graphqlQuery,
             variables,
+            settings,
           });
         }
       } else if (requestType === "grpc") {
@@ -250,6 +366,7 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
           ...(customMetadata || {}),
           ...(authMetadata || {}),
         } as Record<string, string> | undefined;
+        const proxy = currentCollectionSettings()?.proxy;
 
         if (grpcCallType === "unary") {
           result = await invoke("grpc_unary_request", {
@@ -260,17 +377,28 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
             metadata: Object.keys(mergedMetadata || {}).length
               ? mergedMetadata
               : undefined,
+            proxy,
           });
         } else if (grpcCallType === "server_streaming") {
-          result = await invoke("grpc_server_streaming_request", {
-            url: finalUrl,
-            service: grpcService,
-            method: grpcMethod,
-            message,
-            metadata: Object.keys(mergedMetadata || {}).length
-              ? mergedMetadata
-              : undefined,
-          });
+          const abort = new AbortController();
+          streamAbort.current = abort;
+          try {
+            result = await collectServerStream(
+              {
+                url: finalUrl,
+                service: grpcService,
+                method: grpcMethod,
+                message,
+                metadata: Object.keys(mergedMetadata || {}).length
+                  ? mergedMetadata
+                  : undefined,
+                proxy,
+              },
+              abort.signal
+            );
+          } finally {
+            if (streamAbort.current === abort) streamAbort.current = null;
+          }
         } else {
           // For now, handle other streaming types as unary
           result = await invoke("grpc_unary_request", {
@@ -281,33 +409,29 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
             metadata: Object.keys(mergedMetadata || {}).length
               ? mergedMetadata
               : undefined,
+            proxy,
           });
         }
       } else {
         const body = payload.trim() ? JSON.parse(payload) : null;
 
+        let auth: Record<string, string> = { type: "none" };
         if (useBasicAuth) {
-          result = await invoke("basic_auth_request", {
-            method,
-            url: finalUrl,
-            body,
-            username,
-            password,
-          });
+          auth = { type: "basic", username, password };
         } else if (bearerToken.trim()) {
-          result = await invoke("bearer_auth_request", {
-            method,
-            url: finalUrl,
-            body,
-            bearerToken,
-          });
-        } else {
-          result = await invoke("plain_request", {
+          auth = { type: "bearer", token: bearerToken };
+        }
+
+        result = await invoke("http_request", {
+          request: {
             method,
             url: finalUrl,
-            body,
-          });
-        }
+            body:
+              body === null ? { type: "none" } : { type: "json", value: body },
+            auth,
+          },
+          settings: currentCollectionSettings(),
+        });
       }
 
       const endTime = performance.now();
@@ -334,7 +458,11 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
       setStatusCode(null);
       setResponse(null);
       setError(
-        error instanceof Error ? error.message : "An unknown error occurred"
+        error instanceof Error
+          ? error.message
+          : typeof error === "string"
+          ? error
+          : "An unknown error occurred"
       );
     } finally {
       setLoading(false);
@@ -342,7 +470,10 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
   };
 
   const handleCopyResponse = () => {
-    navigator.clipboard.writeText(JSON.stringify(response, null, 2));
+    const content = responseContent(response);
+    navigator.clipboard.writeText(
+      typeof content === "string" ? content : JSON.stringify(content, null, 2)
+    );
     setIsCopied(true);
   };
 
@@ -396,6 +527,7 @@ export const RequestProvider = ({ children }: { children: ReactNode }) => {
         setDescription,
         setGrpcSchema,
         handleRequest,
+        cancelRequest,
         resetFields,
         formatJson,
         formatGraphqlVariables,
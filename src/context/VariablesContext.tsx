@@ -1,4 +1,5 @@
 import { createContext, useContext, useState, ReactNode, useEffect, useCallback } from "react";
+import { COLLECTION_SETTINGS_PREFIX } from "../utils/collectionSettings";
 
 export type Variable = {
   key: string;
@@ -71,7 +72,12 @@ function VariablesProvider({ children }: VariablesProviderProps) {
     // Fallback: scan all folders to find any with files
     for (let i = 0; i < localStorage.length; i++) {
       const key = localStorage.key(i);
-      if (key && !key.startsWith('solo-variables-') && !key.startsWith('update-')) {
+      if (
+        key &&
+        !key.startsWith('solo-variables-') &&
+        !key.startsWith(COLLECTION_SETTINGS_PREFIX) &&
+        !key.startsWith('update-')
+      ) {
         try {
           const files = JSON.parse(localStorage.getItem(key) || "[]");
           if (files.length > 0) {
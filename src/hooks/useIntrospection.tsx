@@ -1,5 +1,6 @@
 import { useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { currentCollectionSettings } from "../utils/collectionSettings";
 
 export interface GraphQLType {
   kind: string;
@@ -71,6 +72,7 @@ export const useIntrospection = () => {
 
     try {
       let result: any;
+      const settings = currentCollectionSettings();
 
       if (useBasicAuth && username) {
         result = await invoke("graphql_introspection_with_auth", {
@@ -78,15 +80,17 @@ export const useIntrospection = () => {
           authType: "basic",
           username,
           password: password || "",
+          settings,
         });
       } else if (bearerToken.trim()) {
         result = await invoke("graphql_introspection_with_auth", {
           url,
           authType: "bearer",
           token: bearerToken,
+          settings,
         });
       } else {
-        result = await invoke("graphql_introspection", { url });
+        result = await invoke("graphql_introspection", { url, settings });
       }
 
       if (result.success && result.data?.data?.__schema) {
@@ -0,0 +1,75 @@
+// HTTP client settings of a collection (folder), stored next to its
+// variables and sent with every HTTP and GraphQL request made from it. Its
+// proxy also carries the gRPC calls.
+// The shape mirrors `HttpClientSettings` in the backend; settings left out
+// use the backend defaults and a request can still override them.
+
+export type RedirectPolicy = { type: "follow"; max: number } | { type: "never" };
+
+export type HttpVersion = "auto" | "http1_only" | "http2_prior_knowledge";
+
+// Mirrors `ProxySettings` in the backend
+export interface ProxySettings {
+  url?: string;
+  username?: string;
+  password?: string;
+  bypass?: string[];
+  use_env?: boolean;
+}
+
+export interface HttpClientSettings {
+  connect_timeout_ms?: number;
+  read_timeout_ms?: number;
+  timeout_ms?: number;
+  redirects?: RedirectPolicy;
+  user_agent?: string;
+  default_headers?: { name: string; value: string }[];
+  http_version?: HttpVersion;
+  gzip?: boolean;
+  brotli?: boolean;
+  zstd?: boolean;
+  max_response_bytes?: number;
+  proxy?: ProxySettings;
+}
+
+export const COLLECTION_SETTINGS_PREFIX = "solo-settings-";
+
+export const collectionSettingsKey = (folder: string) =>
+  `${COLLECTION_SETTINGS_PREFIX}${folder}`;
+
+export const loadCollectionSettings = (
+  folder: string
+): HttpClientSettings | undefined => {
+  const saved = localStorage.getItem(collectionSettingsKey(folder));
+  if (!saved) return undefined;
+
+  try {
+    return JSON.parse(saved) as HttpClientSettings;
+  } catch {
+    return undefined;
+  }
+};
+
+export const saveCollectionSettings = (
+  folder: string,
+  settings: HttpClientSettings
+) => {
+  // Unset fields are dropped so the backend defaults apply
+  const set = Object.fromEntries(
+    Object.entries(settings).filter(
+      ([, value]) => value !== undefined && value !== ""
+    )
+  );
+
+  if (Object.keys(set).length === 0) {
+    localStorage.removeItem(collectionSettingsKey(folder));
+  } else {
+    localStorage.setItem(collectionSettingsKey(folder), JSON.stringify(set));
+  }
+};
+
+// Settings of the collection holding the current request, if any
+export const currentCollectionSettings = (): HttpClientSettings | undefined => {
+  const currentFolder = sessionStorage.getItem("current-request-folder");
+  return currentFolder ? loadCollectionSettings(currentFolder) : undefined;
+};
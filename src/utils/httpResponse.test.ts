@@ -0,0 +1,63 @@
+import { describe, expect, it } from "vitest";
+import { HttpResponse, HttpResponseBody, responseContent } from "./httpResponse";
+
+const httpResponse = (
+  body: HttpResponseBody,
+  raw_body: string | null = null
+): HttpResponse => ({
+  success: true,
+  status: 200,
+  status_text: "OK",
+  http_version: "HTTP/1.1",
+  url: "https://api.example.com/users",
+  headers: [],
+  body,
+  raw_body,
+  size_bytes: 8,
+  timings: {
+    dns_ms: null,
+    connect_ms: null,
+    tls_ms: null,
+    ttfb_ms: 1,
+    download_ms: 1,
+    total_ms: 2,
+  },
+});
+
+describe("responseContent", () => {
+  it("should show the value of JSON bodies", () => {
+    const response = httpResponse({ type: "json", value: { id: 1 } });
+
+    expect(responseContent(response)).toEqual({ id: 1 });
+  });
+
+  it("should show text bodies as text", () => {
+    const response = httpResponse({
+      type: "text",
+      text: "<fault>down</fault>",
+      format: "xml",
+      charset: "UTF-8",
+    });
+
+    expect(responseContent(response)).toBe("<fault>down</fault>");
+  });
+
+  it("should describe binary bodies instead of showing their base64", () => {
+    const response = httpResponse(
+      { type: "binary", mime_type: "image/png" },
+      "iVBORw0KGgo="
+    );
+
+    expect(responseContent(response)).toBe("Binary response (image/png, 8 bytes)");
+  });
+
+  it("should show an empty body as empty text", () => {
+    expect(responseContent(httpResponse({ type: "empty" }))).toBe("");
+  });
+
+  it("should keep other responses as they are", () => {
+    const graphql = { success: true, data: { data: { users: [] } }, error: null };
+
+    expect(responseContent(graphql)).toBe(graphql);
+  });
+});
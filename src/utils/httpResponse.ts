@@ -0,0 +1,53 @@
+// Shape of the responses of the `http_request` command
+
+export type HttpResponseBody =
+  | { type: "empty" }
+  | { type: "json"; value: unknown }
+  | { type: "text"; text: string; format: "plain" | "html" | "xml"; charset: string }
+  | { type: "binary"; mime_type: string };
+
+export interface HttpResponse {
+  success: boolean;
+  status: number;
+  status_text: string | null;
+  http_version: string;
+  url: string;
+  headers: { name: string; value: string }[];
+  body: HttpResponseBody;
+  // Base64 of the body as received, null when empty
+  raw_body: string | null;
+  size_bytes: number;
+  timings: {
+    dns_ms: number | null;
+    connect_ms: number | null;
+    tls_ms: number | null;
+    ttfb_ms: number;
+    download_ms: number;
+    total_ms: number;
+  };
+}
+
+export const isHttpResponse = (response: unknown): response is HttpResponse =>
+  typeof response === "object" &&
+  response !== null &&
+  "body" in response &&
+  "raw_body" in response &&
+  "timings" in response;
+
+// What to show and copy for a response: the decoded body of HTTP responses,
+// as text unless it is JSON, and other responses (GraphQL, gRPC) as they are
+export const responseContent = (response: unknown): unknown => {
+  if (!isHttpResponse(response)) return response;
+
+  const { body } = response;
+  switch (body.type) {
+    case "json":
+      return body.value;
+    case "text":
+      return body.text;
+    case "binary":
+      return `Binary response (${body.mime_type}, ${response.size_bytes} bytes)`;
+    case "empty":
+      return "";
+  }
+};